@@ -1,5 +1,4 @@
 use std::{
-    collections::HashMap,
     fmt::{Debug, Display},
     ops::{Deref, DerefMut},
     sync::Arc,
@@ -7,11 +6,14 @@ use std::{
 
 use ashpd::{WindowIdentifier, desktop::file_chooser::FileFilter};
 use burp::{
-    input::geo_zero::{ColumnValueClonable, GraphWriter},
+    events::Events,
+    input::{filters, geo_zero::GraphWriter},
     oracle::{
         DefaultOracleParams, MinSplitParams, MinimalSplitStrategy, PoiGraph, SimpleSplitStrategy,
         block_pair::BlockPair, oracle::Oracle,
     },
+    output::path_to_geojson,
+    pipeline,
     tree::Tree,
     types::{CoordNode, Poi},
 };
@@ -24,14 +26,14 @@ use galileo::{
 };
 use galileo_types::{
     cartesian::{NewCartesianPoint2d, Point2},
-    geo::{Crs, GeoPoint},
+    geo::{Crs, GeoPoint, impls::GeoPoint2d},
 };
 use geo::Coord;
-use geozero::geojson::read_geojson;
 use graph_rs::{
     CoordGraph, Graph,
     graph::{csr::DirectedCsrGraph, rstar::RTreeGraph},
 };
+use id_tree::Node;
 use log::info;
 use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
@@ -47,15 +49,20 @@ use crate::{
         layers::{
             block_pair_layer::BlockPairLayer,
             line_layer::ContourLayer,
-            node_layer::{NodeLayer, NodeMarker, NodeSymbol},
+            measure_layer::MeasureLayer,
+            node_layer::{NodeLayer, NodeSymbol},
         },
+        symbols::AmenitySymbol,
     },
     widgets::{self, TreeView},
 };
 
 mod app_data;
 
-pub use app_data::AppData;
+pub use app_data::{AppData, Viewport};
+
+/// Import progress is sent every this many features, so it does not flood the event channel.
+const IMPORT_PROGRESS_INTERVAL: u64 = 1000;
 
 #[derive(PartialEq, Clone, Copy)]
 enum SplitStrategy {
@@ -84,8 +91,10 @@ pub struct BurpApp {
     event_handler: EventHandler,
     sender: tokio::sync::mpsc::Sender<Event>,
     build_oracle: bool,
+    measure: bool,
     split_strategy: SplitStrategy,
     merge_blocks: bool,
+    show_block_pairs: bool,
 }
 
 impl BurpApp {
@@ -96,7 +105,7 @@ impl BurpApp {
         TextService::initialize(rasterizer).load_fonts("/home/jakob/.nix-profile/share/fonts");
 
         log::debug!("Initialising map state");
-        let map_state = EguiMapState::new(
+        let mut map_state = EguiMapState::new(
             cc.egui_ctx.clone(),
             egui::Id::new("galileo_map"),
             cc.wgpu_render_state
@@ -112,6 +121,14 @@ impl BurpApp {
 
         let data = AppData::load_from_path(eframe::storage_dir(app_id.as_str()).unwrap());
 
+        if let Some(viewport) = data.viewport {
+            log::debug!("Restoring viewport {viewport:?}");
+            map_state.map.map_mut().set_view(galileo::MapView::new(
+                &GeoPoint2d::latlon(viewport.lat, viewport.lon),
+                viewport.resolution,
+            ));
+        }
+
         Self {
             app_id,
             map: map_state,
@@ -120,8 +137,10 @@ impl BurpApp {
             event_handler,
             sender,
             build_oracle: false,
+            measure: false,
             split_strategy: SplitStrategy::SimpleSplitStrategy,
             merge_blocks: true,
+            show_block_pairs: false,
         }
     }
 
@@ -135,6 +154,32 @@ impl eframe::App for BurpApp {
     fn save(&mut self, _storage: &mut dyn eframe::Storage) {
         let storage_dir = eframe::storage_dir(self.app_id.as_str());
 
+        let view = self.map.map.map().view();
+        if let Some(position) = view.position() {
+            let viewport = Viewport::new(position.lat(), position.lon(), view.resolution());
+            if self.data.viewport != Some(viewport) {
+                self.data.viewport = Some(viewport);
+
+                self.runtime.spawn_blocking({
+                    let storage_dir = storage_dir.clone();
+                    move || {
+                        tracing::info_span!("viewport").in_scope(|| {
+                            let mut file_path = storage_dir.expect("Not supportet on Android/iOS");
+                            file_path.push("viewport.vmp");
+
+                            let mut file =
+                                std::io::BufWriter::new(std::fs::File::create(file_path).unwrap());
+
+                            match rmp_serde::encode::write(&mut file, &viewport) {
+                                Ok(_) => tracing::info!("Saved viewport"),
+                                Err(err) => tracing::error!("Failed to save viewport: {err}"),
+                            }
+                        })
+                    }
+                });
+            }
+        }
+
         let data = self.data.clone();
 
         if let Some(graph) = data.graph
@@ -150,12 +195,14 @@ impl eframe::App for BurpApp {
                         let mut file =
                             std::io::BufWriter::new(std::fs::File::create(file_path).unwrap());
 
-                        match rmp_serde::encode::write(&mut file, graph.read().deref()) {
+                        let mut graph = graph.write();
+
+                        match rmp_serde::encode::write(&mut file, graph.deref()) {
                             Ok(_) => tracing::info!("Saved graph"),
                             Err(err) => tracing::error!("Failed to save graph: {err}"),
                         }
 
-                        graph.write().set_clean();
+                        graph.set_clean();
                     })
                 }
             });
@@ -176,7 +223,7 @@ impl eframe::App for BurpApp {
 
                         let mut oracle = oracle.lock();
 
-                        match rmp_serde::encode::write(&mut file, oracle.deref()) {
+                        match oracle.write_to(&mut file) {
                             Ok(_) => tracing::info!("Saved oracle"),
                             Err(err) => tracing::error!("Failed to save oracle: {err}"),
                         }
@@ -200,12 +247,14 @@ impl eframe::App for BurpApp {
                         let mut file =
                             std::io::BufWriter::new(std::fs::File::create(file_path).unwrap());
 
-                        match rmp_serde::encode::write(&mut file, split_tree.read().deref()) {
+                        let mut split_tree = split_tree.write();
+
+                        match rmp_serde::encode::write(&mut file, split_tree.deref()) {
                             Ok(_) => tracing::info!("Saved split_tree"),
                             Err(err) => tracing::error!("Failed to save split_tree: {err}"),
                         }
 
-                        split_tree.write().set_clean();
+                        split_tree.set_clean();
                     })
                 }
             });
@@ -248,86 +297,57 @@ impl eframe::App for BurpApp {
                         frame,
                         &self.runtime,
                         self.sender.clone(),
-                        |path| {
-                            let file_type = path
-                                .extension()
-                                .unwrap()
-                                .to_str()
-                                .expect("Could not convert OsStr to str");
-                            let file = std::fs::File::open(path).unwrap();
-                            let buf_reader = std::io::BufReader::new(file);
-
-                            match file_type {
-                                "geojson" => {
-                                    let filter = |p: &HashMap<String, ColumnValueClonable>| {
-                                        let footway = p.get("footway");
-                                        let highway = p.get("highway");
-
-                                        match highway {
-                                            None => return false,
-                                            Some(ColumnValueClonable::String(s)) if s == "null" => {
-                                                return false;
-                                            }
-                                            Some(ColumnValueClonable::String(s))
-                                                if s == "cycleway" =>
-                                            {
-                                                return false;
+                        {
+                            let sender = self.sender.clone();
+                            move |path| {
+                                let file_type = path
+                                    .extension()
+                                    .unwrap()
+                                    .to_str()
+                                    .expect("Could not convert OsStr to str");
+                                let file = std::fs::File::open(path).unwrap();
+                                let buf_reader = std::io::BufReader::new(file);
+
+                                match file_type {
+                                    "geojson" => {
+                                        let sender = sender.clone();
+                                        let progress = move |features: u64| {
+                                            if features % IMPORT_PROGRESS_INTERVAL == 0 {
+                                                let _ = sender.try_send(Event::Progress(
+                                                    Events::ImportProgress { features },
+                                                ));
                                             }
-                                            Some(ColumnValueClonable::String(s)) if s == "path" => {
-                                                return false;
-                                            }
-                                            Some(ColumnValueClonable::String(s))
-                                                if s == "footway" =>
-                                            {
-                                                return false;
-                                            }
-                                            Some(ColumnValueClonable::String(s))
-                                                if s == "steps" =>
-                                            {
-                                                return false;
-                                            }
-                                            Some(ColumnValueClonable::String(s))
-                                                if s == "corridor" =>
-                                            {
-                                                return false;
-                                            }
-                                            _ => (),
-                                        }
+                                        };
+                                        let mut graph_writer =
+                                            GraphWriter::new(filters::road_network())
+                                                .with_progress(progress);
 
-                                        match footway {
-                                            None => true,
-                                            Some(ColumnValueClonable::String(s)) => s == "null",
-                                            _ => false,
-                                        }
-                                    };
-                                    let mut graph_writer = GraphWriter::new(filter);
-
-                                    read_geojson(buf_reader, &mut graph_writer)
-                                        .expect("Failed to parse geojson");
-
-                                    let graph =
-                                        RTreeGraph::new_from_graph(graph_writer.get_graph());
+                                        pipeline::read_geojson_into(buf_reader, &mut graph_writer)
+                                            .expect("Failed to parse geojson");
 
-                                    Some(Event::GraphLoaded(PoiGraph::new(graph)))
-                                }
-                                "gmp" => {
-                                    let graph: PoiGraph<Poi> =
-                                        rmp_serde::from_read(buf_reader).unwrap();
+                                        Some(Event::GraphLoaded(pipeline::build_graph(
+                                            graph_writer,
+                                        )))
+                                    }
+                                    "gmp" => {
+                                        let graph: PoiGraph<Poi> =
+                                            rmp_serde::from_read(buf_reader).unwrap();
 
-                                    Some(Event::GraphLoaded(graph))
-                                }
-                                "omp" => {
-                                    let oracle: Oracle<f64, f64> =
-                                        rmp_serde::from_read(buf_reader).unwrap();
+                                        Some(Event::GraphLoaded(graph))
+                                    }
+                                    "omp" => {
+                                        let oracle: Oracle<f64, f64> =
+                                            rmp_serde::from_read(buf_reader).unwrap();
 
-                                    Some(Event::OracleLoaded(oracle))
-                                }
-                                "smp" => {
-                                    let split_tree = rmp_serde::from_read(buf_reader).unwrap();
+                                        Some(Event::OracleLoaded(oracle))
+                                    }
+                                    "smp" => {
+                                        let split_tree = rmp_serde::from_read(buf_reader).unwrap();
 
-                                    Some(Event::SplitTreeLoaded(split_tree))
+                                        Some(Event::SplitTreeLoaded(split_tree))
+                                    }
+                                    _ => None,
                                 }
-                                _ => None,
                             }
                         },
                     ));
@@ -335,6 +355,10 @@ impl eframe::App for BurpApp {
             });
         });
         egui::SidePanel::right("Right Panel").show(ctx, |ui| {
+            if let Some(progress) = &self.data.progress {
+                ui.label(progress.to_string());
+            }
+
             if ui
                 .add_enabled(self.data.graph.is_some(), egui::Button::new("Show Graph"))
                 .clicked()
@@ -342,6 +366,7 @@ impl eframe::App for BurpApp {
                 if self.data.graph.as_ref().unwrap().read().is_dirty() {
                     self.map.map.remove("graph");
                     self.map.map.remove("nodes");
+                    self.map.map.remove("pois");
                 }
 
                 let _ = self.map.map.toggle_layer(&String::from("graph")).or_else(
@@ -386,7 +411,7 @@ impl eframe::App for BurpApp {
                             .downcast_mut()
                             .ok_or("Couldn't downcast layer".to_string())?;
 
-                        layer.write().insert_nodes(
+                        layer.write().insert_coords(
                             self.data
                                 .graph
                                 .as_ref()
@@ -394,16 +419,40 @@ impl eframe::App for BurpApp {
                                 .read()
                                 .graph()
                                 .nodes_iter()
-                                .map(|node| {
-                                    NodeMarker::new(*node.1.get_coord(), node.0, None)
-                                        .expect("could not project point")
-                                })
-                                .collect(),
+                                .map(|node| (*node.1.get_coord(), node.0, None)),
                         );
 
                         Ok(())
                     },
                 );
+
+                let _ = self.map.map.toggle_layer(&String::from("pois")).or_else(
+                    |_| -> Result<(), String> {
+                        let layer: &mut Arc<RwLock<NodeLayer<AmenitySymbol, Poi>>> = self
+                            .map
+                            .map
+                            .or_insert(
+                                "pois".to_string(),
+                                NodeLayer::<AmenitySymbol, Poi>::new(
+                                    AmenitySymbol::new(6.0),
+                                    Crs::WGS84,
+                                ),
+                            )
+                            .as_any_mut()
+                            .downcast_mut()
+                            .ok_or("Couldn't downcast layer".to_string())?;
+
+                        let graph = self.data.graph.as_ref().unwrap().read();
+                        layer
+                            .write()
+                            .insert_coords(graph.poi_nodes().iter().filter_map(|poi| {
+                                let value = graph.graph().node_value(*poi)?;
+                                Some((*value.get_coord(), *poi, Some(value.data().clone())))
+                            }));
+
+                        Ok(())
+                    },
+                );
                 self.map.map.redraw();
             }
 
@@ -416,6 +465,39 @@ impl eframe::App for BurpApp {
 
             ui.toggle_value(&mut self.merge_blocks, "Merge blocks");
 
+            if ui.toggle_value(&mut self.measure, "Measure").changed() {
+                let _ = self.map.map.toggle_layer(&String::from("measure"));
+            }
+
+            let secondary_clicked = self.map.secondary_clicked();
+            if self.measure
+                && (self.map.clicked() || secondary_clicked)
+                && let Some(map_interact_pos) = self.map.map_interact_pos()
+            {
+                error_modal.handle_error(ui, |ui| {
+                    let layer: &mut Arc<RwLock<MeasureLayer>> = self
+                        .map
+                        .map
+                        .or_insert("measure".to_string(), MeasureLayer::new(Crs::WGS84))
+                        .as_any_mut()
+                        .downcast_mut()
+                        .ok_or(ErrorMsg("Couldn't downcast layer"))?;
+
+                    if secondary_clicked {
+                        layer.write().clear();
+                    } else {
+                        let graph = self.data.graph.as_ref().map(|graph| graph.read());
+                        layer.write().click(
+                            Coord::new(map_interact_pos.lon(), map_interact_pos.lat()),
+                            graph.as_ref().map(|graph| graph.deref().deref()),
+                        );
+                    }
+
+                    Ok(())
+                });
+                self.map.map.redraw();
+            }
+
             egui::ComboBox::from_label("Split stragety")
                 .selected_text(format!("{}", self.split_strategy))
                 .show_ui(ui, |ui| {
@@ -462,34 +544,44 @@ impl eframe::App for BurpApp {
                         let merge_blocks = self.merge_blocks;
                         move || {
                             let graph = graph.read();
-                            let oracle = match split_strategy {
+                            let result = match split_strategy {
                                 SplitStrategy::SimpleSplitStrategy => Oracle::build_for_node(
                                     node,
                                     0.25,
                                     graph.graph(),
                                     DefaultOracleParams {
                                         merge_blocks: merge_blocks,
+                                        max_depth: None,
+                                        store_paths: false,
+                                        parallel: true,
                                     },
-                                )
-                                .unwrap(),
+                                ),
                                 SplitStrategy::MinimalSplitStrategy => Oracle::build_for_node(
                                     node,
                                     0.25,
                                     graph.graph(),
                                     MinSplitParams {
                                         merge_blocks: merge_blocks,
+                                        max_depth: None,
+                                        store_paths: false,
+                                        parallel: true,
                                     },
-                                )
-                                .unwrap(),
+                                ),
                             };
 
-                            sender
-                                .try_send(Event::OracleBuild(oracle.0, oracle.1))
-                                .unwrap();
+                            let event = match result {
+                                Ok((oracle, split_tree)) => Event::OracleBuild(oracle, split_tree),
+                                Err(err) => Event::OracleBuildFailed(err),
+                            };
+                            sender.try_send(event).unwrap();
                         }
                     });
                 }
             }
+
+            if let Some(err) = self.data.oracle_error.take() {
+                error_modal.handle_error(ui, |_| Err::<(), _>(err.into()));
+            }
         });
         egui::CentralPanel::default()
             .frame(Frame::new().inner_margin(0).outer_margin(0))
@@ -513,6 +605,70 @@ impl eframe::App for BurpApp {
                             },
                         )
                     });
+                if self.measure {
+                    let measure_layer =
+                        self.map
+                            .map
+                            .get_layer(&String::from("measure"))
+                            .and_then(|layer| {
+                                layer
+                                    .as_any()
+                                    .downcast_ref::<Arc<RwLock<MeasureLayer>>>()
+                                    .cloned()
+                            });
+                    let measurement = measure_layer
+                        .as_ref()
+                        .and_then(|layer| layer.read().measurement());
+
+                    egui::Window::new("Measure")
+                        .collapsible(false)
+                        .anchor(egui::Align2::LEFT_BOTTOM, [5., -5.])
+                        .constrain_to(ui.max_rect())
+                        .auto_sized()
+                        .show(ctx, |ui| match measurement {
+                            Some(measurement) => {
+                                ui.label(format!("Haversine: {:.1} m", measurement.haversine));
+                                ui.label(match measurement.routed {
+                                    Some(routed) => format!("Routed: {routed:.1}"),
+                                    None => "Routed: -".to_string(),
+                                });
+
+                                if let Some(measure_layer) = measure_layer
+                                    && let Some(graph) = self.data.graph.clone()
+                                    && measurement.routed.is_some()
+                                {
+                                    ui.add(widgets::SaveFile::new(
+                                        "Save route",
+                                        "route.geojson",
+                                        vec![FileFilter::new("geojson").glob("*.geojson")],
+                                        frame,
+                                        &self.runtime,
+                                        move |path| {
+                                            let Some(geojson) =
+                                                measure_layer.read().route().and_then(|route| {
+                                                    path_to_geojson(route, graph.read().graph())
+                                                })
+                                            else {
+                                                tracing::error!("Failed to convert route");
+                                                return;
+                                            };
+
+                                            match std::fs::write(path, geojson) {
+                                                Ok(_) => tracing::info!("Saved route"),
+                                                Err(err) => {
+                                                    tracing::error!("Failed to save route: {err}")
+                                                }
+                                            }
+                                        },
+                                    ));
+                                }
+                            }
+                            None => {
+                                ui.label("Click two points on the map");
+                            }
+                        });
+                }
+
                 if self.build_oracle {
                     egui::Window::new("Build Oracle")
                         .collapsible(false)
@@ -528,6 +684,16 @@ impl eframe::App for BurpApp {
                 if self.data.split_tree.is_some() || self.data.oracle.is_some() {
                     egui::Window::new("Oracle").show(ctx, |ui| {
                         if let Some(oracles) = self.data.oracle.as_ref() {
+                            // `Some(None)` shows all pois, `Some(Some(poi))` only one.
+                            let mut show = None;
+                            if ui
+                                .checkbox(&mut self.show_block_pairs, "Show all block pairs")
+                                .changed()
+                            {
+                                show = Some(None);
+                            }
+                            ui.separator();
+
                             let mut delete = Vec::new();
                             for oracle in oracles.lock().iter() {
                                 ui.label(format!("Poi: {}", oracle.0));
@@ -538,6 +704,9 @@ impl eframe::App for BurpApp {
                                         oracle.1.avg_block_ocupancy(graph.read().deref().graph())
                                     ));
                                 }
+                                if ui.button("Show block pairs").clicked() {
+                                    show = Some(Some(*oracle.0));
+                                }
                                 if ui.button("Delete").clicked() {
                                     delete.push(*oracle.0);
                                 }
@@ -547,14 +716,54 @@ impl eframe::App for BurpApp {
                             for del in delete {
                                 oracles.lock().remove(&del);
                             }
+
+                            if let Some(poi) = show {
+                                error_modal.handle_error(ui, |ui| {
+                                    let layer: &mut Arc<RwLock<BlockPairLayer<f64>>> = self
+                                        .map
+                                        .map
+                                        .or_insert(
+                                            "block_pair".to_string(),
+                                            BlockPairLayer::new(Crs::WGS84),
+                                        )
+                                        .as_any_mut()
+                                        .downcast_mut()
+                                        .ok_or(ErrorMsg("Couldn't downcast layer"))?;
+
+                                    let mut layer = layer.write();
+                                    layer.clear_oracles();
+                                    if self.show_block_pairs || poi.is_some() {
+                                        for oracle in oracles.lock().iter() {
+                                            if poi.is_none_or(|poi| poi == *oracle.0) {
+                                                layer.show_oracle(oracle.1);
+                                            }
+                                        }
+                                    }
+                                    Ok(())
+                                });
+                                self.map.map.redraw();
+                            }
                         }
 
                         if let Some(split_trees) = self.data.split_tree.as_ref() {
                             ui.heading("Split Trees");
+                            // The block pair shown on the map is marked in its split tree.
+                            let selected = self
+                                .map
+                                .map
+                                .get_layer(&String::from("block_pair"))
+                                .and_then(|layer| {
+                                    layer
+                                        .as_any()
+                                        .downcast_ref::<Arc<RwLock<BlockPairLayer<f64>>>>()
+                                        .and_then(|layer| layer.read().selected().cloned())
+                                });
                             let mut delete = Vec::new();
                             for split_tree in split_trees.read().iter() {
                                 ui.collapsing(split_tree.0.to_string(), |ui| {
-                                    ui.add(widgets::TreeView::new(split_tree.1, |ui, node, id| {
+                                    let node_ui = |ui: &mut egui::Ui,
+                                                   node: &Node<(BlockPair<f64, f64>, bool)>,
+                                                   id: (usize, usize)| {
                                         if node.data().1 {
                                             ui.visuals_mut().widgets.noninteractive.fg_stroke =
                                                 egui::Stroke::new(1., egui::Color32::GREEN);
@@ -603,7 +812,16 @@ impl eframe::App for BurpApp {
                                             egui::CollapsingHeader::new("Values").id_salt((id, 0));
                                         values
                                             .show(ui, |ui| ui.label(format!("{}", node.data().0)));
-                                    }));
+                                    };
+                                    ui.add(
+                                        widgets::TreeView::new(split_tree.1, node_ui).highlight(
+                                            |(block_pair, _)| {
+                                                selected.as_ref().is_some_and(|selected| {
+                                                    selected.matches(block_pair)
+                                                })
+                                            },
+                                        ),
+                                    );
 
                                     if ui.button("Delete").clicked() {
                                         delete.push(*split_tree.0);
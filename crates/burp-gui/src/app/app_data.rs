@@ -1,10 +1,11 @@
 use std::{fmt::Display, ops::Deref, path::PathBuf, sync::Arc};
 
 use burp::{
+    events::Events,
     oracle::{
         PoiGraph, RTreeGraphType,
         block_pair::BlockPair,
-        oracle::{Oracle, OracleCollection},
+        oracle::{Oracle, OracleCollection, OracleError},
     },
     tree::Tree,
     types::Poi,
@@ -16,6 +17,24 @@ use serde::{Deserialize, Serialize, ser::SerializeStruct};
 
 use crate::types::Dirty;
 
+/// Center and resolution of the map view, stored so the app reopens where it was left.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub lat: f64,
+    pub lon: f64,
+    pub resolution: f64,
+}
+
+impl Viewport {
+    pub fn new(lat: f64, lon: f64, resolution: f64) -> Self {
+        Self {
+            lat,
+            lon,
+            resolution,
+        }
+    }
+}
+
 #[derive(Clone, Default, Deserialize)]
 #[serde(from = "AppDataSerde")]
 pub struct AppData {
@@ -23,6 +42,11 @@ pub struct AppData {
     pub(crate) oracle: Option<Arc<Mutex<Dirty<OracleCollection<RTreeGraphType<Poi>>>>>>,
     pub(crate) split_tree:
         Option<Arc<RwLock<Dirty<FxHashMap<usize, id_tree::Tree<(BlockPair<f64, f64>, bool)>>>>>>,
+    pub(crate) viewport: Option<Viewport>,
+    /// Error of the last oracle build, until it is shown.
+    pub(crate) oracle_error: Option<OracleError>,
+    /// Progress of a running import, until the graph is loaded.
+    pub(crate) progress: Option<Events>,
 }
 
 impl AppData {
@@ -36,6 +60,9 @@ impl AppData {
         let mut split_tree_path = path.clone();
         split_tree_path.push("split_tree.scmp");
 
+        let mut viewport_path = path.clone();
+        viewport_path.push("viewport.vmp");
+
         Self {
             graph: if let Ok(graph_file) = std::fs::File::open(graph_path) {
                 tracing::debug!("Loading graph from \'{:?}\'", graph_file);
@@ -55,11 +82,17 @@ impl AppData {
             oracle: if let Ok(oracle_file) = std::fs::File::open(oracle_path) {
                 tracing::debug!("Loading oracle from \'{:?}\'", oracle_file);
                 let oracle_file_mmap = unsafe { MmapOptions::new().map(&oracle_file).unwrap() };
-                let oracle = rmp_serde::from_read(oracle_file_mmap.as_ref()).ok();
+                // Files saved before the framed format hold the collection as one value.
+                let oracle = OracleCollection::read_from(oracle_file_mmap.as_ref())
+                    .or_else(|err| {
+                        rmp_serde::from_read(oracle_file_mmap.as_ref())
+                            .inspect(|_| tracing::info!("Read oracle in the previous format"))
+                            .map_err(|old_err| format!("{err}, previous format: {old_err}"))
+                    })
+                    .inspect_err(|err| tracing::error!("Failed to load oracle: {err}"))
+                    .ok();
                 if oracle.is_some() {
                     tracing::info!("Loaded oracle from file");
-                } else {
-                    tracing::warn!("Failed to load oracle")
                 }
 
                 oracle.map(|oracle| Arc::new(Mutex::new(Dirty::new_clean(oracle))))
@@ -82,6 +115,23 @@ impl AppData {
             } else {
                 None
             },
+
+            viewport: if let Ok(viewport_file) = std::fs::File::open(viewport_path) {
+                tracing::debug!("Loading viewport from \'{:?}\'", viewport_file);
+                let viewport = rmp_serde::from_read(viewport_file).ok();
+                if viewport.is_some() {
+                    tracing::info!("Loaded viewport from file");
+                } else {
+                    tracing::warn!("Failed to load viewport")
+                }
+
+                viewport
+            } else {
+                None
+            },
+
+            oracle_error: None,
+            progress: None,
         }
     }
 }
@@ -91,7 +141,7 @@ impl Serialize for AppData {
     where
         S: serde::Serializer,
     {
-        let mut state = serializer.serialize_struct("AppDataSerde", 3)?;
+        let mut state = serializer.serialize_struct("AppDataSerde", 4)?;
 
         if let Some(graph) = self.graph.as_ref().map(|g| g.read_arc()) {
             state.serialize_field("graph", &Some(graph.deref().deref()))?;
@@ -111,6 +161,8 @@ impl Serialize for AppData {
             state.serialize_field("split_tree", &None::<Tree<BlockPair<f64, f64>>>)?;
         }
 
+        state.serialize_field("viewport", &self.viewport)?;
+
         state.end()
     }
 }
@@ -120,6 +172,7 @@ struct AppDataSerde {
     graph: Option<PoiGraph<Poi>>,
     oracle: Option<OracleCollection<RTreeGraphType<Poi>>>,
     split_tree: Option<FxHashMap<usize, id_tree::Tree<(BlockPair<f64, f64>, bool)>>>,
+    viewport: Option<Viewport>,
 }
 
 impl From<AppDataSerde> for AppData {
@@ -134,6 +187,9 @@ impl From<AppDataSerde> for AppData {
             split_tree: value
                 .split_tree
                 .map(|split_tree| Arc::new(RwLock::new(Dirty::new(split_tree)))),
+            viewport: value.viewport,
+            oracle_error: None,
+            progress: None,
         }
     }
 }
@@ -169,6 +225,7 @@ impl TryFrom<AppData> for AppDataSerde {
                 ),
                 None => None,
             },
+            viewport: value.viewport,
         })
     }
 }
@@ -4,7 +4,12 @@ use std::{
 };
 
 use burp::{
-    oracle::{PoiGraph, block_pair::BlockPair, oracle::Oracle},
+    events::Events,
+    oracle::{
+        PoiGraph,
+        block_pair::BlockPair,
+        oracle::{Oracle, OracleError},
+    },
     tree::Tree,
     types::Poi,
 };
@@ -19,6 +24,8 @@ pub enum Event {
     OracleLoaded(Oracle<f64, f64>),
     SplitTreeLoaded((usize, id_tree::Tree<(BlockPair<f64, f64>, bool)>)),
     OracleBuild(Oracle<f64, f64>, id_tree::Tree<(BlockPair<f64, f64>, bool)>),
+    OracleBuildFailed(OracleError),
+    Progress(Events),
 }
 
 impl Event {
@@ -28,6 +35,7 @@ impl Event {
         match self {
             Self::GraphLoaded(graph) => {
                 app_data.graph = Some(Arc::new(RwLock::new(Dirty::new(graph))));
+                app_data.progress = None;
             }
             Self::OracleLoaded(oracle) => {
                 app_data
@@ -55,6 +63,13 @@ impl Event {
                     .lock()
                     .insert(oracle);
             }
+            Self::OracleBuildFailed(err) => {
+                tracing::error!("Could not build oracle: {err}");
+                app_data.oracle_error = Some(err);
+            }
+            Self::Progress(event) => {
+                app_data.progress = Some(event);
+            }
         }
     }
 }
@@ -69,6 +84,8 @@ impl Display for Event {
                 Self::OracleLoaded(_) => "OracleLoaded",
                 Self::SplitTreeLoaded(_) => "SplitTreeLoaded",
                 Self::OracleBuild(_, _) => "OracleBuild",
+                Self::OracleBuildFailed(_) => "OracleBuildFailed",
+                Self::Progress(_) => "Progress",
             }
         )
     }
@@ -84,6 +101,8 @@ impl Debug for Event {
                 Self::OracleLoaded(_) => "Event::OracleLoaded",
                 Self::SplitTreeLoaded(_) => "Event::SplitTreeLoaded",
                 Self::OracleBuild(_, _) => "Event::OracleBuild",
+                Self::OracleBuildFailed(_) => "Event::OracleBuildFailed",
+                Self::Progress(_) => "Event::Progress",
             }
         )
     }
@@ -118,6 +118,7 @@ where
 
         let mut map_state = MapState::load(&self.ctx, self.id);
         map_state.clicked = response.clicked();
+        map_state.secondary_clicked = response.secondary_clicked();
         map_state.map_center_pos = self.map.map.view().position();
         map_state.map_interact_pos = if let Some(screen_pos) = response.hover_pos() {
             self.map
@@ -138,6 +139,12 @@ where
         map_state.clicked
     }
 
+    #[inline]
+    pub fn secondary_clicked(&self) -> bool {
+        let map_state = MapState::load(&self.ctx, self.id);
+        map_state.secondary_clicked
+    }
+
     #[inline]
     pub fn map_center_pos(&self) -> Option<GeoPoint2d> {
         let map_state = MapState::load(&self.ctx, self.id);
@@ -4,6 +4,7 @@ use galileo_types::geo::impls::GeoPoint2d;
 #[derive(Clone, Debug)]
 pub struct MapState {
     pub clicked: bool,
+    pub secondary_clicked: bool,
     pub map_center_pos: Option<GeoPoint2d>,
     pub map_interact_pos: Option<GeoPoint2d>,
 }
@@ -21,6 +22,7 @@ impl Default for MapState {
     fn default() -> Self {
         Self {
             clicked: false,
+            secondary_clicked: false,
             map_center_pos: None,
             map_interact_pos: None,
         }
@@ -1,21 +1,23 @@
 use std::{fmt::Debug, sync::Arc};
 
-use burp::oracle::block_pair::BlockPair;
+use burp::oracle::{block_pair::BlockPair, oracle::Oracle};
 use galileo::{
     Color, Messenger,
     layer::{FeatureId, FeatureLayer, Layer as GalileoLayer, feature_layer::Feature},
+    render::render_bundle::RenderBundle,
     symbol::{
         ArbitraryGeometrySymbol, CirclePointSymbol, SimpleContourSymbol, SimplePolygonSymbol,
+        Symbol,
     },
 };
 use galileo_types::{
     Disambig, Disambiguate, Geometry, MultiPolygon as MultiPolygonTrait,
-    cartesian::{CartesianPoint2d, NewCartesianPoint2d},
+    cartesian::{CartesianPoint2d, NewCartesianPoint2d, Point3},
     geo::{Crs, Datum, NewGeoPoint, impls::projection::WebMercator},
     geometry::Geom,
     geometry_type::{CartesianSpace2d, GeoSpace2d},
 };
-use geo::{Coord, CoordFloat, LineString, MultiPoint, MultiPolygon, Point, Polygon};
+use geo::{Coord, CoordFloat, LineString, MultiPoint, MultiPolygon, Point, Polygon, Rect};
 use graph_rs::{CoordGraph, algorithms::dijkstra::Dijkstra};
 use log::info;
 use nalgebra::Scalar;
@@ -65,6 +67,90 @@ where
         CirclePointSymbol,
         GeoSpace2d,
     >,
+    oracle_layer: FeatureLayer<
+        <Disambig<MultiPolygon<C>, GeoSpace2d> as Geometry>::Point,
+        PoiBlocks<C>,
+        PoiBlocksSymbol,
+        GeoSpace2d,
+    >,
+    selected: Option<SelectedBlockPair<C>>,
+}
+
+/// The block pair drawn by [BlockPairLayer::show_block_pair].
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectedBlockPair<C>
+where
+    C: CoordFloat,
+{
+    pub poi: usize,
+    pub s_block: Rect<C>,
+    pub t_block: Rect<C>,
+}
+
+impl<C> SelectedBlockPair<C>
+where
+    C: RTreeNum + CoordFloat,
+{
+    /// Whether `block_pair` has the same poi and blocks.
+    pub fn matches<EV>(&self, block_pair: &BlockPair<EV, C>) -> bool
+    where
+        EV: FloatCore + Debug,
+    {
+        self.poi == block_pair.poi_id()
+            && self.s_block == *block_pair.s_block()
+            && self.t_block == *block_pair.t_block()
+    }
+}
+
+/// The blocks of a block pair, drawn in the color of its poi.
+pub struct PoiBlocks<C>
+where
+    C: CoordFloat,
+{
+    poi: usize,
+    blocks: Disambig<MultiPolygon<C>, GeoSpace2d>,
+}
+
+impl<C> Feature for PoiBlocks<C>
+where
+    C: CoordFloat,
+{
+    type Geom = Disambig<MultiPolygon<C>, GeoSpace2d>;
+    fn geometry(&self) -> &Self::Geom {
+        &self.blocks
+    }
+}
+
+pub struct PoiBlocksSymbol;
+
+impl PoiBlocksSymbol {
+    /// Spreads consecutive poi ids over the hue circle.
+    fn color(poi: usize) -> Color {
+        let hue = (poi as f32 * 0.618_034).fract();
+        let [r, g, b, a] = egui::ecolor::Hsva::new(hue, 0.8, 0.9, 1.).to_srgba_unmultiplied();
+        Color::rgba(r, g, b, a)
+    }
+}
+
+impl<C> Symbol<PoiBlocks<C>> for PoiBlocksSymbol
+where
+    C: CoordFloat,
+{
+    fn render(
+        &self,
+        feature: &PoiBlocks<C>,
+        geometry: &Geom<Point3>,
+        min_resolution: f64,
+        bundle: &mut RenderBundle,
+    ) {
+        SimplePolygonSymbol {
+            fill_color: Color::TRANSPARENT,
+            stroke_color: Self::color(feature.poi),
+            stroke_width: 1.,
+            stroke_offset: 0.,
+        }
+        .render(feature, geometry, min_resolution, bundle);
+    }
 }
 
 impl<C> BlockPairLayer<C>
@@ -108,15 +194,38 @@ where
             poi_layer: FeatureLayer::new(
                 vec![],
                 CirclePointSymbol::new(Color::from_hex("#F7F304"), 6.),
-                crs,
+                crs.clone(),
             ),
+            oracle_layer: FeatureLayer::new(vec![], PoiBlocksSymbol, crs),
+            selected: None,
         }
     }
+
+    /// The block pair currently drawn by [Self::show_block_pair].
+    pub fn selected(&self) -> Option<&SelectedBlockPair<C>> {
+        self.selected.as_ref()
+    }
+
+    fn block_polygons<EV>(block_pair: &BlockPair<EV, C>) -> MultiPolygon<C>
+    where
+        EV: FloatCore + Debug,
+    {
+        let s_poly = block_pair.s_block().to_polygon();
+        let t_poly = block_pair.t_block().to_polygon();
+
+        geo_types::MultiPolygon::new(vec![s_poly, t_poly])
+    }
     pub fn show_block_pair<G, EV>(&mut self, block_pair: BlockPair<EV, C>, graph: &G)
     where
         G: CoordGraph<C = C, EV = EV> + Dijkstra,
         EV: FloatCore + Debug,
     {
+        self.selected = Some(SelectedBlockPair {
+            poi: block_pair.poi_id(),
+            s_block: *block_pair.s_block(),
+            t_block: *block_pair.t_block(),
+        });
+
         if let Some(s_repr) = graph.node_coord(block_pair.values().s)
             && let Some(t_repr) = graph.node_coord(block_pair.values().t)
         {
@@ -225,10 +334,7 @@ where
             self.radius_layer.update_all_features();
         };
 
-        let s_poly = block_pair.s_block().to_polygon();
-        let t_poly = block_pair.t_block().to_polygon();
-
-        let polys = geo_types::MultiPolygon::new(vec![s_poly, t_poly]);
+        let polys = Self::block_polygons(&block_pair);
 
         info!("inserting polygons {polys:?}");
 
@@ -251,15 +357,38 @@ where
     where
         EV: FloatCore + Debug,
     {
-        let s_poly = block_pair.s_block().to_polygon();
-        let t_poly = block_pair.t_block().to_polygon();
-
-        let polys = geo_types::MultiPolygon::new(vec![s_poly, t_poly]);
+        let polys = Self::block_polygons(&block_pair);
 
         info!("inserting polygons {polys:?}");
 
         self.poly_layer.features_mut().add(polys.to_geo2d())
     }
+
+    /// Draws the blocks of all block pairs of `oracle`, colored by poi.
+    pub fn show_oracle<EV>(&mut self, oracle: &Oracle<EV, C>)
+    where
+        EV: FloatCore + Debug,
+    {
+        let features = self.oracle_layer.features_mut();
+        for block_pair in oracle.block_pairs() {
+            features.add(PoiBlocks {
+                poi: block_pair.poi_id(),
+                blocks: Self::block_polygons(block_pair).to_geo2d(),
+            });
+        }
+        self.oracle_layer.update_all_features();
+    }
+
+    /// Removes everything drawn by [Self::show_oracle].
+    pub fn clear_oracles(&mut self) {
+        let features = self.oracle_layer.features_mut();
+        let f_ids: Vec<_> = features.iter().map(|f| f.0).collect();
+
+        for f_id in f_ids {
+            features.remove(f_id);
+        }
+        self.oracle_layer.update_all_features();
+    }
 }
 
 impl<C> GalileoLayer for BlockPairLayer<C>
@@ -276,6 +405,7 @@ where
     Point<C>: NewGeoPoint,
 {
     fn render(&self, view: &galileo::MapView, canvas: &mut dyn galileo::render::Canvas) {
+        self.oracle_layer.render(view, canvas);
         self.poly_layer.render(view, canvas);
         self.radius_layer.render(view, canvas);
         self.shortest_path_layer.render(view, canvas);
@@ -285,6 +415,7 @@ where
     }
 
     fn prepare(&self, view: &galileo::MapView) {
+        self.oracle_layer.prepare(view);
         self.poly_layer.prepare(view);
         self.radius_layer.prepare(view);
         self.shortest_path_layer.prepare(view);
@@ -295,6 +426,7 @@ where
 
     fn set_messenger(&mut self, messenger: Box<dyn galileo::Messenger>) {
         let messenger = ArcMessenger(Arc::new(messenger));
+        self.oracle_layer.set_messenger(Box::new(messenger.clone()));
         self.poly_layer.set_messenger(Box::new(messenger.clone()));
         self.radius_layer.set_messenger(Box::new(messenger.clone()));
         self.shortest_path_layer
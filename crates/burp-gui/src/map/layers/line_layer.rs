@@ -64,6 +64,17 @@ where
             .collect()
     }
 
+    pub fn clear(&mut self) {
+        let features = self.layer.features_mut();
+        let f_ids: Vec<_> = features.iter().map(|f| f.0).collect();
+
+        for f_id in f_ids {
+            features.remove(f_id);
+        }
+
+        self.layer.update_all_features();
+    }
+
     pub fn insert_coord_graph<T>(&mut self, graph: &T)
     where
         T: CoordGraph,
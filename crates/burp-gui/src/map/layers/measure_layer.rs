@@ -0,0 +1,121 @@
+use burp::{oracle::PoiGraph, types::Poi};
+use galileo::{Color, control::UserEvent, symbol::SimpleContourSymbol};
+use galileo_types::geo::Crs;
+use geo::{Coord, HaversineDistance, LineString, Point};
+use graph_rs::{CoordGraph, graph::Path, types::Direction};
+use rustc_hash::FxHashSet;
+
+use super::{EventLayer, line_layer::ContourLayer};
+
+/// Maximum distance between a click and the node it snaps to.
+const SNAP_TOLERANCE: f64 = 20.;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Measurement {
+    pub haversine: f64,
+    pub routed: Option<f64>,
+}
+
+/// Measures the distance between two successive clicks on the map.
+///
+/// If a graph is given the clicks are snapped to the nearest node and the routed
+/// distance is computed as well.
+pub struct MeasureLayer {
+    line_layer: ContourLayer<SimpleContourSymbol, f64>,
+    start: Option<(Option<usize>, Coord<f64>)>,
+    measurement: Option<Measurement>,
+    route: Option<Path<f64>>,
+}
+
+impl MeasureLayer {
+    pub fn new(crs: Crs) -> Self {
+        Self {
+            line_layer: ContourLayer::new(SimpleContourSymbol::new(Color::BLUE, 3.), crs),
+            start: None,
+            measurement: None,
+            route: None,
+        }
+    }
+
+    pub fn measurement(&self) -> Option<Measurement> {
+        self.measurement
+    }
+
+    /// The routed path of the last measurement, if the clicks could be snapped to nodes.
+    pub fn route(&self) -> Option<&Path<f64>> {
+        self.route.as_ref()
+    }
+
+    pub fn click(&mut self, coord: Coord<f64>, graph: Option<&PoiGraph<Poi>>) {
+        let (node, coord) = match graph.and_then(|graph| {
+            let node = graph.graph().nearest_node_bound(&coord, SNAP_TOLERANCE)?;
+            Some((node, graph.graph().node_coord(node)?))
+        }) {
+            Some((node, coord)) => (Some(node), coord),
+            None => (None, coord),
+        };
+
+        match self.start {
+            Some((start_node, start_coord)) if self.measurement.is_none() => {
+                self.route = match (graph, start_node, node) {
+                    (Some(graph), Some(start_node), Some(node)) => graph
+                        .dijkstra(
+                            start_node,
+                            FxHashSet::from_iter([node]),
+                            Direction::Outgoing,
+                        )
+                        .path(node),
+                    _ => None,
+                };
+
+                self.measurement = Some(Measurement {
+                    haversine: Point::from(start_coord).haversine_distance(&Point::from(coord)),
+                    routed: self.route.as_ref().map(|route| route.cost()),
+                });
+                self.line_layer
+                    .insert_line(LineString::new(vec![start_coord, coord]));
+            }
+            _ => {
+                self.clear();
+                self.start = Some((node, coord));
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.start = None;
+        self.measurement = None;
+        self.route = None;
+        self.line_layer.clear();
+    }
+}
+
+impl galileo::layer::Layer for MeasureLayer {
+    fn render(&self, view: &galileo::MapView, canvas: &mut dyn galileo::render::Canvas) {
+        self.line_layer.render(view, canvas)
+    }
+
+    fn prepare(&self, view: &galileo::MapView) {
+        self.line_layer.prepare(view)
+    }
+
+    fn set_messenger(&mut self, messenger: Box<dyn galileo::Messenger>) {
+        self.line_layer.set_messenger(messenger)
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn attribution(&self) -> Option<galileo::layer::attribution::Attribution> {
+        None
+    }
+}
+
+impl EventLayer for MeasureLayer {
+    fn handle_event(&self, event: &UserEvent, map: &mut galileo::Map) {}
+}
@@ -8,6 +8,7 @@ use galileo::{
 use parking_lot::RwLock;
 pub mod block_pair_layer;
 pub mod line_layer;
+pub mod measure_layer;
 pub mod node_layer;
 // pub mod oracle_layer;
 
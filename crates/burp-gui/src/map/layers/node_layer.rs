@@ -15,7 +15,7 @@ use galileo_types::{
     geometry_type::{CartesianSpace2d, GeoSpace2d},
 };
 use geo::Coord;
-use log::info;
+use log::{info, warn};
 use maybe_sync::{MaybeSend, MaybeSync};
 
 use super::EventLayer;
@@ -27,7 +27,14 @@ pub struct NodeMarker<T> {
 }
 
 impl<T> NodeMarker<T> {
+    /// Creates a marker at `coord`, given as WGS84 longitude and latitude.
+    ///
+    /// Returns `None` if `coord` is out of range, as it can not be projected.
     pub fn new(coord: Coord, node: usize, data: Option<Vec<T>>) -> Option<Self> {
+        if !(-180.0..=180.0).contains(&coord.x) || !(-90.0..=90.0).contains(&coord.y) {
+            return None;
+        }
+
         Some(Self {
             coord: GeoPoint2d::from(&coord),
             node,
@@ -120,6 +127,28 @@ where
     pub fn insert_nodes(&mut self, nodes: Vec<NodeMarker<T>>) {
         nodes.into_iter().for_each(|node| self.insert_node(node));
     }
+
+    /// Inserts a marker for each `(coord, node, data)`, skipping coordinates which can not be
+    /// projected instead of failing the whole layer.
+    ///
+    /// Returns the number of skipped nodes.
+    pub fn insert_coords(
+        &mut self,
+        nodes: impl IntoIterator<Item = (Coord, usize, Option<Vec<T>>)>,
+    ) -> usize {
+        let mut skipped = 0;
+        for (coord, node, data) in nodes {
+            match NodeMarker::new(coord, node, data) {
+                Some(marker) => self.insert_node(marker),
+                None => skipped += 1,
+            }
+        }
+
+        if skipped > 0 {
+            warn!("Skipped {skipped} nodes which could not be projected");
+        }
+        skipped
+    }
 }
 
 impl<S, T> GalileoLayer for NodeLayer<S, T>
@@ -169,3 +198,31 @@ where
         };
     }
 }
+
+#[cfg(test)]
+mod test {
+    use galileo::{Color, symbol::CirclePointSymbol};
+    use galileo_types::geo::Crs;
+    use geo::Coord;
+
+    use super::{NodeLayer, NodeMarker, NodeSymbol};
+
+    #[test]
+    fn skip_unprojectable_nodes() {
+        assert!(NodeMarker::<()>::new(Coord { x: 13.4, y: 52.5 }, 0, None).is_some());
+        assert!(NodeMarker::<()>::new(Coord { x: 200., y: 52.5 }, 1, None).is_none());
+        assert!(NodeMarker::<()>::new(Coord { x: 13.4, y: -91. }, 2, None).is_none());
+        assert!(NodeMarker::<()>::new(Coord { x: f64::NAN, y: 0. }, 3, None).is_none());
+
+        let mut layer: NodeLayer<NodeSymbol, ()> = NodeLayer::new(
+            NodeSymbol::new(CirclePointSymbol::new(Color::BLACK, 5.)),
+            Crs::WGS84,
+        );
+        let skipped = layer.insert_coords([
+            (Coord { x: 13.4, y: 52.5 }, 0, None),
+            (Coord { x: 200., y: 52.5 }, 1, None),
+            (Coord { x: 13.5, y: 52.6 }, 2, None),
+        ]);
+        assert_eq!(skipped, 1);
+    }
+}
@@ -1,7 +1,8 @@
-use burp::types::Poi;
+use burp::types::{Amenity, Poi};
 use galileo::{
+    Color,
     error::GalileoError,
-    symbol::{ImagePointSymbol, Symbol},
+    symbol::{CirclePointSymbol, ImagePointSymbol, Symbol},
 };
 use galileo_types::geometry::Geom;
 use num_traits::AsPrimitive;
@@ -40,4 +41,89 @@ impl Symbol<NodeMarker<Poi>> for PoiSymbol {
     }
 }
 
+/// Renders [Poi] markers as circles colored by the [Amenity] of their first poi.
+pub struct AmenitySymbol {
+    size: f64,
+}
+
+impl AmenitySymbol {
+    pub fn new(size: f64) -> Self {
+        Self { size }
+    }
+
+    /// Returns the color used for `amenity`.
+    pub fn color(amenity: &Amenity) -> Color {
+        match amenity {
+            Amenity::None => Color::from_hex("#808080"),
+            Amenity::Bar => Color::from_hex("#8E44AD"),
+            Amenity::Biergarten => Color::from_hex("#F1C40F"),
+            Amenity::Cafe => Color::BLUE,
+            Amenity::FastFood => Color::from_hex("#E67E22"),
+            Amenity::FoodCourt => Color::from_hex("#D35400"),
+            Amenity::IceCream => Color::from_hex("#FF69B4"),
+            Amenity::Pub => Color::from_hex("#8B4513"),
+            Amenity::Restaurant => Color::RED,
+        }
+    }
+}
+
+impl Symbol<NodeMarker<Poi>> for AmenitySymbol {
+    fn render(
+        &self,
+        feature: &NodeMarker<Poi>,
+        geometry: &Geom<galileo_types::cartesian::Point3>,
+        min_resolution: f64,
+        bundle: &mut galileo::render::render_bundle::RenderBundle,
+    ) {
+        let amenity = feature
+            .data()
+            .and_then(|data| data.first())
+            .map_or(&Amenity::None, Poi::amenity);
+
+        CirclePointSymbol::new(Self::color(amenity), self.size).render(
+            feature,
+            geometry,
+            min_resolution,
+            bundle,
+        );
+    }
+}
+
 pub struct BlockPairSymbol;
+
+#[cfg(test)]
+mod test {
+    use burp::types::Amenity;
+    use galileo::Color;
+
+    use super::AmenitySymbol;
+
+    #[test]
+    fn amenity_colors() {
+        let amenities = [
+            Amenity::None,
+            Amenity::Bar,
+            Amenity::Biergarten,
+            Amenity::Cafe,
+            Amenity::FastFood,
+            Amenity::FoodCourt,
+            Amenity::IceCream,
+            Amenity::Pub,
+            Amenity::Restaurant,
+        ];
+
+        assert_eq!(AmenitySymbol::color(&Amenity::Cafe), Color::BLUE);
+        assert_eq!(AmenitySymbol::color(&Amenity::Restaurant), Color::RED);
+
+        // Every amenity can be told apart on the map.
+        for (i, a) in amenities.iter().enumerate() {
+            for b in &amenities[i + 1..] {
+                assert_ne!(
+                    AmenitySymbol::color(a),
+                    AmenitySymbol::color(b),
+                    "{a:?} and {b:?}"
+                );
+            }
+        }
+    }
+}
@@ -1,6 +1,5 @@
 extern crate geozero;
 
-use std::collections::HashMap;
 use std::error::Error;
 use std::f64;
 use std::fmt::Debug;
@@ -37,7 +36,10 @@ use crate::map::layers::{
 };
 use crate::state::Events;
 use crate::widgets::tree_view::TreeView;
-use burp::input::geo_zero::{ColumnValueClonable, GraphWriter, PoiWriter};
+use burp::input::{
+    filters,
+    geo_zero::{GraphWriter, PoiWriter},
+};
 use memmap2::MmapOptions;
 use rmp_serde::{Deserializer, Serializer};
 use serde::{Deserialize, Serialize};
@@ -167,28 +169,7 @@ pub fn run_ui(state: &mut UiState, ctx: &Context) {
             let file = File::open(file_path).unwrap();
             let buf_reader = BufReader::new(file);
 
-            let filter = |p: &HashMap<String, ColumnValueClonable>| {
-                let footway = p.get("footway");
-                let highway = p.get("highway");
-
-                match highway {
-                    None => return false,
-                    Some(ColumnValueClonable::String(s)) if s == "null" => return false,
-                    Some(ColumnValueClonable::String(s)) if s == "cycleway" => return false,
-                    Some(ColumnValueClonable::String(s)) if s == "path" => return false,
-                    Some(ColumnValueClonable::String(s)) if s == "footway" => return false,
-                    Some(ColumnValueClonable::String(s)) if s == "steps" => return false,
-                    Some(ColumnValueClonable::String(s)) if s == "corridor" => return false,
-                    _ => (),
-                }
-
-                match footway {
-                    None => true,
-                    Some(ColumnValueClonable::String(s)) => s == "null",
-                    _ => false,
-                }
-            };
-            let mut graph_writer = GraphWriter::new(filter);
+            let mut graph_writer = GraphWriter::new(filters::road_network());
 
             read_geojson(buf_reader, &mut graph_writer);
             let graph = RTreeGraph::new_from_graph(graph_writer.get_graph());
@@ -2,6 +2,10 @@ use std::ops::{Deref, DerefMut};
 
 use serde::{Deserialize, Serialize};
 
+/// Tracks whether the wrapped value changed since it was last saved.
+///
+/// Every mutable access goes through [DerefMut], which marks the value dirty,
+/// so mutating methods on the inner type don't need to track this themselves.
 pub(crate) struct Dirty<T> {
     inner: T,
     dirty: bool,
@@ -72,3 +76,34 @@ impl<'de, T: Deserialize<'de>> Deserialize<'de> for Dirty<T> {
         Ok(Self::new_clean(T::deserialize(deserializer)?))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::Dirty;
+
+    #[test]
+    fn mutation_sets_dirty() {
+        let mut value = Dirty::new_clean(vec![1]);
+        assert!(!value.is_dirty());
+
+        assert_eq!(value.len(), 1);
+        assert!(!value.is_dirty());
+
+        value.push(2);
+        assert!(value.is_dirty());
+
+        value.set_clean();
+        assert!(!value.is_dirty());
+        assert!(Dirty::new(0).is_dirty());
+        assert!(Dirty::<u8>::default().is_dirty());
+    }
+
+    #[test]
+    fn deserialized_is_clean() {
+        let bytes = rmp_serde::to_vec(&Dirty::new(vec![1, 2])).unwrap();
+        let value: Dirty<Vec<i32>> = rmp_serde::from_slice(&bytes).unwrap();
+
+        assert!(!value.is_dirty());
+        assert_eq!(value.into_inner(), vec![1, 2]);
+    }
+}
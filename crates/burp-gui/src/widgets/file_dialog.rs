@@ -0,0 +1,74 @@
+use std::{future::Future, path::PathBuf, sync::Arc};
+
+use ashpd::{
+    WindowIdentifier,
+    desktop::{Request, file_chooser::SelectedFiles},
+};
+use parking_lot::Mutex;
+use tokio::{
+    runtime::Runtime,
+    task::{JoinHandle, LocalSet},
+};
+use wgpu::rwh::{HasDisplayHandle, HasWindowHandle};
+
+/// Opens a file dialog over the xdg-desktop-portal if `button` was clicked.
+///
+/// `request` sends the dialog for the identifier of the window, `on_path` gets the first chosen
+/// file. Nothing happens if the dialog is cancelled. A button opens at most one dialog at a
+/// time.
+pub(super) fn spawn_file_dialog<Req, Cont>(
+    ui: &mut egui::Ui,
+    button: &egui::Response,
+    frame: &eframe::Frame,
+    runtime: &Runtime,
+    request: impl FnOnce(Option<WindowIdentifier>) -> Req + Send + 'static,
+    on_path: impl FnOnce(PathBuf) -> Cont + Send + 'static,
+) where
+    Req: Future<Output = ashpd::Result<Request<SelectedFiles>>> + Send + 'static,
+    Cont: Future<Output = ()> + Send + 'static,
+{
+    if !button.clicked() {
+        return;
+    }
+
+    ui.memory_mut(|mem| {
+        if let Some(handle) = mem.data.get_temp::<Arc<Mutex<JoinHandle<()>>>>(button.id) {
+            let handle = handle.lock();
+            if handle.is_finished() {
+                mem.data.remove::<Arc<Mutex<JoinHandle<()>>>>(button.id);
+                log::debug!("Removed file dialog handle");
+            } else {
+                log::info!("File Dialog already open");
+                return;
+            }
+        }
+        mem.data.get_temp_mut_or_insert_with(button.id, move || {
+            let identifier = LocalSet::new().block_on(runtime, async {
+                WindowIdentifier::from_raw_handle(
+                    &frame.window_handle().unwrap().as_raw(),
+                    Some(&frame.display_handle().unwrap().as_raw()),
+                )
+                .await
+            });
+            Arc::new(Mutex::new(runtime.spawn(async move {
+                log::debug!("[xdg-desktop-portal] WindowIdentifier {:?}", identifier);
+                let files = match request(identifier)
+                    .await
+                    .and_then(|request| request.response())
+                {
+                    Ok(files) => files,
+                    Err(err) => {
+                        log::info!("No file chosen: {err}");
+                        return;
+                    }
+                };
+                let Some(path) = files.uris().first().map(|url| PathBuf::from(url.path())) else {
+                    log::info!("No file chosen");
+                    return;
+                };
+
+                on_path(path).await
+            })))
+        });
+    });
+}
@@ -1,6 +1,9 @@
+mod file_dialog;
 pub mod modals;
 mod open_file;
+mod save_file;
 mod tree_view;
 
 pub use open_file::OpenFile;
+pub use save_file::SaveFile;
 pub use tree_view::TreeView;
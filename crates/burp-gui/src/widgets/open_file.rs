@@ -1,15 +1,8 @@
-use std::sync::Arc;
-
-use ashpd::{WindowIdentifier, desktop::file_chooser::FileFilter};
+use ashpd::desktop::file_chooser::{FileFilter, OpenFileRequest};
 use egui::Widget;
-use parking_lot::{Mutex, RawMutex};
-use tokio::{
-    runtime::Runtime,
-    sync::mpsc::Sender,
-    task::{JoinHandle, LocalSet},
-};
-use wgpu::rwh::{HasDisplayHandle, HasWindowHandle};
+use tokio::{runtime::Runtime, sync::mpsc::Sender};
 
+use super::file_dialog::spawn_file_dialog;
 use crate::event_handler::Event;
 
 pub struct OpenFile<'a, 'b> {
@@ -46,58 +39,33 @@ impl Widget for OpenFile<'_, '_> {
     fn ui(self, ui: &mut egui::Ui) -> egui::Response {
         let button = ui.button(self.label);
 
-        if button.clicked() {
-            ui.memory_mut(|mem| {
-                if let Some(handle) = mem.data.get_temp::<Arc<Mutex<JoinHandle<()>>>>(button.id) {
-                    let handle = handle.lock();
-                    if handle.is_finished() {
-                        mem.data.remove::<Arc<Mutex<JoinHandle<()>>>>(button.id);
-                        log::debug!("Removed file dialog handle");
-                    } else {
-                        log::info!("File Dialog already open");
-                        return;
-                    }
-                }
-                mem.data.get_temp_mut_or_insert_with(button.id, move || {
-                    let identifier = LocalSet::new().block_on(&self.runtime, async {
-                        WindowIdentifier::from_raw_handle(
-                            &self.frame.window_handle().unwrap().as_raw(),
-                            Some(&self.frame.display_handle().unwrap().as_raw()),
-                        )
+        let (file_filter, callback, sender) = (self.file_filter, self.callback, self.sender);
+        spawn_file_dialog(
+            ui,
+            &button,
+            self.frame,
+            self.runtime,
+            move |identifier| async move {
+                OpenFileRequest::default()
+                    .identifier(identifier)
+                    .multiple(false)
+                    .filters(file_filter)
+                    .send()
+                    .await
+            },
+            move |path| async move {
+                if let Some(event) = tokio::task::spawn_blocking(move || callback(&path))
+                    .await
+                    .expect("[OpenFile] Callback panic")
+                {
+                    sender
+                        .send(event)
                         .await
-                    });
-                    Arc::new(Mutex::new(self.runtime.spawn(async move {
-                        use ashpd::desktop::file_chooser::FileFilter;
-
-                        log::debug!("[xdg-desktop-portal] WindowIdentifier {:?}", identifier);
-                        let files = ashpd::desktop::file_chooser::OpenFileRequest::default()
-                            .identifier(identifier)
-                            .multiple(false)
-                            .filters(self.file_filter)
-                            .send()
-                            .await
-                            .unwrap()
-                            .response()
-                            .unwrap();
-
-                        if let Some(event) = tokio::task::spawn_blocking(move || {
-                            let urls = files.uris();
-                            let url = urls.first().unwrap();
-                            (self.callback)(std::path::Path::new(url.path()))
-                        })
-                        .await
-                        .expect("[OpenFile] Callback panic")
-                        {
-                            self.sender
-                                .send(event)
-                                .await
-                                .expect("[OpenFile] Failed to send event");
-                            tracing::debug!("Event \x1b[1mGraphLoaded\x1b[0m send")
-                        }
-                    })))
-                });
-            });
-        }
+                        .expect("[OpenFile] Failed to send event");
+                    tracing::debug!("Event \x1b[1mGraphLoaded\x1b[0m send")
+                }
+            },
+        );
 
         button
     }
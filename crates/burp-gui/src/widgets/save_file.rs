@@ -0,0 +1,64 @@
+use ashpd::desktop::file_chooser::{FileFilter, SaveFileRequest};
+use egui::Widget;
+use tokio::runtime::Runtime;
+
+use super::file_dialog::spawn_file_dialog;
+
+pub struct SaveFile<'a, 'b> {
+    label: String,
+    file_name: String,
+    file_filter: Vec<FileFilter>,
+    frame: &'a eframe::Frame,
+    runtime: &'b Runtime,
+    callback: Box<dyn FnOnce(&std::path::Path) + Send>,
+}
+
+impl<'a, 'b> SaveFile<'a, 'b> {
+    pub fn new(
+        label: impl Into<String>,
+        file_name: impl Into<String>,
+        file_filter: Vec<FileFilter>,
+        frame: &'a eframe::Frame,
+        runtime: &'b Runtime,
+        callback: impl FnOnce(&std::path::Path) + Send + 'static,
+    ) -> Self {
+        Self {
+            label: label.into(),
+            file_name: file_name.into(),
+            file_filter,
+            frame,
+            runtime,
+            callback: Box::new(callback),
+        }
+    }
+}
+
+impl Widget for SaveFile<'_, '_> {
+    #[cfg(target_os = "linux")]
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let button = ui.button(self.label);
+
+        let (file_name, file_filter, callback) = (self.file_name, self.file_filter, self.callback);
+        spawn_file_dialog(
+            ui,
+            &button,
+            self.frame,
+            self.runtime,
+            move |identifier| async move {
+                SaveFileRequest::default()
+                    .identifier(identifier)
+                    .current_name(file_name.as_str())
+                    .filters(file_filter)
+                    .send()
+                    .await
+            },
+            move |path| async move {
+                tokio::task::spawn_blocking(move || callback(&path))
+                    .await
+                    .expect("[SaveFile] Callback panic")
+            },
+        );
+
+        button
+    }
+}
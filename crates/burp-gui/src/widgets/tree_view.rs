@@ -13,6 +13,7 @@ where
 {
     tree: &'a Tree<T>,
     node_ui: F,
+    highlight: Option<Box<dyn Fn(&T) -> bool + 'a>>,
 }
 
 impl<'a, T, F> TreeView<'a, T, F>
@@ -20,11 +21,32 @@ where
     F: FnMut(&mut Ui, &Node<T>, (usize, usize)),
 {
     pub fn new(tree: &'a Tree<T>, node_ui: F) -> Self {
-        Self { tree, node_ui }
+        Self {
+            tree,
+            node_ui,
+            highlight: None,
+        }
+    }
+
+    /// Draws a frame in the selection color around the nodes whose data matches `predicate`.
+    pub fn highlight(mut self, predicate: impl Fn(&T) -> bool + 'a) -> Self {
+        self.highlight = Some(Box::new(predicate));
+        self
     }
 
     fn build_tree_view(&mut self, ui: &mut egui::Ui, root: &NodeId, id: (usize, usize)) {
-        (self.node_ui)(ui, self.tree.get(root).unwrap(), id);
+        let node = self.tree.get(root).unwrap();
+        if self
+            .highlight
+            .as_ref()
+            .is_some_and(|highlight| highlight(node.data()))
+        {
+            egui::Frame::group(ui.style())
+                .stroke(ui.visuals().selection.stroke)
+                .show(ui, |ui| (self.node_ui)(ui, node, id));
+        } else {
+            (self.node_ui)(ui, node, id);
+        }
 
         let mut children = self.tree.children_ids(root).unwrap().peekable();
 
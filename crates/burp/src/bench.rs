@@ -1,10 +1,22 @@
-use std::{fs::File, path::PathBuf, rc::Rc};
+use std::{
+    fs::File,
+    path::PathBuf,
+    rc::Rc,
+    time::{Duration, Instant},
+};
 
 use burp::{
-    oracle::{DefaultOracleParams, PoiGraph, SimpleSplitStrategy, oracle::Oracle},
+    oracle::{
+        DefaultOracleParams, PoiGraph, RTreeGraphType, SimpleSplitStrategy,
+        oracle::{Oracle, OracleCollection},
+    },
     types::Poi,
 };
-use graph_rs::Graph;
+use graph_rs::{
+    Graph,
+    algorithms::dijkstra::{Dijkstra, Frontier},
+    types::Direction,
+};
 use log::info;
 use memmap2::MmapOptions;
 use rand::{SeedableRng, rng, rngs::SmallRng, seq::index::sample};
@@ -13,17 +25,7 @@ use rustc_hash::FxHashSet;
 use serde::Deserialize;
 
 pub fn oracle_size_merge(in_file: &PathBuf, v_epsilon: &[f64], batch_size: u64) -> Vec<(f64, f64)> {
-    let in_file = File::open(in_file).unwrap();
-    let in_file_mmap = unsafe { MmapOptions::new().map(&in_file).unwrap() };
-
-    let mut rmp_deserializer = Deserializer::new(in_file_mmap.as_ref());
-
-    let mut graph: PoiGraph<Poi> = PoiGraph::deserialize(&mut rmp_deserializer).unwrap();
-    info!(
-        "Loaded graph: {} nodes, {} edges",
-        graph.graph().node_count(),
-        graph.graph().edge_count()
-    );
+    let graph = load_graph(in_file);
 
     v_epsilon
         .iter()
@@ -40,7 +42,12 @@ pub fn oracle_size_merge(in_file: &PathBuf, v_epsilon: &[f64], batch_size: u64)
                     poi,
                     *epsilon,
                     graph.graph(),
-                    DefaultOracleParams { merge_blocks: true },
+                    DefaultOracleParams {
+                        merge_blocks: true,
+                        max_depth: None,
+                        store_paths: false,
+                        parallel: false,
+                    },
                 )
                 .unwrap();
 
@@ -61,17 +68,7 @@ pub fn oracle_size_no_merge(
     v_epsilon: &[f64],
     batch_size: u64,
 ) -> Vec<(f64, f64)> {
-    let in_file = File::open(in_file).unwrap();
-    let in_file_mmap = unsafe { MmapOptions::new().map(&in_file).unwrap() };
-
-    let mut rmp_deserializer = Deserializer::new(in_file_mmap.as_ref());
-
-    let mut graph: PoiGraph<Poi> = PoiGraph::deserialize(&mut rmp_deserializer).unwrap();
-    info!(
-        "Loaded graph: {} nodes, {} edges",
-        graph.graph().node_count(),
-        graph.graph().edge_count()
-    );
+    let graph = load_graph(in_file);
 
     v_epsilon
         .iter()
@@ -90,6 +87,9 @@ pub fn oracle_size_no_merge(
                     graph.graph(),
                     DefaultOracleParams {
                         merge_blocks: false,
+                        max_depth: None,
+                        store_paths: false,
+                        parallel: false,
                     },
                 )
                 .unwrap();
@@ -105,3 +105,120 @@ pub fn oracle_size_no_merge(
         })
         .collect()
 }
+
+/// Measures the wall-clock time to build the oracles for `batch_size` sampled nodes
+/// with each epsilon.
+pub fn oracle_build_time(
+    in_file: &PathBuf,
+    v_epsilon: &[f64],
+    batch_size: u64,
+) -> Vec<(f64, Duration)> {
+    let graph = load_graph(in_file);
+
+    let pois: FxHashSet<usize> = (0..batch_size)
+        .map(|i| {
+            sample(
+                &mut SmallRng::seed_from_u64(i),
+                graph.graph().node_count(),
+                1,
+            )
+            .index(0)
+        })
+        .collect();
+
+    v_epsilon
+        .iter()
+        .map(|epsilon| {
+            let mut oracles: OracleCollection<RTreeGraphType<Poi>> = OracleCollection::default();
+
+            let start = Instant::now();
+            oracles
+                .build_for_nodes(
+                    &pois,
+                    *epsilon,
+                    graph.graph(),
+                    DefaultOracleParams::default(),
+                )
+                .unwrap();
+            let elapsed = start.elapsed();
+            info!(
+                "Built {} oracles with epsilon {epsilon} in {elapsed:?}",
+                pois.len()
+            );
+
+            (*epsilon, elapsed)
+        })
+        .collect()
+}
+
+/// Measures the wall-clock time of full Dijkstra searches from `batch_size` sampled nodes
+/// with each [Frontier].
+///
+/// The searches go through [Dijkstra::dijkstra_full] of the CSR graph. The Dijkstra cache is
+/// emptied before each frontier, so no search is served from the results of another one.
+///
+/// # Panics
+///
+/// Panics if the frontiers settle a node at different costs.
+pub fn dijkstra_frontier_time(in_file: &PathBuf, batch_size: u64) -> Vec<(Frontier, Duration)> {
+    let mut graph = load_graph(in_file).graph.into_graph();
+    let node_count = graph.node_count();
+
+    let start_nodes: Vec<usize> = (0..batch_size)
+        .map(|i| sample(&mut SmallRng::seed_from_u64(i), node_count, 1).index(0))
+        .collect();
+
+    let (times, results): (Vec<_>, Vec<_>) = [Frontier::PriorityQueue, Frontier::BinaryHeap]
+        .into_iter()
+        .map(|frontier| {
+            graph.clear_cache();
+            graph.set_frontier(frontier);
+
+            let start = Instant::now();
+            let results: Vec<_> = start_nodes
+                .iter()
+                .map(|node| graph.dijkstra_full(*node, Direction::Outgoing))
+                .collect();
+            let elapsed = start.elapsed();
+            info!(
+                "{} searches with {frontier:?} in {elapsed:?}",
+                start_nodes.len()
+            );
+
+            ((frontier, elapsed), results)
+        })
+        .unzip();
+
+    for (node, (queue, heap)) in start_nodes.iter().zip(results[0].iter().zip(&results[1])) {
+        for target in 0..node_count {
+            assert_eq!(
+                queue.cost(target),
+                heap.cost(target),
+                "cost from {node} to {target}"
+            );
+        }
+    }
+
+    times
+}
+
+/// Reads the [PoiGraph] stored in `in_file`.
+///
+/// # Panics
+///
+/// Panics if the file cannot be read or does not hold a graph.
+fn load_graph(in_file: &PathBuf) -> PoiGraph<Poi> {
+    let in_file = File::open(in_file).unwrap();
+    let in_file_mmap = unsafe { MmapOptions::new().map(&in_file).unwrap() };
+
+    let mut rmp_deserializer = Deserializer::new(in_file_mmap.as_ref());
+
+    let graph: PoiGraph<Poi> = PoiGraph::deserialize(&mut rmp_deserializer).unwrap();
+    info!(
+        "Loaded graph: {} nodes, {} edges",
+        graph.graph().node_count(),
+        graph.graph().edge_count()
+    );
+
+    graph
+}
@@ -1,5 +1,33 @@
+use std::fmt::Display;
+
+/// Events reported by long running library calls through an optional callback.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Events {
     PoisLoaded,
     GraphLoaded,
     OracleLoaded,
+    /// A new stage of the call started.
+    Stage(&'static str),
+    /// This many features were read so far.
+    ImportProgress {
+        features: u64,
+    },
+    /// The oracles of `done` out of `total` nodes are built or skipped.
+    OracleProgress {
+        done: usize,
+        total: usize,
+    },
+}
+
+impl Display for Events {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PoisLoaded => write!(f, "Pois loaded"),
+            Self::GraphLoaded => write!(f, "Graph loaded"),
+            Self::OracleLoaded => write!(f, "Oracle loaded"),
+            Self::Stage(name) => write!(f, "{name}"),
+            Self::ImportProgress { features } => write!(f, "Read {features} features"),
+            Self::OracleProgress { done, total } => write!(f, "Built {done}/{total} oracles"),
+        }
+    }
 }
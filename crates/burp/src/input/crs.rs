@@ -0,0 +1,146 @@
+//! Coordinate reference systems of imported data.
+//!
+//! The graph stores WGS84 longitude and latitude, as edge lengths are haversine distances.
+//! Input in other systems is transformed with [Crs::to_wgs84] while reading it.
+
+use std::f64::consts::PI;
+
+use geo::{Coord, coord};
+
+/// Semi-major axis of the WGS84 ellipsoid in meters.
+const A: f64 = 6_378_137.;
+/// Flattening of the WGS84 ellipsoid.
+const F: f64 = 1. / 298.257_223_563;
+/// Scale on the central meridian of a UTM zone.
+const K0: f64 = 0.9996;
+const FALSE_EASTING: f64 = 500_000.;
+const FALSE_NORTHING_SOUTH: f64 = 10_000_000.;
+
+/// The coordinate reference system of input coordinates.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum Crs {
+    /// WGS84 longitude and latitude in degrees, EPSG:4326.
+    #[default]
+    Wgs84,
+    /// Universal Transverse Mercator easting and northing in meters.
+    Utm { zone: u8, north: bool },
+}
+
+impl Crs {
+    /// Returns the system with the EPSG `code`, or `None` if it is not supported.
+    ///
+    /// Supported are EPSG:4326, WGS84 / UTM (EPSG:326xx and 327xx) and ETRS89 / UTM
+    /// (EPSG:258xx). ETRS89 is treated as WGS84, they differ by less than a meter.
+    pub fn from_epsg(code: u32) -> Option<Self> {
+        let (zone, north) = match code {
+            4326 => return Some(Crs::Wgs84),
+            32601..=32660 => (code - 32600, true),
+            32701..=32760 => (code - 32700, false),
+            25828..=25838 => (code - 25800, true),
+            _ => return None,
+        };
+
+        Some(Crs::Utm {
+            zone: zone as u8,
+            north,
+        })
+    }
+
+    /// Transforms `coord` from this system to WGS84 longitude and latitude.
+    pub fn to_wgs84(self, coord: Coord) -> Coord {
+        match self {
+            Crs::Wgs84 => coord,
+            Crs::Utm { zone, north } => utm_to_wgs84(coord, zone, north),
+        }
+    }
+}
+
+/// Inverse transverse mercator projection using Krüger's series to third order,
+/// which is accurate to well below a millimeter within a zone.
+fn utm_to_wgs84(coord: Coord, zone: u8, north: bool) -> Coord {
+    let n = F / (2. - F);
+    let a = A / (1. + n) * (1. + n.powi(2) / 4. + n.powi(4) / 64.);
+    let beta = [
+        n / 2. - 2. * n.powi(2) / 3. + 37. * n.powi(3) / 96.,
+        n.powi(2) / 48. + n.powi(3) / 15.,
+        17. * n.powi(3) / 480.,
+    ];
+    let delta = [
+        2. * n - 2. * n.powi(2) / 3. - 2. * n.powi(3),
+        7. * n.powi(2) / 3. - 8. * n.powi(3) / 5.,
+        56. * n.powi(3) / 15.,
+    ];
+
+    let false_northing = if north { 0. } else { FALSE_NORTHING_SOUTH };
+    let xi = (coord.y - false_northing) / (K0 * a);
+    let eta = (coord.x - FALSE_EASTING) / (K0 * a);
+
+    let (mut xi_p, mut eta_p) = (xi, eta);
+    for (j, beta) in (1..).zip(beta) {
+        let j = 2. * j as f64;
+        xi_p -= beta * (j * xi).sin() * (j * eta).cosh();
+        eta_p -= beta * (j * xi).cos() * (j * eta).sinh();
+    }
+
+    let chi = (xi_p.sin() / eta_p.cosh()).asin();
+    let lat = (1..).zip(delta).fold(chi, |lat, (j, delta)| {
+        lat + delta * (2. * j as f64 * chi).sin()
+    });
+
+    let central_meridian = (zone as f64 * 6. - 183.) * PI / 180.;
+    let lon = central_meridian + eta_p.sinh().atan2(xi_p.cos());
+
+    coord! {x: lon.to_degrees(), y: lat.to_degrees()}
+}
+
+#[cfg(test)]
+mod test {
+    use geo::coord;
+
+    use super::Crs;
+
+    #[test]
+    fn from_epsg() {
+        assert_eq!(Crs::from_epsg(4326), Some(Crs::Wgs84));
+        assert_eq!(
+            Crs::from_epsg(25833),
+            Some(Crs::Utm {
+                zone: 33,
+                north: true
+            })
+        );
+        assert_eq!(
+            Crs::from_epsg(32719),
+            Some(Crs::Utm {
+                zone: 19,
+                north: false
+            })
+        );
+        assert_eq!(Crs::from_epsg(3857), None);
+    }
+
+    #[test]
+    fn utm_to_wgs84() {
+        let utm_33n = Crs::from_epsg(25833).unwrap();
+        // (easting, northing), (longitude, latitude)
+        let pairs = [
+            // On the central meridian the northing is the scaled meridian arc.
+            ((500_000., 4_982_950.400), (15., 45.)),
+            // Brandenburg Gate, Berlin
+            ((389_918.042, 5_819_699.132), (13.377704, 52.516275)),
+        ];
+
+        for ((x, y), (lon, lat)) in pairs {
+            let coord = utm_33n.to_wgs84(coord! {x: x, y: y});
+            assert!((coord.x - lon).abs() < 1e-6, "{coord:?}");
+            assert!((coord.y - lat).abs() < 1e-6, "{coord:?}");
+        }
+
+        // Southern hemisphere, Santiago de Chile
+        let coord = Crs::from_epsg(32719)
+            .unwrap()
+            .to_wgs84(coord! {x: 344_846.720, y: 6_297_700.156});
+        assert!((coord.x - -70.6693).abs() < 1e-6, "{coord:?}");
+        assert!((coord.y - -33.4489).abs() < 1e-6, "{coord:?}");
+    }
+}
@@ -0,0 +1,167 @@
+//! Composable predicates over the OSM properties of a feature.
+//!
+//! All filters take the properties collected by [GraphWriter](super::geo_zero::GraphWriter)
+//! and return `true` if the feature should be included. Classifiers like [highway_class] return
+//! the [EdgeClass] of an included feature instead.
+
+use std::collections::HashMap;
+
+use graph_rs::types::EdgeClass;
+
+use super::geo_zero::ColumnValueClonable;
+use crate::types::Amenity;
+
+pub type Properties = HashMap<String, ColumnValueClonable>;
+
+/// `highway` values which are not part of the routing graph.
+pub const EXCLUDED_HIGHWAYS: [&str; 6] =
+    ["null", "cycleway", "path", "footway", "steps", "corridor"];
+
+/// `highway` values of ways for pedestrians.
+pub const FOOTPATH_HIGHWAYS: [&str; 4] = ["path", "footway", "steps", "corridor"];
+
+/// Accepts features with a `highway` tag that is not in [EXCLUDED_HIGHWAYS].
+pub fn routable_highways() -> impl Fn(&Properties) -> bool + Clone {
+    |properties| match properties.get("highway").map(ColumnValueClonable::as_str) {
+        Some(Some(highway)) => !EXCLUDED_HIGHWAYS.contains(&highway),
+        Some(None) => true,
+        None => false,
+    }
+}
+
+/// Classifies features by their `highway` tag and rejects features without one.
+///
+/// Unlike [road_network] cycleways and footpaths are kept, so one graph can serve every mode
+/// of transport.
+pub fn highway_class() -> impl Fn(&Properties) -> Option<EdgeClass> + Clone {
+    |properties| match properties.get("highway").map(ColumnValueClonable::as_str) {
+        None | Some(Some("null")) => None,
+        Some(Some("cycleway")) => Some(EdgeClass::Cycleway),
+        Some(Some(highway)) if FOOTPATH_HIGHWAYS.contains(&highway) => Some(EdgeClass::Footpath),
+        Some(_) => Some(EdgeClass::Road),
+    }
+}
+
+/// Rejects features with a `footway` tag.
+pub fn exclude_footways() -> impl Fn(&Properties) -> bool + Clone {
+    |properties| {
+        properties
+            .get("footway")
+            .is_none_or(ColumnValueClonable::is_null)
+    }
+}
+
+/// Accepts features whose `amenity` tag maps to `amenity`.
+pub fn only_amenity(amenity: Amenity) -> impl Fn(&Properties) -> bool + Clone {
+    move |properties| {
+        properties
+            .get("amenity")
+            .and_then(ColumnValueClonable::as_str)
+            .map_or(Amenity::None, Amenity::from_osm_tag)
+            == amenity
+    }
+}
+
+/// Accepts features accepted by both `lhs` and `rhs`.
+pub fn and(
+    lhs: impl Fn(&Properties) -> bool + Clone,
+    rhs: impl Fn(&Properties) -> bool + Clone,
+) -> impl Fn(&Properties) -> bool + Clone {
+    move |properties| lhs(properties) && rhs(properties)
+}
+
+/// Accepts features accepted by `lhs` or `rhs`.
+pub fn or(
+    lhs: impl Fn(&Properties) -> bool + Clone,
+    rhs: impl Fn(&Properties) -> bool + Clone,
+) -> impl Fn(&Properties) -> bool + Clone {
+    move |properties| lhs(properties) || rhs(properties)
+}
+
+/// Accepts features rejected by `filter`.
+pub fn not(filter: impl Fn(&Properties) -> bool + Clone) -> impl Fn(&Properties) -> bool + Clone {
+    move |properties| !filter(properties)
+}
+
+/// The filter used to build the routing graph.
+pub fn road_network() -> impl Fn(&Properties) -> bool + Clone {
+    and(routable_highways(), exclude_footways())
+}
+
+#[cfg(test)]
+mod test {
+    use graph_rs::types::EdgeClass;
+
+    use crate::{input::geo_zero::ColumnValueClonable, types::Amenity};
+
+    use super::{Properties, highway_class, not, only_amenity, or, road_network};
+
+    fn properties(tags: &[(&str, &str)]) -> Properties {
+        tags.iter()
+            .map(|(key, value)| {
+                (
+                    key.to_string(),
+                    ColumnValueClonable::String(value.to_string()),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn road_network_table() {
+        let table = [
+            (vec![], false),
+            (vec![("highway", "residential")], true),
+            (vec![("highway", "primary"), ("footway", "null")], true),
+            (
+                vec![("highway", "residential"), ("footway", "sidewalk")],
+                false,
+            ),
+            (vec![("highway", "null")], false),
+            (vec![("highway", "cycleway")], false),
+            (vec![("highway", "path")], false),
+            (vec![("highway", "footway")], false),
+            (vec![("highway", "steps")], false),
+            (vec![("highway", "corridor")], false),
+            (vec![("amenity", "pub")], false),
+        ];
+
+        let filter = road_network();
+
+        for (tags, expected) in table {
+            assert_eq!(filter(&properties(&tags)), expected, "{tags:?}");
+        }
+    }
+
+    #[test]
+    fn highway_class_table() {
+        let table = [
+            (vec![], None),
+            (vec![("highway", "null")], None),
+            (vec![("highway", "residential")], Some(EdgeClass::Road)),
+            (vec![("highway", "cycleway")], Some(EdgeClass::Cycleway)),
+            (vec![("highway", "footway")], Some(EdgeClass::Footpath)),
+            (vec![("highway", "steps")], Some(EdgeClass::Footpath)),
+            (vec![("amenity", "pub")], None),
+        ];
+
+        let classifier = highway_class();
+
+        for (tags, expected) in table {
+            assert_eq!(classifier(&properties(&tags)), expected, "{tags:?}");
+        }
+    }
+
+    #[test]
+    fn amenity_combinators() {
+        let filter = or(only_amenity(Amenity::Pub), only_amenity(Amenity::Bar));
+
+        assert!(filter(&properties(&[("amenity", "pub")])));
+        assert!(filter(&properties(&[("amenity", "bar")])));
+        assert!(!filter(&properties(&[("amenity", "cafe")])));
+        assert!(!filter(&properties(&[])));
+
+        let filter = not(filter);
+        assert!(filter(&properties(&[("amenity", "cafe")])));
+    }
+}
@@ -3,61 +3,326 @@ use log::{debug, info};
 use ordered_float::OrderedFloat;
 use rayon::iter::ParallelIterator;
 
-use std::{cmp::Ordering, collections::HashMap, io::Read, mem};
+use std::{collections::HashMap, fmt::Display, io::Read, mem};
 
-use geo::{Centroid, EuclideanDistance, HaversineDistance, coord};
+use geo::{Centroid, HaversineDistance, coord};
 use geo_types::{
     Coord, Geometry, GeometryCollection, LineString, MultiLineString, MultiPoint, MultiPolygon,
-    Point, Polygon,
+    Point, Polygon, Rect,
 };
 use geozero::{
     ColumnValue, FeatureProcessor, GeomProcessor, PropertyProcessor, error::GeozeroError,
 };
 
 use graph_rs::{
-    Graph, algorithms::trajan_scc::TarjanSCC, graph::csr::DirectedCsrGraph, input::edgelist,
+    Graph, algorithms::trajan_scc::TarjanSCC, graph::csr::DirectedCsrGraph, types::EdgeClass,
 };
 
 use crate::types::{Amenity, CoordNode, Poi};
 
+use super::{
+    crs::Crs,
+    filters::{self, Properties},
+};
+
+/// Edges shorter than this many meters are taken to connect two points at the same place.
+pub const ZERO_LENGTH_EPSILON: f64 = 0.01;
+
+/// Counts collected by [GraphWriter] while reading features.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    /// Coordinates of included features, including repeated ones.
+    pub coords: usize,
+    /// Distinct coordinates, each one a node of the graph.
+    pub nodes: usize,
+    /// Edges before removing duplicates.
+    pub edges: usize,
+    /// Features rejected by the property filter.
+    pub filtered_features: usize,
+}
+
+impl ImportStats {
+    /// Share of coordinates which were already seen before, i.e. joined to an existing node.
+    pub fn shared_ratio(&self) -> f64 {
+        if self.coords == 0 {
+            return 0.;
+        }
+        (self.coords - self.nodes) as f64 / self.coords as f64
+    }
+}
+
+impl Display for ImportStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} coordinates, {} nodes ({:.1}% shared), {} edges, {} features filtered out",
+            self.coords,
+            self.nodes,
+            self.shared_ratio() * 100.,
+            self.edges,
+            self.filtered_features
+        )
+    }
+}
+
 pub struct GraphWriter {
     node_map: HashMap<Coord<OrderedFloat<f64>>, (usize, CoordNode<f64, Poi>)>,
     nodes: Vec<CoordNode<f64, Poi>>,
-    graph: DirectedCsrGraph<f64, CoordNode<f64, Poi>>,
     edges: Vec<(usize, usize, f64)>,
     line: Vec<(usize, usize, f64)>,
     coords: Option<Vec<Coord>>,
     index: usize,
-    property_filter: Box<dyn Fn(&HashMap<String, ColumnValueClonable>) -> bool>,
+    classifier: Box<dyn Fn(&Properties) -> Option<EdgeClass>>,
     properties: HashMap<String, ColumnValueClonable>,
-    include_feature: bool,
+    /// Class of the current feature, `None` if it is rejected. Geometries outside of a feature
+    /// or without properties are taken as roads.
+    edge_class: Option<EdgeClass>,
+    /// Edges as `(source, target, class)` if classes are recorded.
+    edge_classes: Option<Vec<(usize, usize, EdgeClass)>>,
+    area_edges: bool,
+    in_polygon: bool,
+    progress: Option<Box<dyn FnMut(u64)>>,
+    crs: Option<Crs>,
+    collapse_zero_length: bool,
+    bbox: Option<Rect>,
+    clip_bbox: bool,
+    /// Edges as `(source, target, feature)` if provenance is recorded.
+    provenance: Option<Vec<(usize, usize, i64)>>,
+    feature_id: i64,
+    coord_count: usize,
+    filtered_features: usize,
 }
 
 impl GraphWriter {
-    pub fn new(
-        property_filter: impl Fn(&HashMap<String, ColumnValueClonable>) -> bool + 'static,
+    /// Imports the features accepted by `property_filter`.
+    ///
+    /// Their edges are classified by [filters::highway_class], or as [EdgeClass::Road] if it
+    /// rejects them.
+    pub fn new(property_filter: impl Fn(&Properties) -> bool + 'static) -> Self {
+        let highway_class = filters::highway_class();
+        Self::from_classifier(move |properties| {
+            property_filter(properties).then(|| highway_class(properties).unwrap_or_default())
+        })
+    }
+
+    /// Imports the features `classifier` returns a class for.
+    ///
+    /// Features without a class are rejected. The class of each edge is only kept with
+    /// [GraphWriter::with_edge_classes].
+    pub fn from_classifier(
+        classifier: impl Fn(&Properties) -> Option<EdgeClass> + 'static,
     ) -> Self {
         GraphWriter {
             node_map: HashMap::default(),
             nodes: Vec::default(),
-            graph: DirectedCsrGraph::default(),
             edges: Vec::default(),
             line: Vec::default(),
             coords: None,
             index: usize::default(),
-            property_filter: Box::new(property_filter),
+            classifier: Box::new(classifier),
             properties: HashMap::default(),
-            include_feature: true,
+            edge_class: Some(EdgeClass::default()),
+            edge_classes: None,
+            area_edges: false,
+            in_polygon: false,
+            progress: None,
+            crs: None,
+            collapse_zero_length: false,
+            bbox: None,
+            clip_bbox: false,
+            provenance: None,
+            feature_id: 0,
+            coord_count: 0,
+            filtered_features: 0,
         }
     }
 
+    /// Also turn polygon rings into edges.
+    ///
+    /// Off by default, as area features like building outlines are usually not part of the
+    /// routing network. Each ring is closed, even if its last coordinate does not repeat the
+    /// first one.
+    pub fn with_area_edges(mut self, area_edges: bool) -> Self {
+        self.area_edges = area_edges;
+        self
+    }
+
+    /// Calls `progress` with the number of features read so far at the start of each feature.
+    pub fn with_progress(mut self, progress: impl FnMut(u64) + 'static) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// Transforms the input from `crs` to WGS84 longitude and latitude.
+    ///
+    /// Without a CRS coordinates are taken as they are. With one, coordinates which are out
+    /// of range after the transformation are an error, as they were given in another system.
+    pub fn with_crs(mut self, crs: Crs) -> Self {
+        self.crs = Some(crs);
+        self
+    }
+
+    /// Contracts the endpoints of edges shorter than [ZERO_LENGTH_EPSILON] into one node.
+    ///
+    /// Repeated or nearly repeated coordinates, which are common in OSM data, otherwise end up
+    /// as zero length edges and tie with the paths around them. The node with the smaller id
+    /// keeps its coordinate.
+    pub fn with_collapse_zero_length(mut self, collapse_zero_length: bool) -> Self {
+        self.collapse_zero_length = collapse_zero_length;
+        self
+    }
+
+    /// Only imports coordinates within `bbox`, given in longitude and latitude.
+    ///
+    /// Coordinates outside of it get no node and edges touching them are dropped, unless
+    /// [GraphWriter::with_clip_bbox] is set.
+    pub fn with_bbox(mut self, bbox: Rect) -> Self {
+        self.bbox = Some(bbox);
+        self
+    }
+
+    /// Clips edges crossing the boundary of the [GraphWriter::with_bbox] box instead of
+    /// dropping them.
+    ///
+    /// The clipped edge ends in a new node where it leaves the box.
+    pub fn with_clip_bbox(mut self, clip_bbox: bool) -> Self {
+        self.clip_bbox = clip_bbox;
+        self
+    }
+
+    /// Records the feature each edge is read from, see
+    /// [DirectedCsrGraph::edge_provenance].
+    ///
+    /// Features are identified by their `osm_id` property or, if they have none, their index in
+    /// the file. Off by default, as it takes memory for every edge.
+    pub fn with_provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance.then(Vec::new);
+        self
+    }
+
+    /// Records the class of each edge, see [DirectedCsrGraph::edge_class].
+    ///
+    /// Off by default, as it takes memory for every edge.
+    pub fn with_edge_classes(mut self, edge_classes: bool) -> Self {
+        self.edge_classes = edge_classes.then(Vec::new);
+        self
+    }
+
     pub fn new_from(graph_writer: Self) -> Self {
         graph_writer
     }
 
-    pub fn get_graph(self) -> DirectedCsrGraph<f64, CoordNode<f64, Poi>> {
+    /// Counts of everything read so far.
+    pub fn stats(&self) -> ImportStats {
+        ImportStats {
+            coords: self.coord_count,
+            nodes: self.index,
+            edges: self.edges.len(),
+            filtered_features: self.filtered_features,
+        }
+    }
+
+    fn include_geometry(&self) -> bool {
+        self.edge_class.is_some() && (self.area_edges || !self.in_polygon)
+    }
+
+    fn in_bbox(&self, coord: &Coord) -> bool {
+        self.bbox.is_none_or(|bbox| {
+            (bbox.min().x..=bbox.max().x).contains(&coord.x)
+                && (bbox.min().y..=bbox.max().y).contains(&coord.y)
+        })
+    }
+
+    /// The part of the edge from `a` to `b` which is imported, if any.
+    fn bbox_segment(&self, a: Coord, b: Coord) -> Option<(Coord, Coord)> {
+        match self.bbox {
+            Some(bbox) if self.clip_bbox => clip_segment(&bbox, a, b),
+            _ if self.in_bbox(&a) && self.in_bbox(&b) => Some((a, b)),
+            _ => None,
+        }
+    }
+
+    /// Returns the node at `coord`, adding it if there is none yet.
+    fn insert_node(&mut self, coord: Coord) -> usize {
+        match self.node_map.entry(to_ord_coord(&coord)) {
+            std::collections::hash_map::Entry::Occupied(e) => e.get().0,
+            std::collections::hash_map::Entry::Vacant(e) => {
+                e.insert((self.index, CoordNode::new(coord, vec![])));
+                self.index += 1;
+                self.index - 1
+            }
+        }
+    }
+
+    /// Returns the node of an endpoint of a possibly clipped edge. Endpoints which are not the
+    /// `original` coordinate are where the edge was clipped and are added as new nodes.
+    fn segment_node(&mut self, coord: Coord, original: Coord) -> geozero::error::Result<usize> {
+        if coord != original {
+            return Ok(self.insert_node(coord));
+        }
+        self.node_map
+            .get(&to_ord_coord(&coord))
+            .map(|node| node.0)
+            .ok_or(GeozeroError::Geometry(
+                "Coord not processed yet".to_string(),
+            ))
+    }
+
+    /// Builds the graph from all files read so far and keeps its biggest strongly
+    /// connected component.
+    ///
+    /// Nodes are identified by their coordinate, so several files, e.g. tiles of a city, are
+    /// joined where they share coordinates. Edges read more than once, like the ways crossing
+    /// the seam of two tiles, are only added once.
+    pub fn get_graph(mut self) -> DirectedCsrGraph<f64, CoordNode<f64, Poi>> {
+        let mut node_values = vec![CoordNode::default(); self.index];
+        for node in self.node_map.values_mut() {
+            node_values[node.0] = mem::take(&mut node.1);
+        }
+
+        let mut edges = mem::take(&mut self.edges);
+        let mut provenance = self.provenance.take();
+        let mut edge_classes = self.edge_classes.take();
+        let zero_length = edges
+            .iter()
+            .filter(|edge| edge.2 < ZERO_LENGTH_EPSILON)
+            .inspect(|edge| debug!("zero length edge {edge:?}"))
+            .count();
+        if zero_length > 0 {
+            info!("Found {zero_length} edges shorter than {ZERO_LENGTH_EPSILON} m");
+            if self.collapse_zero_length {
+                let new_ids = collapse_zero_length(&mut node_values, &mut edges);
+                info!(
+                    "Collapsed {} nodes into their neighbors",
+                    new_ids.len() - node_values.len()
+                );
+                for edge in provenance.iter_mut().flatten() {
+                    *edge = (new_ids[edge.0], new_ids[edge.1], edge.2);
+                }
+                for edge in edge_classes.iter_mut().flatten() {
+                    *edge = (new_ids[edge.0], new_ids[edge.1], edge.2);
+                }
+            }
+        }
+
+        let edge_count = edges.len();
+        edges.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)).then(a.2.total_cmp(&b.2)));
+        edges.dedup_by_key(|edge| (edge.0, edge.1));
+        if edges.len() < edge_count {
+            info!("Removed {} duplicate edges", edge_count - edges.len());
+        }
+
+        let mut graph = DirectedCsrGraph::from_edges_and_values(edges, node_values);
+        if let Some(provenance) = provenance {
+            graph.set_edge_provenance(provenance);
+        }
+        if let Some(edge_classes) = edge_classes {
+            graph.set_edge_classes(edge_classes);
+        }
+        info!("Created graph");
+
         info!("Computing scc...");
-        let sccs = self.graph.tarjan_scc();
+        let sccs = graph.tarjan_scc();
         let biggest_scc = sccs
             .iter()
             .max_by(|lhs, rhs| lhs.len().cmp(&rhs.len()))
@@ -65,7 +330,7 @@ impl GraphWriter {
 
         info!("Found biggest scc");
 
-        let graph = self.graph.filter(|node| biggest_scc.contains(&node.0));
+        let graph = graph.filter(|node| biggest_scc.contains(&node.0));
         info!(
             "Graph: {} nodes, {} edges",
             graph.node_count(),
@@ -75,36 +340,106 @@ impl GraphWriter {
     }
 }
 
-impl Default for GraphWriter {
-    fn default() -> Self {
-        let filter = |p: &HashMap<String, ColumnValueClonable>| {
-            let footway = p.get("footway");
-            let highway = p.get("highway");
-
-            match highway {
-                None => return false,
-                Some(ColumnValueClonable::String(s)) if s == "null" => return false,
-                Some(ColumnValueClonable::String(s)) if s == "cycleway" => return false,
-                Some(ColumnValueClonable::String(s)) if s == "path" => return false,
-                Some(ColumnValueClonable::String(s)) if s == "footway" => return false,
-                Some(ColumnValueClonable::String(s)) if s == "steps" => return false,
-                Some(ColumnValueClonable::String(s)) if s == "corridor" => return false,
-                _ => (),
+/// Clips the segment from `a` to `b` to `bbox` with the Liang-Barsky algorithm.
+///
+/// Endpoints within `bbox` are returned as they are. Segments which only touch `bbox` in a
+/// single point are dropped.
+fn clip_segment(bbox: &Rect, a: Coord, b: Coord) -> Option<(Coord, Coord)> {
+    let d = b - a;
+    let (mut t_a, mut t_b) = (0., 1.);
+    for (p, q) in [
+        (-d.x, a.x - bbox.min().x),
+        (d.x, bbox.max().x - a.x),
+        (-d.y, a.y - bbox.min().y),
+        (d.y, bbox.max().y - a.y),
+    ] {
+        if p == 0. {
+            if q < 0. {
+                return None;
             }
+        } else if p < 0. {
+            t_a = f64::max(t_a, q / p);
+        } else {
+            t_b = f64::min(t_b, q / p);
+        }
+    }
+    if t_a > t_b || (t_a == t_b && a != b) {
+        return None;
+    }
 
-            match footway {
-                None => true,
-                Some(ColumnValueClonable::String(s)) => s == "null",
-                _ => false,
-            }
+    let at = |t: f64| {
+        if t <= 0. {
+            a
+        } else if t >= 1. {
+            b
+        } else {
+            a + d * t
+        }
+    };
+    Some((at(t_a), at(t_b)))
+}
+
+/// Contracts the endpoints of every edge shorter than [ZERO_LENGTH_EPSILON] into the node with
+/// the smallest id and renumbers the remaining nodes.
+///
+/// Edges which become loops are removed. Returns the new id of every old node.
+fn collapse_zero_length(
+    node_values: &mut Vec<CoordNode<f64, Poi>>,
+    edges: &mut Vec<(usize, usize, f64)>,
+) -> Vec<usize> {
+    fn find(parent: &mut [usize], mut node: usize) -> usize {
+        while parent[node] != node {
+            parent[node] = parent[parent[node]];
+            node = parent[node];
+        }
+        node
+    }
+
+    // The root of each set is its smallest node.
+    let mut parent: Vec<usize> = (0..node_values.len()).collect();
+    for &(a, b, length) in edges.iter() {
+        if length < ZERO_LENGTH_EPSILON {
+            let (a, b) = (find(&mut parent, a), find(&mut parent, b));
+            parent[a.max(b)] = a.min(b);
+        }
+    }
+
+    // Roots come before the other nodes of their set, so their new id is known first.
+    let mut new_ids = vec![0; node_values.len()];
+    let mut node_count = 0;
+    for node in 0..node_values.len() {
+        let root = find(&mut parent, node);
+        new_ids[node] = if root == node {
+            node_count += 1;
+            node_count - 1
+        } else {
+            new_ids[root]
         };
-        Self::new(filter)
+    }
+
+    let mut node = 0;
+    node_values.retain(|_| {
+        node += 1;
+        parent[node - 1] == node - 1
+    });
+    edges.retain_mut(|edge| {
+        edge.0 = new_ids[edge.0];
+        edge.1 = new_ids[edge.1];
+        edge.0 != edge.1
+    });
+
+    new_ids
+}
+
+impl Default for GraphWriter {
+    fn default() -> Self {
+        Self::new(filters::road_network())
     }
 }
 
 impl GeomProcessor for GraphWriter {
     fn xy(&mut self, x: f64, y: f64, idx: usize) -> geozero::error::Result<()> {
-        if !self.include_feature {
+        if !self.include_geometry() {
             return Ok(());
         }
         let coords = self
@@ -112,14 +447,23 @@ impl GeomProcessor for GraphWriter {
             .as_mut()
             .ok_or(GeozeroError::Geometry("Not ready for coords".to_string()))?;
 
-        let coord = coord! {x: x, y: y};
-        let ord_coord = coord! {x: OrderedFloat(x), y: OrderedFloat(y)};
+        let coord = match self.crs {
+            Some(crs) => {
+                let coord = crs.to_wgs84(coord! {x: x, y: y});
+                if !(-180.0..=180.0).contains(&coord.x) || !(-90.0..=90.0).contains(&coord.y) {
+                    return Err(GeozeroError::Geometry(format!(
+                        "({x}, {y}) is not a coordinate in {crs:?}"
+                    )));
+                }
+                coord
+            }
+            None => coord! {x: x, y: y},
+        };
         coords.push(coord);
+        self.coord_count += 1;
 
-        if let std::collections::hash_map::Entry::Vacant(e) = self.node_map.entry(ord_coord) {
-            let node = CoordNode::new(coord, vec![]);
-            e.insert((self.index, node));
-            self.index += 1;
+        if self.in_bbox(&coord) {
+            self.insert_node(coord);
         }
         Ok(())
     }
@@ -150,15 +494,21 @@ impl GeomProcessor for GraphWriter {
     }
 
     fn linestring_end(&mut self, tagged: bool, idx: usize) -> geozero::error::Result<()> {
-        if !self.include_feature {
+        if !self.include_geometry() {
             self.coords.take();
             return Ok(());
         }
         let mut coords = self
             .coords
             .take()
-            .ok_or(GeozeroError::Geometry("No coords in LineSting".to_string()))?
-            .into_iter();
+            .ok_or(GeozeroError::Geometry("No coords in LineSting".to_string()))?;
+        if self.in_polygon && coords.len() > 2 && coords.first() != coords.last() {
+            coords.push(coords[0]);
+        }
+        let mut coords = coords.into_iter();
+        let edge_class = self.edge_class.ok_or(GeozeroError::Geometry(
+            "No class for LineString".to_string(),
+        ))?;
         let oneway = if self.properties.get("oneway")
             != Some(&ColumnValueClonable::String("yes".to_string()))
         {
@@ -169,34 +519,55 @@ impl GeomProcessor for GraphWriter {
 
         let mut coord_a = coords.next().unwrap();
         for coord_b in coords {
-            let node_a =
-                self.node_map
-                    .get(&to_ord_coord(&coord_a))
-                    .ok_or(GeozeroError::Geometry(
-                        "Coord not processed yet".to_string(),
-                    ))?;
-            let node_b =
-                self.node_map
-                    .get(&to_ord_coord(&coord_b))
-                    .ok_or(GeozeroError::Geometry(
-                        "Coord not processed yet".to_string(),
-                    ))?;
-
-            let p_a: Point = coord_a.into();
-            let p_b: Point = coord_b.into();
+            let Some((a, b)) = self.bbox_segment(coord_a, coord_b) else {
+                coord_a = coord_b;
+                continue;
+            };
+            let node_a = self.segment_node(a, coord_a)?;
+            let node_b = self.segment_node(b, coord_b)?;
+
+            let p_a: Point = a.into();
+            let p_b: Point = b.into();
 
             let d = p_a.haversine_distance(&p_b);
 
-            self.edges.push((node_a.0, node_b.0, d));
+            self.edges.push((node_a, node_b, d));
+            if let Some(edge_classes) = self.edge_classes.as_mut() {
+                edge_classes.push((node_a, node_b, edge_class));
+            }
+            if let Some(provenance) = self.provenance.as_mut() {
+                provenance.push((node_a, node_b, self.feature_id));
+            }
 
             if !oneway {
-                self.edges.push((node_b.0, node_a.0, d));
+                self.edges.push((node_b, node_a, d));
+                if let Some(edge_classes) = self.edge_classes.as_mut() {
+                    edge_classes.push((node_b, node_a, edge_class));
+                }
+                if let Some(provenance) = self.provenance.as_mut() {
+                    provenance.push((node_b, node_a, self.feature_id));
+                }
             }
             coord_a = coord_b;
         }
 
         Ok(())
     }
+
+    fn polygon_begin(
+        &mut self,
+        tagged: bool,
+        size: usize,
+        idx: usize,
+    ) -> geozero::error::Result<()> {
+        self.in_polygon = true;
+        Ok(())
+    }
+
+    fn polygon_end(&mut self, tagged: bool, idx: usize) -> geozero::error::Result<()> {
+        self.in_polygon = false;
+        Ok(())
+    }
 }
 
 impl FeatureProcessor for GraphWriter {
@@ -205,25 +576,36 @@ impl FeatureProcessor for GraphWriter {
         Ok(())
     }
     fn feature_begin(&mut self, idx: u64) -> geozero::error::Result<()> {
-        self.include_feature = true;
+        self.edge_class = Some(EdgeClass::default());
         self.properties = HashMap::default();
+        self.feature_id = idx as i64;
+
+        if let Some(progress) = self.progress.as_mut() {
+            progress(idx + 1);
+        }
 
         Ok(())
     }
 
     fn properties_end(&mut self) -> geozero::error::Result<()> {
-        self.include_feature = (self.property_filter)(&self.properties);
+        self.edge_class = (self.classifier)(&self.properties);
+        if self.edge_class.is_none() {
+            self.filtered_features += 1;
+        }
+        if self.provenance.is_some()
+            && let Some(osm_id) = self.properties.get("osm_id")
+        {
+            let osm_id = match osm_id.as_str() {
+                Some(osm_id) => osm_id.parse().ok(),
+                None => osm_id.as_f64().map(|osm_id| osm_id as i64),
+            };
+            self.feature_id = osm_id.unwrap_or(self.feature_id);
+        }
         Ok(())
     }
 
     fn dataset_end(&mut self) -> geozero::error::Result<()> {
         info!("Parsed geojson");
-        let edgelist = edgelist::EdgeList::new(mem::take(&mut self.edges));
-        info!("Created edgelist");
-        self.graph = DirectedCsrGraph::from(edgelist);
-        for node in self.node_map.values_mut() {
-            self.graph.set_node_value(node.0, mem::take(&mut node.1));
-        }
         Ok(())
     }
 
@@ -272,6 +654,52 @@ pub enum ColumnValueClonable {
     Json(String),
 }
 
+impl ColumnValueClonable {
+    /// Returns the value of the string like variants.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) | Self::DateTime(s) | Self::Json(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the numeric variants as `f64`.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Byte(i) => Some(*i as f64),
+            Self::UByte(i) => Some(*i as f64),
+            Self::Short(i) => Some(*i as f64),
+            Self::UShort(i) => Some(*i as f64),
+            Self::Int(i) => Some(*i as f64),
+            Self::UInt(i) => Some(*i as f64),
+            Self::Long(i) => Some(*i as f64),
+            Self::ULong(i) => Some(*i as f64),
+            Self::Float(i) => Some(*i as f64),
+            Self::Double(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` for `"null"`, which the OSM exports use for unset tags.
+    ///
+    /// The empty string is a value like any other and not null.
+    pub fn is_null(&self) -> bool {
+        matches!(self.as_str(), Some("null"))
+    }
+}
+
+impl PartialEq<str> for ColumnValueClonable {
+    fn eq(&self, other: &str) -> bool {
+        matches!(self, Self::String(s) if s == other)
+    }
+}
+
+impl PartialEq<&str> for ColumnValueClonable {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
 impl<'a> From<&ColumnValue<'a>> for ColumnValueClonable {
     fn from(value: &ColumnValue<'a>) -> Self {
         match value {
@@ -393,17 +821,7 @@ where
         if let Some(ColumnValueClonable::String(poi_name)) = properties.get("name") {
             let amenity =
                 if let Some(ColumnValueClonable::String(amenity)) = properties.get("amenity") {
-                    match amenity.as_str() {
-                        "bar" => Amenity::Bar,
-                        "biergarten" => Amenity::Biergarten,
-                        "cafe" => Amenity::Cafe,
-                        "fast_food" => Amenity::FastFood,
-                        "food_court" => Amenity::FoodCourt,
-                        "pub" => Amenity::Pub,
-                        "ice_cream" => Amenity::IceCream,
-                        "restaurant" => Amenity::Restaurant,
-                        _ => Amenity::None,
-                    }
+                    Amenity::from_osm_tag(amenity)
                 } else {
                     Amenity::None
                 };
@@ -617,35 +1035,63 @@ where
     }
 }
 
-fn cmp_points(a: &Point, b: &Point) -> Ordering {
-    let d = a.euclidean_distance(b);
-
-    match d {
-        x if x < 0.0_f64 => Ordering::Less,
-        x if x == 0.0_f64 => Ordering::Equal,
-        x if x > 0.0_f64 => Ordering::Greater,
-        _ => unreachable!(),
-    }
-}
-
 fn to_ord_coord(&coord: &Coord) -> Coord<OrderedFloat<f64>> {
     coord! {x: OrderedFloat(coord.x), y: OrderedFloat(coord.y)}
 }
 
 #[cfg(test)]
 mod test {
-    use std::{collections::HashMap, error::Error};
+    use std::{cell::RefCell, collections::HashMap, error::Error, rc::Rc};
 
-    use geo::{Coord, CoordsIter, Geometry, Point};
+    use geo::{Coord, CoordsIter, Geometry, Point, Rect};
     use geozero::{geo_types::GeoWriter, geojson::read_geojson};
-    use graph_rs::Graph;
+    use graph_rs::{
+        DirectedGraph, Graph,
+        algorithms::dijkstra::Dijkstra,
+        types::{Direction, EdgeClass},
+    };
     use ordered_float::OrderedFloat;
 
     use crate::input::{
         NodeValue,
-        geo_zero::{ColumnValueClonable, GraphWriter},
+        crs::Crs,
+        filters,
+        geo_zero::{ColumnValueClonable, GraphWriter, ImportStats, ZERO_LENGTH_EPSILON},
     };
 
+    #[test]
+    fn column_value_accessors() {
+        let numbers = [
+            ColumnValueClonable::Byte(-3),
+            ColumnValueClonable::UByte(3),
+            ColumnValueClonable::Short(-3),
+            ColumnValueClonable::UShort(3),
+            ColumnValueClonable::Int(-3),
+            ColumnValueClonable::UInt(3),
+            ColumnValueClonable::Long(-3),
+            ColumnValueClonable::ULong(3),
+            ColumnValueClonable::Float(-3.),
+            ColumnValueClonable::Double(3.),
+        ];
+
+        for number in numbers {
+            assert_eq!(number.as_f64().map(f64::abs), Some(3.), "{number:?}");
+            assert_eq!(number.as_str(), None);
+            assert!(!number.is_null());
+        }
+
+        let value = ColumnValueClonable::String("yes".to_string());
+        assert_eq!(value.as_f64(), None);
+        assert_eq!(value.as_str(), Some("yes"));
+        assert!(value == "yes");
+        assert!(value != "no");
+
+        assert!(ColumnValueClonable::String("null".to_string()).is_null());
+        assert!(ColumnValueClonable::Json("null".to_string()).is_null());
+        assert!(!ColumnValueClonable::String(String::new()).is_null());
+        assert!(!ColumnValueClonable::Bool(false).is_null());
+    }
+
     #[test]
     fn line_string() {
         let geojson = r#"{
@@ -656,6 +1102,17 @@ mod test {
         }"#;
         let mut graph_writer = GraphWriter::new(|_| true);
         assert!(read_geojson(geojson.as_bytes(), &mut graph_writer).is_ok());
+        let stats = graph_writer.stats();
+        assert_eq!(
+            stats,
+            ImportStats {
+                coords: 7,
+                nodes: 7,
+                edges: 12,
+                filtered_features: 0,
+            }
+        );
+        assert_eq!(stats.shared_ratio(), 0.);
         let graph = graph_writer.get_graph();
 
         dbg!(&graph);
@@ -674,24 +1131,347 @@ mod test {
     }
 
     #[test]
-    #[ignore = "broken"]
+    fn progress() {
+        let geojson = r#"{
+          "type": "FeatureCollection",
+          "features": [{
+            "type": "Feature",
+            "properties": {},
+            "geometry": {"type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 0.0]]}
+          }, {
+            "type": "Feature",
+            "properties": {},
+            "geometry": {"type": "LineString", "coordinates": [[1.0, 0.0], [1.0, 1.0]]}
+          }]
+        }"#;
+        let features = Rc::new(RefCell::new(vec![]));
+        let mut graph_writer = GraphWriter::new(|_| true).with_progress({
+            let features = features.clone();
+            move |count| features.borrow_mut().push(count)
+        });
+        read_geojson(geojson.as_bytes(), &mut graph_writer).unwrap();
+
+        assert_eq!(*features.borrow(), vec![1, 2]);
+    }
+
+    #[test]
+    fn provenance() {
+        let geojson = r#"{
+          "type": "FeatureCollection",
+          "features": [{
+            "type": "Feature",
+            "properties": {"osm_id": 4242},
+            "geometry": {"type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 0.0]]}
+          }, {
+            "type": "Feature",
+            "properties": {},
+            "geometry": {"type": "LineString", "coordinates": [[1.0, 0.0], [1.0, 1.0]]}
+          }]
+        }"#;
+
+        let mut graph_writer = GraphWriter::new(|_| true);
+        read_geojson(geojson.as_bytes(), &mut graph_writer).unwrap();
+        assert_eq!(graph_writer.get_graph().edge_provenance(0, 1), None);
+
+        let mut graph_writer = GraphWriter::new(|_| true).with_provenance(true);
+        read_geojson(geojson.as_bytes(), &mut graph_writer).unwrap();
+        let graph = graph_writer.get_graph();
+
+        // The first feature has an osm_id, the second one is identified by its index.
+        assert_eq!(graph.edge_provenance(0, 1), Some(4242));
+        assert_eq!(graph.edge_provenance(1, 0), Some(4242));
+        assert_eq!(graph.edge_provenance(1, 2), Some(1));
+        assert_eq!(graph.edge_provenance(2, 1), Some(1));
+        assert_eq!(graph.edge_provenance(0, 2), None);
+    }
+
+    #[test]
+    fn edge_classes() {
+        // A road around three sides of a square and a footway along the fourth.
+        let geojson = r#"{
+          "type": "FeatureCollection",
+          "features": [{
+            "type": "Feature",
+            "properties": {"highway": "residential"},
+            "geometry": {
+              "type": "LineString",
+              "coordinates": [[0.0, 0.0], [0.0, 0.01], [0.01, 0.01], [0.01, 0.0]]
+            }
+          }, {
+            "type": "Feature",
+            "properties": {"highway": "footway"},
+            "geometry": {"type": "LineString", "coordinates": [[0.0, 0.0], [0.01, 0.0]]}
+          }, {
+            "type": "Feature",
+            "properties": {"amenity": "pub"},
+            "geometry": {"type": "LineString", "coordinates": [[0.0, 0.0], [0.02, 0.0]]}
+          }]
+        }"#;
+
+        let mut graph_writer =
+            GraphWriter::from_classifier(filters::highway_class()).with_edge_classes(true);
+        read_geojson(geojson.as_bytes(), &mut graph_writer).unwrap();
+        assert_eq!(graph_writer.stats().filtered_features, 1);
+        let graph = graph_writer.get_graph();
+        assert_eq!(graph.edge_class(0, 1), Some(EdgeClass::Road));
+        assert_eq!(graph.edge_class(0, 3), Some(EdgeClass::Footpath));
+
+        let roads = graph.filter_edge_classes(|class| class == EdgeClass::Road);
+        let all_modes = graph.dijkstra_full(0, Direction::Outgoing).cost(3).unwrap();
+        let road_only = roads.dijkstra_full(0, Direction::Outgoing).cost(3).unwrap();
+        assert!(all_modes * 2.5 < road_only, "{all_modes} {road_only}");
+
+        // The boolean filter drops the footway and classifies what is left.
+        let mut graph_writer = GraphWriter::default().with_edge_classes(true);
+        read_geojson(geojson.as_bytes(), &mut graph_writer).unwrap();
+        let graph = graph_writer.get_graph();
+        assert_eq!(graph.edge_count(), 6);
+        assert_eq!(graph.edge_class(0, 1), Some(EdgeClass::Road));
+
+        // Classes are only kept if asked for.
+        let mut graph_writer = GraphWriter::default();
+        read_geojson(geojson.as_bytes(), &mut graph_writer).unwrap();
+        assert_eq!(graph_writer.get_graph().edge_class(0, 1), None);
+    }
+
+    #[test]
+    fn tiles() {
+        // Both tiles contain the way between (1, 0) and (2, 0).
+        let tiles = [
+            r#"{
+              "type": "FeatureCollection",
+              "features": [{
+                "type": "Feature",
+                "properties": {},
+                "geometry": {
+                  "type": "LineString",
+                  "coordinates": [[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]
+                }
+              }]
+            }"#,
+            r#"{
+              "type": "FeatureCollection",
+              "features": [{
+                "type": "Feature",
+                "properties": {},
+                "geometry": {
+                  "type": "LineString",
+                  "coordinates": [[1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]
+                }
+              }]
+            }"#,
+        ];
+
+        let mut graph_writer = GraphWriter::new(|_| true);
+        for tile in tiles {
+            read_geojson(tile.as_bytes(), &mut graph_writer).unwrap();
+        }
+        let stats = graph_writer.stats();
+        assert_eq!((stats.coords, stats.nodes, stats.edges), (6, 4, 8));
+        let graph = graph_writer.get_graph();
+
+        // The scc filter keeps all nodes, so the graph is connected across the seam.
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 6);
+        let mut neighbors = graph
+            .out_neighbors(1)
+            .map(|x| x.target())
+            .collect::<Vec<_>>();
+        neighbors.sort();
+        assert_eq!(neighbors, vec![0, 2]);
+    }
+
+    #[test]
+    fn collapse_zero_length() {
+        // (1, 0) is repeated and (1, 1e-9) is only a fraction of a millimeter away from it.
+        let geojson = r#"{
+          "type": "FeatureCollection",
+          "features": [{
+            "type": "Feature",
+            "properties": {},
+            "geometry": {
+              "type": "LineString",
+              "coordinates": [[0.0, 0.0], [1.0, 0.0], [1.0, 0.0], [1.0, 1e-9], [2.0, 0.0]]
+            }
+          }]
+        }"#;
+        let mut graph_writer = GraphWriter::new(|_| true);
+        read_geojson(geojson.as_bytes(), &mut graph_writer).unwrap();
+        let graph = graph_writer.get_graph();
+        assert_eq!(graph.node_count(), 4);
+        assert!((0..graph.node_count()).any(|node| {
+            graph
+                .out_neighbors(node)
+                .any(|t| *t.value() < ZERO_LENGTH_EPSILON)
+        }));
+
+        let mut graph_writer = GraphWriter::new(|_| true).with_collapse_zero_length(true);
+        read_geojson(geojson.as_bytes(), &mut graph_writer).unwrap();
+        let graph = graph_writer.get_graph();
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 4);
+        assert!((0..graph.node_count()).all(|node| {
+            graph
+                .out_neighbors(node)
+                .all(|t| *t.value() >= ZERO_LENGTH_EPSILON)
+        }));
+        assert_eq!(
+            graph.node_value(1).unwrap().get_coord(),
+            &Coord { x: 1.0, y: 0.0 }
+        );
+        let mut neighbors = graph
+            .out_neighbors(1)
+            .map(|x| x.target())
+            .collect::<Vec<_>>();
+        neighbors.sort();
+        assert_eq!(neighbors, vec![0, 2]);
+    }
+
+    #[test]
+    fn bbox() {
+        let geojson = r#"{
+          "type": "FeatureCollection",
+          "features": [{
+            "type": "Feature",
+            "properties": {},
+            "geometry": {
+              "type": "LineString",
+              "coordinates": [[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]
+            }
+          }]
+        }"#;
+        let bbox = Rect::new(Coord { x: -0.5, y: -0.5 }, Coord { x: 1.5, y: 0.5 });
+
+        let mut graph_writer = GraphWriter::new(|_| true).with_bbox(bbox);
+        read_geojson(geojson.as_bytes(), &mut graph_writer).unwrap();
+        assert_eq!(graph_writer.stats().nodes, 2);
+        let graph = graph_writer.get_graph();
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.edge_count(), 2);
+        let coords: Vec<_> = (0..graph.node_count())
+            .map(|node| *graph.node_value(node).unwrap().get_coord())
+            .collect();
+        assert_eq!(
+            coords,
+            vec![Coord { x: 0.0, y: 0.0 }, Coord { x: 1.0, y: 0.0 }]
+        );
+
+        // The edge leaving the box ends on its boundary.
+        let mut graph_writer = GraphWriter::new(|_| true)
+            .with_bbox(bbox)
+            .with_clip_bbox(true);
+        read_geojson(geojson.as_bytes(), &mut graph_writer).unwrap();
+        let graph = graph_writer.get_graph();
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 4);
+        assert_eq!(
+            graph.node_value(2).unwrap().get_coord(),
+            &Coord { x: 1.5, y: 0.0 }
+        );
+        let mut neighbors = graph
+            .out_neighbors(1)
+            .map(|x| x.target())
+            .collect::<Vec<_>>();
+        neighbors.sort();
+        assert_eq!(neighbors, vec![0, 2]);
+    }
+
+    #[test]
+    fn crs() {
+        let geojson = r#"{
+          "type": "FeatureCollection",
+          "features": [{
+            "type": "Feature",
+            "properties": {},
+            "geometry": {
+              "type": "LineString",
+              "coordinates": [[500000.0, 4982950.4], [389918.042, 5819699.132]]
+            }
+          }]
+        }"#;
+
+        let utm_33n = Crs::from_epsg(25833).unwrap();
+        let mut graph_writer = GraphWriter::new(|_| true).with_crs(utm_33n);
+        read_geojson(geojson.as_bytes(), &mut graph_writer).unwrap();
+        let graph = graph_writer.get_graph();
+
+        let coord = graph.node_value(1).unwrap().get_coord();
+        assert!((coord.x - 13.377704).abs() < 1e-6, "{coord:?}");
+        assert!((coord.y - 52.516275).abs() < 1e-6, "{coord:?}");
+        // Edge lengths are haversine distances of the transformed coordinates.
+        let length = *graph.out_neighbors(0).next().unwrap().value();
+        assert!((length - 844_132.).abs() < 1_000., "{length}");
+
+        // UTM coordinates are no longitude and latitude.
+        let mut graph_writer = GraphWriter::new(|_| true).with_crs(Crs::Wgs84);
+        assert!(read_geojson(geojson.as_bytes(), &mut graph_writer).is_err());
+    }
+
+    #[test]
     fn multi_polygon() {
         let geojson = r#"{
+          "type": "FeatureCollection",
+          "features": [{
+          "type": "Feature",
+          "properties": {},
+          "geometry": {
             "type": "MultiPolygon",
             "coordinates": [[[
                 [73.020375,-40.919052],[70.247234,-41.331999],[173.958405,-40.926701],[174.247587,-41.349155],[174.248517,-41.770008],[173.876447,-42.233184],[173.22274,-42.970038],[172.711246,-43.372288],[173.080113,-43.853344],[172.308584,-43.865694],[171.452925,-44.242519],[171.185138,-44.897104],[170.616697,-45.908929],[169.831422,-46.355775],[169.332331,-46.641235],[168.411354,-46.619945],[167.763745,-46.290197],[166.676886,-46.219917],[166.509144,-45.852705],[167.046424,-45.110941],[168.303763,-44.123973],[168.949409,-43.935819],[169.667815,-43.555326],[170.52492,-43.031688],[171.12509,-42.512754],[171.569714,-41.767424],[171.948709,-41.514417],[172.097227,-40.956104],[172.79858,-40.493962],[173.020375,-40.919052],[73.020375,-40.919052]
             ]],[[
                 [174.612009,-36.156397],[175.336616,-37.209098],[175.357596,-36.526194],[175.808887,-36.798942],[175.95849,-37.555382],[176.763195,-37.881253],[177.438813,-37.961248],[178.010354,-37.579825],[178.517094,-37.695373],[178.274731,-38.582813],[177.97046,-39.166343],[177.206993,-39.145776],[176.939981,-39.449736],[177.032946,-39.879943],[176.885824,-40.065978],[176.508017,-40.604808],[176.01244,-41.289624],[175.239567,-41.688308],[175.067898,-41.425895],[174.650973,-41.281821],[175.22763,-40.459236],[174.900157,-39.908933],[173.824047,-39.508854],[173.852262,-39.146602],[174.574802,-38.797683],[174.743474,-38.027808],[174.697017,-37.381129],[174.292028,-36.711092],[174.319004,-36.534824],[173.840997,-36.121981],[173.054171,-35.237125],[172.636005,-34.529107],[173.007042,-34.450662],[173.551298,-35.006183],[174.32939,-35.265496],[174.612009,-36.156397]
             ]]]
+          }
+          }]
         }"#;
-        let mut graph_writer = GraphWriter::new(|_| true);
+        let mut graph_writer = GraphWriter::new(|_| true).with_area_edges(true);
+        assert!(read_geojson(geojson.as_bytes(), &mut graph_writer).is_ok());
+        let graph = graph_writer.get_graph();
+
+        // Only the bigger second polygon survives the scc filter.
+        assert_eq!(graph.node_count(), 35);
+        let mut neighbors = graph.neighbors(0).map(|x| x.target()).collect::<Vec<_>>();
+        neighbors.sort();
+
+        assert_eq!(neighbors, vec![1, 34]);
+
+        // An open ring is closed as well.
+        let geojson = r#"{
+          "type": "FeatureCollection",
+          "features": [{
+            "type": "Feature",
+            "properties": {},
+            "geometry": {
+              "type": "Polygon",
+              "coordinates": [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]]]
+            }
+          }, {
+            "type": "Feature",
+            "properties": {},
+            "geometry": {
+              "type": "LineString",
+              "coordinates": [[5.0, 5.0], [6.0, 6.0]]
+            }
+          }]
+        }"#;
+        let mut graph_writer = GraphWriter::new(|_| true).with_area_edges(true);
         assert!(read_geojson(geojson.as_bytes(), &mut graph_writer).is_ok());
         let graph = graph_writer.get_graph();
 
         let mut neighbors = graph.neighbors(0).map(|x| x.target()).collect::<Vec<_>>();
         neighbors.sort();
+        assert_eq!(neighbors, vec![1, 2]);
+
+        // Without area edges only the line string is left.
+        let mut graph_writer = GraphWriter::new(|_| true);
+        assert!(read_geojson(geojson.as_bytes(), &mut graph_writer).is_ok());
+        let graph = graph_writer.get_graph();
 
-        assert_eq!(neighbors, vec![1, 29]);
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(
+            graph.node_value(0).unwrap().get_coord(),
+            &Coord { x: 5.0, y: 5.0 }
+        );
     }
 
     #[test]
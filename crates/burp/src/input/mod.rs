@@ -1,8 +1,32 @@
+pub mod crs;
+pub mod filters;
 pub mod geo_zero;
 
+use std::io::{self, BufRead, Read};
+
+use flate2::bufread::GzDecoder;
 use geo_types::Coord;
 use graph_rs::Coordinate;
 
+/// Magic bytes at the start of every gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Returns `true` if `bytes` start like a gzip stream.
+pub fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC)
+}
+
+/// Decompresses `reader` on the fly if it is gzip compressed, e.g. a `.geojson.gz` export.
+///
+/// The content is detected by its magic bytes, not by a file extension.
+pub fn decompressed<'a>(mut reader: impl BufRead + 'a) -> io::Result<Box<dyn Read + 'a>> {
+    if is_gzip(reader.fill_buf()?) {
+        Ok(Box::new(GzDecoder::new(reader)))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum NodeValue {
     Coord(Coord),
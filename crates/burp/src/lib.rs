@@ -5,6 +5,8 @@ extern crate rstar;
 pub mod events;
 pub mod input;
 pub mod oracle;
+pub mod output;
+pub mod pipeline;
 mod serde;
 pub mod tree;
 pub mod types;
@@ -1,30 +1,37 @@
 use std::{
     fmt::Display,
     fs::{File, OpenOptions},
-    io::BufWriter,
-    path::PathBuf,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
 use burp::{
     input::{
-        self,
+        crs::Crs,
         geo_zero::{GraphWriter, PoiWriter},
     },
     oracle::{
-        DefaultOracleParams, PoiGraph, SimpleSplitStrategy,
+        DefaultOracleParams, LargeNodeParams, PoiGraph, RTreeGraphType, SimpleSplitStrategy,
+        SmallNodeParams,
+        block_pair::BlockPair,
         oracle::{self, Oracle, OracleCollection},
     },
+    output, pipeline,
     types::Poi,
+    util,
 };
-use clap::{Parser, Subcommand};
-use geozero::geojson::read_geojson;
-use graph_rs::{Graph, graph::rstar::RTreeGraph};
-use indicatif::ProgressBar;
-use log::{debug, info};
+use clap::{Parser, Subcommand, ValueEnum};
+use geo::{Rect, coord};
+use graph_rs::{CoordGraph, Graph, graph::Target, types::Direction};
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use log::{debug, info, warn};
 use memmap2::MmapOptions;
 use rand::{prelude::*, rng, seq::index::sample};
 use rayon::iter::IntoParallelRefIterator;
 use rmp_serde::{Deserializer, Serializer};
+use rstar::{DefaultParams, RTreeParams};
+use rustc_hash::{FxHashMap, FxHashSet};
 use serde::{Deserialize, Serialize};
 
 mod bench;
@@ -34,14 +41,21 @@ struct Cli {
     /// Build .ocl file from geo-json
     #[command(subcommand)]
     command: Commands,
+
+    /// Number of worker threads for parallel work. Defaults to all cores.
+    #[arg(long, global = true, value_name = "N")]
+    threads: Option<usize>,
 }
 
 #[derive(Subcommand, Clone)]
 enum Commands {
     Graph {
-        in_file: PathBuf,
+        /// Input files, several geojson files are joined into one graph. Gzip compressed
+        /// '.geojson.gz' files are decompressed while reading.
+        #[arg(required = true)]
+        in_files: Vec<PathBuf>,
 
-        /// Set output file to <FILE>. Defaults to '<IN_FILE>.gmp'.
+        /// Set output file to <FILE>. Defaults to '<IN_FILE>.gmp' for the first in-file.
         #[arg(short = 'o', long)]
         out_file: Option<PathBuf>,
 
@@ -56,6 +70,46 @@ enum Commands {
         /// Sample <NUMBER> pois form nodes at random.
         #[arg(short, long, conflicts_with = "pois")]
         sample: Option<usize>,
+
+        /// Sample <NUMBER> pois spread evenly over the area of the graph.
+        #[arg(long, value_name = "NUMBER", conflicts_with_all = ["pois", "sample"])]
+        sample_spatial: Option<usize>,
+
+        /// Also add polygon rings as edges.
+        #[arg(long)]
+        area_edges: bool,
+
+        /// EPSG code of the coordinate system of the in-files, e.g. 25833 for ETRS89 / UTM 33N
+        #[arg(long, value_name = "EPSG", default_value = "4326", value_parser = parse_crs)]
+        crs: Crs,
+
+        /// Merge ways mapped twice whose endpoints are within <METERS> of each other. Lossy.
+        #[arg(long, value_name = "METERS", value_parser = parse_positive)]
+        merge_parallel_ways: Option<f64>,
+
+        /// Contract the endpoints of zero length edges, e.g. from repeated coordinates.
+        #[arg(long)]
+        collapse_zero_length: bool,
+
+        /// Split edges longer than <METERS> by adding nodes along them.
+        #[arg(long, value_name = "METERS")]
+        subdivide_edges: Option<f64>,
+
+        /// Only import coordinates within this box, in longitude and latitude.
+        #[arg(long, value_name = "MIN_LON,MIN_LAT,MAX_LON,MAX_LAT", value_parser = parse_bbox)]
+        bbox: Option<Rect>,
+
+        /// Clip edges leaving the box given by --bbox instead of dropping them.
+        #[arg(long, requires = "bbox")]
+        clip_bbox: bool,
+
+        /// Record the feature each edge is read from, e.g. to debug filters.
+        #[arg(long)]
+        provenance: bool,
+
+        /// Record whether each edge is a road, footpath or cycleway.
+        #[arg(long)]
+        edge_classes: bool,
     },
     Build {
         /// Input graph in '.gmp' format
@@ -73,22 +127,144 @@ enum Commands {
         #[arg(short, long)]
         merge_blocks: bool,
 
+        /// Check the loaded graph for consistency before building the oracle
+        #[arg(long)]
+        validate: bool,
+
+        /// Stop splitting block pairs at depth <DEPTH> and store them as in-path.
+        /// Smaller oracle, but may report pois which are not on a near-shortest path.
+        #[arg(long, value_name = "DEPTH")]
+        max_depth: Option<usize>,
+
+        /// Store one beer path per in-path block pair. Larger oracle, slower build.
+        #[arg(long)]
+        store_paths: bool,
+
+        /// Node size of the r-tree indexing the blocks. Only changes build and query time.
+        #[arg(long, value_enum, default_value_t = RTreeNodes::Default)]
+        r_tree_nodes: RTreeNodes,
+
+        /// Write all oracles to one '<OUT_FILE>.ocmp' file instead of one file per poi
+        #[arg(long)]
+        single_file: bool,
+
         /// Set output file to <FILE>. Defaults to '<IN_FILE>.omp'.
         #[arg(short = 'o', long)]
         out_file: Option<PathBuf>,
     },
 
+    Query {
+        /// Directory with the oracles in '.omp' format, or a single '.ocmp' file
+        oracle_dir: PathBuf,
+
+        /// Graph in '.gmp' format
+        graph: PathBuf,
+
+        /// Latitude of the start
+        #[arg(allow_negative_numbers = true)]
+        s_lat: f64,
+
+        /// Longitude of the start
+        #[arg(allow_negative_numbers = true)]
+        s_lon: f64,
+
+        /// Latitude of the target
+        #[arg(allow_negative_numbers = true)]
+        t_lat: f64,
+
+        /// Longitude of the target
+        #[arg(allow_negative_numbers = true)]
+        t_lon: f64,
+
+        /// Maximum distance in meters when snapping the coordinates to nodes
+        #[arg(long, value_name = "FLOAT", default_value_t = 100.)]
+        tolerance: f64,
+
+        /// Also compute the exact answer with epsilon <FLOAT> for comparison
+        #[arg(long, value_name = "FLOAT")]
+        exact: Option<f64>,
+
+        /// Write the route over the cheapest poi as GeoJSON to <FILE>
+        #[arg(long, value_name = "FILE")]
+        export_route: Option<PathBuf>,
+
+        /// Write the distances from the start to all nodes as CSV to <FILE>
+        #[arg(long, value_name = "FILE")]
+        dump_distances: Option<PathBuf>,
+    },
+
     Bench {
         in_file: PathBuf,
         /// Measure oracle size
         #[arg(short, long)]
         size: bool,
 
+        /// Measure oracle build time in seconds
+        #[arg(short, long)]
+        time: bool,
+
+        /// Measure full Dijkstra searches with each frontier in seconds
+        #[arg(short, long)]
+        dijkstra: bool,
+
         #[arg(short, long)]
         batch_size: u64,
     },
 }
 
+/// R-tree parameter sets selectable on the command line.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum RTreeNodes {
+    /// rstar's defaults, 3 to 6 children per node
+    Default,
+    /// 2 to 4 children per node
+    Small,
+    /// 8 to 24 children per node
+    Large,
+}
+
+/// Extension of `path`, including the one before `.gz` of compressed files, e.g. `geojson.gz`.
+fn file_extension(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?;
+    if extension != "gz" {
+        return Some(extension.to_string());
+    }
+    let inner = Path::new(path.file_stem()?).extension()?.to_str()?;
+    Some(format!("{inner}.{extension}"))
+}
+
+/// Edges this many standard deviations longer than the mean are reported after importing a
+/// graph.
+const OUTLIER_SIGMA: f64 = 10.;
+
+fn parse_crs(epsg: &str) -> Result<Crs, String> {
+    let code = epsg.parse().map_err(|err| format!("{err}"))?;
+    Crs::from_epsg(code).ok_or_else(|| format!("EPSG:{code} is not supported"))
+}
+
+fn parse_positive(value: &str) -> Result<f64, String> {
+    let value: f64 = value.parse().map_err(|err| format!("{err}"))?;
+    if value > 0. {
+        Ok(value)
+    } else {
+        Err(format!("{value} is not positive"))
+    }
+}
+
+fn parse_bbox(bbox: &str) -> Result<Rect, String> {
+    let values = bbox
+        .split(',')
+        .map(|value| value.trim().parse::<f64>().map_err(|err| format!("{err}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let [min_x, min_y, max_x, max_y] = values[..] else {
+        return Err(format!("expected 4 values, got {}", values.len()));
+    };
+    Ok(Rect::new(
+        coord! {x: min_x, y: min_y},
+        coord! {x: max_x, y: max_y},
+    ))
+}
+
 fn main() {
     let cli = Cli::parse();
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
@@ -96,42 +272,139 @@ fn main() {
     let working_dir = std::env::current_dir().unwrap();
     log::info!("Current working dir '{}'", working_dir.display());
 
-    match cli.command {
+    // A scoped pool, so several runs side by side do not each use all cores.
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(cli.threads.unwrap_or_default())
+        .build()
+        .expect("Failed to build thread pool");
+    info!("Using {} worker threads", pool.current_num_threads());
+
+    pool.install(|| run(cli.command));
+}
+
+fn run(command: Commands) {
+    match command {
         Commands::Graph {
-            in_file,
+            in_files,
             out_file,
             coords_file,
             pois,
             sample: sample_size,
+            sample_spatial,
+            area_edges,
+            crs,
+            merge_parallel_ways,
+            collapse_zero_length,
+            subdivide_edges,
+            bbox,
+            clip_bbox,
+            provenance,
+            edge_classes,
         } => {
-            info!("Build graph from {:?}", in_file);
+            info!("Build graph from {:?}", in_files);
+            let file_extension =
+                file_extension(&in_files[0]).expect("in-file is missing a file extension");
+            if in_files
+                .iter()
+                .any(|in_file| file_extension(in_file).as_ref() != Some(&file_extension))
+            {
+                panic!("all in-files must have the same file extension");
+            }
             let out_file = out_file.unwrap_or_else(|| {
-                let mut out_file = in_file.clone();
+                let mut out_file = in_files[0].clone();
+                if file_extension.ends_with(".gz") {
+                    out_file.set_extension("");
+                }
                 out_file.set_extension("gmp");
                 out_file
             });
-            let file_extension = in_file
-                .extension()
-                .expect("in-file is missing a file extension")
-                .to_owned();
-            let in_file = File::open(in_file).unwrap();
-            let in_file_mmap = unsafe { MmapOptions::new().map(&in_file).unwrap() };
 
             let mut graph;
 
-            match file_extension
-                .to_str()
-                .expect("Cannot convert file_extension to 'str'")
-            {
-                "geojson" => {
-                    let mut graph_writer = GraphWriter::default();
+            match file_extension.as_str() {
+                "geojson" | "geojson.gz" => {
+                    // geozero parses the whole file before the first feature, so the
+                    // feature count is unknown and only the file size is shown.
+                    let progress = ProgressBar::new_spinner().with_style(
+                        ProgressStyle::with_template(
+                            "{spinner} {msg}: {human_pos} features ({per_sec})",
+                        )
+                        .unwrap(),
+                    );
+                    progress.enable_steady_tick(Duration::from_millis(100));
+
+                    let mut graph_writer = GraphWriter::default()
+                        .with_area_edges(area_edges)
+                        .with_crs(crs)
+                        .with_collapse_zero_length(collapse_zero_length)
+                        .with_clip_bbox(clip_bbox)
+                        .with_provenance(provenance)
+                        .with_edge_classes(edge_classes)
+                        .with_progress({
+                            let progress = progress.clone();
+                            move |features| progress.set_position(features)
+                        });
+                    if let Some(bbox) = bbox {
+                        graph_writer = graph_writer.with_bbox(bbox);
+                    }
 
-                    read_geojson(in_file_mmap.as_ref(), &mut graph_writer).unwrap();
-                    graph = PoiGraph::new(RTreeGraph::new_from_graph(graph_writer.get_graph()));
+                    // One writer for all files, so nodes at the same coordinate are shared.
+                    for path in in_files.iter() {
+                        let in_file = File::open(path).unwrap();
+                        progress.set_message(format!(
+                            "Reading {} ({})",
+                            path.display(),
+                            HumanBytes(in_file.metadata().unwrap().len())
+                        ));
+                        pipeline::read_geojson_into(in_file, &mut graph_writer).unwrap();
+                    }
+                    progress.finish();
+                    graph = pipeline::build_graph(graph_writer);
                 }
                 ext => panic!("file type '.{ext}' not supported"),
             }
 
+            if let Some(tolerance) = merge_parallel_ways {
+                let merged = graph.merge_parallel_ways(tolerance);
+                info!("Merged {merged} nodes of parallel ways");
+            }
+
+            if let Some(max_len) = subdivide_edges {
+                let added = graph.subdivide_edges(max_len);
+                info!("Added {added} nodes subdividing long edges");
+            }
+
+            if let Some(bounding_rect) = graph.graph().bounding_rect() {
+                info!(
+                    "Total edge length: {:.2} km, extent: {:?} - {:?}",
+                    graph.total_edge_length() / 1000.,
+                    bounding_rect.min().x_y(),
+                    bounding_rect.max().x_y()
+                );
+            }
+
+            let csr = graph.graph().graph();
+            if let Some((min, max, mean, stddev)) = csr.edge_weight_stats() {
+                info!(
+                    "Edge lengths: {min:.2} - {max:.2} m, mean {mean:.2} m, stddev {stddev:.2} m"
+                );
+                let outliers = csr.find_outlier_edges(OUTLIER_SIGMA);
+                if !outliers.is_empty() {
+                    warn!(
+                        "{} edges are more than {OUTLIER_SIGMA} standard deviations longer than \
+                        the mean, check the input for broken coordinates",
+                        outliers.len()
+                    );
+                }
+                for (a, b, length) in outliers {
+                    debug!("Outlier edge ({a}, {b}): {length:.2} m");
+                }
+            }
+            info!(
+                "Degree histogram (degree: nodes): {:?}",
+                graph.graph().graph().degree_histogram()
+            );
+
             if let Some(pois) = pois {
                 panic!("Pois are not read correctly at the moment!");
                 // let mut poi_writer = PoiWriter::new(|_| true);
@@ -147,6 +420,14 @@ fn main() {
                 graph.add_node_pois(pois)
             }
 
+            if let Some(sample_size) = sample_spatial {
+                let pois = util::sample_spatial(graph.graph(), sample_size, &mut rng())
+                    .into_iter()
+                    .map(|node_id| (node_id, vec![]))
+                    .collect();
+                graph.add_node_pois(pois)
+            }
+
             let out_file = OpenOptions::new()
                 .read(true)
                 .write(true)
@@ -170,6 +451,11 @@ fn main() {
             epsilon,
             split_tree,
             merge_blocks,
+            validate,
+            max_depth,
+            store_paths,
+            r_tree_nodes,
+            single_file,
         } => {
             let oracle_file = out_file.unwrap_or_else(|| {
                 let mut out_file = in_file.clone();
@@ -189,16 +475,45 @@ fn main() {
                 graph.graph().edge_count()
             );
 
-            let mut oracles = OracleCollection::default();
+            if validate {
+                if let Err(err) = graph.graph().graph().validate() {
+                    panic!("Invalid graph: {err}");
+                }
+                info!("Graph is valid");
+            }
 
-            let split_trees = oracles
-                .build_for_nodes(
-                    graph.poi_nodes(),
+            let params = DefaultOracleParams {
+                merge_blocks,
+                max_depth,
+                store_paths,
+                parallel: true,
+            };
+            let split_trees = match r_tree_nodes {
+                RTreeNodes::Default => build_oracles(
+                    &graph,
                     epsilon,
-                    graph.graph(),
-                    DefaultOracleParams { merge_blocks },
-                )
-                .unwrap();
+                    params,
+                    DefaultParams,
+                    &oracle_file,
+                    single_file,
+                ),
+                RTreeNodes::Small => build_oracles(
+                    &graph,
+                    epsilon,
+                    params,
+                    SmallNodeParams,
+                    &oracle_file,
+                    single_file,
+                ),
+                RTreeNodes::Large => build_oracles(
+                    &graph,
+                    epsilon,
+                    params,
+                    LargeNodeParams,
+                    &oracle_file,
+                    single_file,
+                ),
+            };
 
             if split_tree {
                 for split_tree in split_trees.iter() {
@@ -217,35 +532,127 @@ fn main() {
                     split_tree.serialize(&mut rmp_serializer).unwrap();
                 }
             }
+        }
+        Commands::Query {
+            oracle_dir,
+            graph,
+            s_lat,
+            s_lon,
+            t_lat,
+            t_lon,
+            tolerance,
+            exact,
+            export_route,
+            dump_distances,
+        } => {
+            let graph_file = File::open(graph).unwrap();
+            let graph_mmap = unsafe { MmapOptions::new().map(&graph_file).unwrap() };
+            let mut rmp_deserializer = Deserializer::new(graph_mmap.as_ref());
+            let graph: PoiGraph<Poi> = PoiGraph::deserialize(&mut rmp_deserializer).unwrap();
+
+            let oracles: OracleCollection<RTreeGraphType<Poi>> = if oracle_dir.is_file() {
+                let file = BufReader::new(File::open(oracle_dir).unwrap());
+                OracleCollection::read_from(file).unwrap()
+            } else {
+                OracleCollection::load_dir(oracle_dir).unwrap()
+            };
+
+            let (s, s_value) = graph
+                .get_node_value_at(&coord! {x: s_lon, y: s_lat}, tolerance)
+                .unwrap();
+            let (t, t_value) = graph
+                .get_node_value_at(&coord! {x: t_lon, y: t_lat}, tolerance)
+                .unwrap();
+            info!("Snapped start to node {s} and target to node {t}");
+
+            // The representatives of a block pair approximate the detour over its poi.
+            let mut candidates: Vec<(usize, f64)> = oracles
+                .iter()
+                .filter_map(|(poi, oracle)| {
+                    let block_pair = oracle
+                        .get_block_pairs(s_value.get_coord(), t_value.get_coord())
+                        .into_iter()
+                        .next()?;
+                    Some((*poi, block_pair.values().d_sp + block_pair.values().d_pt))
+                })
+                .collect();
+            candidates.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+            println!("Oracle: {} pois", candidates.len());
+            for (poi, cost) in candidates.iter() {
+                println!("{poi} \t ~{cost:.2}");
+            }
+
+            if let Some(epsilon) = exact {
+                let mut exact = graph
+                    .beer_path_dijkstra_base(s, t, graph.poi_nodes(), epsilon)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .collect::<Vec<_>>();
+                exact.sort_by(|a, b| a.1.total_cmp(&b.1));
 
-            for oracle in oracles.iter() {
-                let mut file_name = oracle_file.file_stem().unwrap().to_os_string();
-                file_name.push(format!("_{}", oracle.0));
+                println!("Exact: {} pois", exact.len());
+                for (poi, cost) in exact.iter() {
+                    println!("{poi} \t {cost:.2}");
+                }
+            }
 
-                let mut oracle_file = oracle_file.clone();
-                oracle_file.set_file_name(file_name.as_os_str());
-                oracle_file.set_extension("omp");
+            if let Some(export_route) = export_route {
+                if let Some((poi, _)) = candidates.first() {
+                    let route = graph
+                        .dijkstra(s, FxHashSet::from_iter([*poi]), Direction::Outgoing)
+                        .path(*poi)
+                        .zip(
+                            graph
+                                .dijkstra(*poi, FxHashSet::from_iter([t]), Direction::Outgoing)
+                                .path(t),
+                        )
+                        .map(|(mut route, rest)| {
+                            let offset = route.cost();
+                            for target in rest.path.into_iter().skip(1) {
+                                route.push(Target::new(target.target(), *target.value() + offset));
+                            }
+                            route
+                        })
+                        .expect("poi is not on a path from start to target");
+
+                    let geojson = output::path_to_geojson(&route, graph.graph()).unwrap();
+                    std::fs::write(&export_route, geojson).unwrap();
+                    info!("Wrote route to {:?}", export_route);
+                } else {
+                    warn!(
+                        "No route over a poi from node {s} to node {t}, not writing {export_route:?}"
+                    );
+                }
+            }
 
-                let writer = BufWriter::new(File::create(oracle_file).unwrap());
-                let mut rmp_serializer = Serializer::new(writer);
-                oracle.1.serialize(&mut rmp_serializer).unwrap();
+            if let Some(dump_distances) = dump_distances {
+                let writer = BufWriter::new(File::create(&dump_distances).unwrap());
+                graph
+                    .dijkstra_full(s, Direction::Outgoing)
+                    .write_csv(writer)
+                    .unwrap();
+                info!("Wrote distances to {:?}", dump_distances);
             }
         }
         Commands::Bench {
             in_file,
             size,
+            time,
+            dijkstra,
             batch_size,
         } => {
-            if size {
-                struct Measurements(Vec<(f64, f64)>);
-                impl Display for Measurements {
-                    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                        self.0.iter().for_each(|e| {
-                            writeln!(f, "{} \t {}", e.0, e.1).unwrap_or_else(|e| debug!("{e}"));
-                        });
-                        Ok(())
-                    }
+            struct Measurements(Vec<(f64, f64)>);
+            impl Display for Measurements {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    self.0.iter().for_each(|e| {
+                        writeln!(f, "{} \t {}", e.0, e.1).unwrap_or_else(|e| debug!("{e}"));
+                    });
+                    Ok(())
                 }
+            }
+
+            if size {
                 println!(
                     "Merged: {}",
                     Measurements(bench::oracle_size_merge(
@@ -268,6 +675,77 @@ fn main() {
                     ))
                 );
             }
+
+            if time {
+                let build_time = bench::oracle_build_time(
+                    &in_file,
+                    &[
+                        0.05, 0.1, 0.2, 0.25, 0.3, 0.4, 0.5, 0.75, 1., 2., 3., 4., 5.,
+                    ],
+                    batch_size,
+                );
+                println!(
+                    "Build time: {}",
+                    Measurements(
+                        build_time
+                            .into_iter()
+                            .map(|(epsilon, duration)| (epsilon, duration.as_secs_f64()))
+                            .collect()
+                    )
+                );
+            }
+
+            if dijkstra {
+                println!("Dijkstra:");
+                for (frontier, duration) in bench::dijkstra_frontier_time(&in_file, batch_size) {
+                    println!("{frontier:?} \t {}", duration.as_secs_f64());
+                }
+            }
         }
     }
 }
+
+/// Builds the oracles for all pois of `graph` and writes each one to '<OUT_FILE>_<POI>.omp',
+/// or all of them to '<OUT_FILE>.ocmp' with `single_file`.
+///
+/// Returns the split-trees of the oracles.
+fn build_oracles<R>(
+    graph: &PoiGraph<Poi>,
+    epsilon: f64,
+    params: DefaultOracleParams,
+    r_tree_params: R,
+    oracle_file: &Path,
+    single_file: bool,
+) -> FxHashMap<usize, id_tree::Tree<(BlockPair<f64, f64>, bool)>>
+where
+    R: RTreeParams + Default,
+{
+    let (oracles, split_trees) =
+        pipeline::build_oracles(graph, epsilon, params, r_tree_params, |_| {}).unwrap();
+
+    if single_file {
+        let mut oracle_file = oracle_file.to_path_buf();
+        oracle_file.set_extension("ocmp");
+
+        let writer = BufWriter::new(File::create(&oracle_file).unwrap());
+        oracles.write_to(writer).unwrap();
+        info!("Wrote oracles to {}", oracle_file.display());
+
+        return split_trees;
+    }
+
+    for oracle in oracles.iter() {
+        let mut file_name = oracle_file.file_stem().unwrap().to_os_string();
+        file_name.push(format!("_{}", oracle.0));
+
+        let mut oracle_file = oracle_file.to_path_buf();
+        oracle_file.set_file_name(file_name.as_os_str());
+        oracle_file.set_extension("omp");
+
+        let writer = BufWriter::new(File::create(oracle_file).unwrap());
+        let mut rmp_serializer = Serializer::new(writer);
+        oracle.1.serialize(&mut rmp_serializer).unwrap();
+    }
+
+    split_trees
+}
@@ -5,11 +5,11 @@ use std::{
     rc::Rc,
 };
 
-use geo::{Coord, CoordFloat, Rect};
+use geo::{Contains, Coord, CoordFloat, Rect};
 use graph_rs::{
     CoordGraph, Graph,
     algorithms::dijkstra::{Dijkstra, ResultNode},
-    graph::Path,
+    graph::{Path, Target},
     types::Direction,
 };
 use log::trace;
@@ -36,6 +36,9 @@ where
     t_block: Rect<C>,
     poi_id: usize,
     values: Values<EV>,
+    /// Shortest path from `values.s` over the poi to `values.t`, see [BlockPair::path].
+    #[serde(default)]
+    path: Option<Path<EV>>,
 }
 
 impl<EV, C> BlockPair<EV, C>
@@ -54,6 +57,7 @@ where
             t_block,
             poi_id,
             values,
+            path: None,
         }
     }
 
@@ -77,9 +81,58 @@ where
         Rectangle::from_corners(self.t_block.min(), self.t_block.max())
     }
 
+    /// Like [BlockPair::s_block], but by value.
+    pub fn s_rect(&self) -> Rect<C> {
+        *self.s_block()
+    }
+
+    /// Like [BlockPair::t_block], but by value.
+    pub fn t_rect(&self) -> Rect<C> {
+        *self.t_block()
+    }
+
+    /// Returns `true` if `s` lies in the s-block and `t` in the t-block.
+    pub fn contains(&self, s: &Coord<C>, t: &Coord<C>) -> bool {
+        self.s_block.contains(s) && self.t_block.contains(t)
+    }
+
     pub fn values(&self) -> &Values<EV> {
         &self.values
     }
+
+    /// A beer path for the block pair, from a node in the s-block over the poi to a node in the
+    /// t-block. Only stored with [OracleParams::store_paths](super::OracleParams::store_paths).
+    ///
+    /// The values of the path are the costs from its first node.
+    pub fn path(&self) -> Option<&Path<EV>> {
+        self.path.as_ref()
+    }
+
+    /// Computes and stores the path over the poi between the nodes `values.s` and `values.t`.
+    pub(crate) fn store_path<G>(&mut self, graph: &G)
+    where
+        G: Graph<EV = EV> + Dijkstra,
+    {
+        let (s, t, poi) = (self.values.s, self.values.t, self.poi_id);
+        let to_poi = graph
+            .dijkstra(s, FxHashSet::from_iter([poi]), Direction::Outgoing)
+            .path(poi);
+        let from_poi = graph
+            .dijkstra(poi, FxHashSet::from_iter([t]), Direction::Outgoing)
+            .path(t);
+
+        self.path = to_poi.zip(from_poi).map(|(mut path, from_poi)| {
+            let offset = path.cost();
+            path.path.extend(
+                from_poi
+                    .path
+                    .into_iter()
+                    .skip(1)
+                    .map(|target| Target::new(target.target(), offset + *target.value())),
+            );
+            path
+        });
+    }
 }
 
 impl<EV, C> PartialEq for BlockPair<EV, C>
@@ -145,9 +198,14 @@ impl<T: FloatCore> Values<T> {
         let s: usize;
         let t: usize;
         {
+            // Isolated nodes are not reached from any other node.
             let mut points = (
-                graph.locate_in_envelope(s_block),
-                graph.locate_in_envelope(t_block),
+                graph
+                    .locate_in_envelope(s_block)
+                    .filter(|node| graph.degree(*node) > 0),
+                graph
+                    .locate_in_envelope(t_block)
+                    .filter(|node| graph.degree(*node) > 0),
             );
 
             let mut rng = rand::rng();
@@ -166,13 +224,12 @@ impl<T: FloatCore> Values<T> {
             s,
             t,
             epsilon,
-            d_st: d_s.path(t).unwrap().cost(),
-            d_sp: d_s.path(poi_id).unwrap().cost(),
+            d_st: d_s.cost(t).unwrap(),
+            d_sp: d_s.cost(poi_id).unwrap(),
             d_pt: graph
                 .dijkstra(poi_id, FxHashSet::from_iter([t]), Direction::Outgoing)
-                .path(t)
-                .unwrap()
-                .cost(),
+                .cost(t)
+                .unwrap(),
             r_af: graph.radius(s, s_block, Direction::Outgoing).unwrap(),
             r_ab: graph.radius(s, s_block, Direction::Incoming).unwrap(),
             r_bf: graph.radius(t, t_block, Direction::Outgoing).unwrap(),
@@ -233,13 +290,48 @@ mod test {
 
     use geo::{Coord, Rect};
     use graph_rs::{
-        graph::{csr::DirectedCsrGraph, rstar::RTreeGraph},
+        graph::{Path, csr::DirectedCsrGraph, rstar::RTreeGraph},
         types::CoordNode,
     };
     use serde::{Deserialize, Serialize};
     use serde_test::{Token, assert_ser_tokens};
 
-    use crate::oracle::block_pair::BlockPair;
+    use crate::oracle::block_pair::{BlockPair, Values};
+
+    fn block_pair(s_block: Rect<f64>, t_block: Rect<f64>) -> BlockPair<f64, f64> {
+        BlockPair {
+            s_block,
+            t_block,
+            poi_id: 0,
+            values: Values {
+                s: 0,
+                t: 0,
+                epsilon: 0.2,
+                d_st: 0.,
+                d_sp: 0.,
+                d_pt: 0.,
+                r_af: Path::new(vec![]),
+                r_ab: Path::new(vec![]),
+                r_bf: Path::new(vec![]),
+                r_bb: Path::new(vec![]),
+            },
+            path: None,
+        }
+    }
+
+    #[test]
+    fn rects() {
+        let s_block = Rect::new((0.5, 1.0), (1.0, 0.5));
+        let t_block = Rect::new((10., 9.), (11., 12.));
+        let block_pair = block_pair(s_block, t_block);
+
+        assert_eq!(block_pair.s_rect(), s_block);
+        assert_eq!(block_pair.t_rect(), t_block);
+        assert_eq!(block_pair.s_rect().min(), Coord { x: 0.5, y: 0.5 });
+
+        assert!(block_pair.contains(&Coord { x: 0.6, y: 0.8 }, &Coord { x: 10.5, y: 10. }));
+        assert!(!block_pair.contains(&Coord { x: 10.5, y: 10. }, &Coord { x: 0.6, y: 0.8 }));
+    }
 
     #[test]
     fn test_ser_de() {
@@ -11,11 +11,17 @@ use std::{
     thread, usize,
 };
 
-use geo::Coord;
+use geo::{
+    Centroid, Coord, HaversineDistance, HaversineIntermediate, MultiPoint, Point, Rect, coord,
+};
 use graph_rs::{
     CoordGraph, Coordinate, DirectedGraph, Graph,
     algorithms::dijkstra::{Dijkstra, DijkstraResult, ResultNode},
-    graph::{Path, Target, csr::DirectedCsrGraph, rstar::RTreeGraph},
+    graph::{
+        Path, Target,
+        csr::DirectedCsrGraph,
+        rstar::{RTreeGraph, haversine_envelope},
+    },
     types::Direction,
 };
 use log::{info, warn};
@@ -43,6 +49,10 @@ pub trait NodeTrait: Clone + Debug + Send + Sync {}
 
 pub type RTreeGraphType<T> = RTreeGraph<DirectedCsrGraph<f64, CoordNode<f64, T>>, f64>;
 
+fn exact_key(coord: &Coord) -> Coord<OrderedFloat<f64>> {
+    coord! {x: OrderedFloat(coord.x), y: OrderedFloat(coord.y)}
+}
+
 #[derive(Default, Serialize, Deserialize)]
 pub struct PoiGraph<NV>
 where
@@ -50,22 +60,32 @@ where
 {
     pub graph: RTreeGraphType<NV>,
     pub poi_nodes: FxHashSet<usize>,
+    /// Nodes by their exact coordinate, see [PoiGraph::build_exact_index].
+    #[serde(skip)]
+    exact_index: Option<FxHashMap<Coord<OrderedFloat<f64>>, usize>>,
 }
 
 impl<NV> PoiGraph<NV>
 where
     NV: NodeTrait + Serialize + DeserializeOwned,
 {
+    /// Creates a new `PoiGraph`, using all nodes with data as pois.
+    ///
+    /// Isolated nodes are left out, as they can not lie on any path.
     pub fn new(graph: RTreeGraphType<NV>) -> Self {
         let poi_nodes = graph
             .nodes_iter()
             .fold(HashSet::default(), |mut poi_nodes, node| {
-                if node.1.has_data() {
+                if node.1.has_data() && graph.degree(node.0) > 0 {
                     poi_nodes.insert(node.0);
                 }
                 poi_nodes
             });
-        Self { graph, poi_nodes }
+        Self {
+            graph,
+            poi_nodes,
+            exact_index: None,
+        }
     }
 
     pub fn add_node_poi(&mut self, mut node: (usize, Vec<NV>)) -> Option<&mut CoordNode<f64, NV>> {
@@ -85,28 +105,54 @@ where
     }
 
     pub fn add_coord_poi(&mut self, mut poi: CoordNode<f64, NV>) -> Result<(), Error> {
-        let nearest_node;
-        {
-            nearest_node = self
-                .graph
-                .nearest_node(poi.get_coord())
-                .ok_or(Error::NoValue(format!("quad graph empty")))?;
-        }
-        {
-            let node = self
-                .graph
-                .node_value_mut(nearest_node)
-                .ok_or(Error::NoValue(format!("node: {:?}", nearest_node)))?;
+        let (nearest_node, node) = self.nearest_node_value_mut(poi.get_coord())?;
+        node.append_data(poi.data_mut());
+        self.poi_nodes.insert(nearest_node);
 
-            info!("Found node: {}", &node);
+        Ok(())
+    }
 
-            node.append_data(poi.data_mut());
-            self.poi_nodes.insert(nearest_node);
+    /// Like [PoiGraph::add_coord_poi], but skips data equal to data already on the node.
+    ///
+    /// Returns the number of skipped duplicates.
+    pub fn add_coord_poi_dedup(&mut self, mut poi: CoordNode<f64, NV>) -> Result<usize, Error>
+    where
+        NV: PartialEq,
+    {
+        let (nearest_node, node) = self.nearest_node_value_mut(poi.get_coord())?;
+        let mut duplicates = 0;
+        for data in poi.data_mut().drain(..) {
+            if node.data().contains(&data) {
+                duplicates += 1;
+            } else {
+                node.push_data(data);
+            }
         }
+        self.poi_nodes.insert(nearest_node);
 
-        Ok(())
+        Ok(duplicates)
+    }
+
+    /// Returns the node nearest to `coord` and its value.
+    fn nearest_node_value_mut(
+        &mut self,
+        coord: &Coord<f64>,
+    ) -> Result<(usize, &mut CoordNode<f64, NV>), Error> {
+        let nearest_node = self
+            .graph
+            .nearest_node(coord)
+            .ok_or(Error::NoValue(format!("graph empty")))?;
+        let node = self
+            .graph
+            .node_value_mut(nearest_node)
+            .ok_or(Error::NoValue(format!("node: {:?}", nearest_node)))?;
+
+        info!("Found node: {}", &node);
+
+        Ok((nearest_node, node))
     }
 
+    /// Returns the node nearest to `coord` within `tolerance` meters and its value.
     pub fn get_node_value_at(
         &self,
         coord: &Coord<f64>,
@@ -132,11 +178,134 @@ where
 
     pub fn add_coord_pois(&mut self, pois: &[CoordNode<f64, NV>]) -> Result<(), Vec<Error>> {
         info!("Adding {} pois", pois.len());
-        pois.iter().for_each(|poi| {
-            self.add_coord_poi(poi.to_owned());
-        });
+        let errors: Vec<Error> = pois
+            .iter()
+            .filter_map(|poi| self.add_coord_poi(poi.to_owned()).err())
+            .collect();
 
-        Ok(())
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Like [PoiGraph::add_coord_pois], but skips pois equal to a poi already on their node,
+    /// see [PoiGraph::add_coord_poi_dedup].
+    ///
+    /// Returns the number of skipped duplicates.
+    pub fn add_coord_pois_dedup(&mut self, pois: &[CoordNode<f64, NV>]) -> Result<usize, Vec<Error>>
+    where
+        NV: PartialEq,
+    {
+        info!("Adding {} pois", pois.len());
+        let mut duplicates = 0;
+        let mut errors = Vec::new();
+        for poi in pois {
+            match self.add_coord_poi_dedup(poi.to_owned()) {
+                Ok(count) => duplicates += count,
+                Err(err) => errors.push(err),
+            }
+        }
+        info!("Skipped {duplicates} duplicate pois");
+
+        if errors.is_empty() {
+            Ok(duplicates)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Snaps each poi to the nearest non-isolated node within `tolerance` meters and adds it to
+    /// that node.
+    ///
+    /// Distances are haversine distances, like in [CoordGraph::nearest_node_bound]. The pois
+    /// are grouped into grid cells about `tolerance` high, so the spatial index is queried once
+    /// per cell instead of once per poi.
+    /// On failure returns the index into `pois` of every poi without a node in range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tolerance` is not positive.
+    pub fn add_coord_pois_batch(
+        &mut self,
+        pois: &[CoordNode<f64, NV>],
+        tolerance: f64,
+    ) -> Result<(), Vec<(usize, Error)>> {
+        assert!(tolerance > 0., "tolerance must be positive");
+        info!("Adding {} pois", pois.len());
+
+        // Degrees of latitude spanned by `tolerance` meters, the same everywhere.
+        let cell_size = haversine_envelope(Point::new(0., 0.), tolerance).height() / 2.;
+        let mut cells: FxHashMap<(i64, i64), Vec<usize>> = FxHashMap::default();
+        for (index, poi) in pois.iter().enumerate() {
+            let coord = poi.get_coord();
+            let cell = (
+                (coord.x / cell_size).floor() as i64,
+                (coord.y / cell_size).floor() as i64,
+            );
+            cells.entry(cell).or_default().push(index);
+        }
+
+        let mut errors = Vec::new();
+        for indices in cells.into_values() {
+            // Covers every node within `tolerance` of a poi in the cell, degrees of longitude
+            // shrink toward the poles.
+            let envelope = indices
+                .iter()
+                .map(|index| haversine_envelope(Point::from(*pois[*index].get_coord()), tolerance))
+                .reduce(|a, b| {
+                    Rect::new(
+                        coord! {x: a.min().x.min(b.min().x), y: a.min().y.min(b.min().y)},
+                        coord! {x: a.max().x.max(b.max().x), y: a.max().y.max(b.max().y)},
+                    )
+                })
+                .expect("cells are not empty");
+            let candidates: Vec<(usize, Point<f64>)> = self
+                .graph
+                .locate_in_envelope(&envelope)
+                .filter(|node| self.graph.degree(*node) > 0)
+                .filter_map(|node| {
+                    Some((node, Point::from(*self.graph.node_value(node)?.get_coord())))
+                })
+                .collect();
+
+            for index in indices {
+                let poi = &pois[index];
+                let point = Point::from(*poi.get_coord());
+                let nearest = candidates
+                    .iter()
+                    .map(|(node, coord)| (*node, point.haversine_distance(coord)))
+                    .filter(|(_, distance)| *distance <= tolerance)
+                    .min_by(|a, b| a.1.total_cmp(&b.1));
+
+                let Some((node, _)) = nearest else {
+                    errors.push((
+                        index,
+                        Error::NoValue(format!(
+                            "no node at {:?} with tolerance {}",
+                            poi.get_coord().x_y(),
+                            tolerance
+                        )),
+                    ));
+                    continue;
+                };
+
+                let node_value = self
+                    .graph
+                    .node_value_mut(node)
+                    .expect("node from the spatial index is in the graph");
+                node_value.append_data(&mut poi.data().clone());
+                self.poi_nodes.insert(node);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            errors.sort_by_key(|(index, _)| *index);
+            Err(errors)
+        }
     }
 
     pub fn poi_nodes(&self) -> &FxHashSet<usize> {
@@ -149,10 +318,496 @@ impl<T: NodeTrait> PoiGraph<T> {
         &self.graph
     }
 
+    /// Gives mutable access to the graph, dropping the exact index.
     pub fn graph_mut(&mut self) -> &mut RTreeGraphType<T> {
+        self.exact_index = None;
         &mut self.graph
     }
 
+    /// Indexes all nodes by their exact coordinate, which speeds up [PoiGraph::node_at_exact].
+    ///
+    /// The index is not serialized. [PoiGraph::add_node] keeps it up to date, while
+    /// [PoiGraph::remove_node] and [PoiGraph::graph_mut] drop it, as they may change node ids.
+    pub fn build_exact_index(&mut self) {
+        let mut index = FxHashMap::default();
+        for (node, value) in self.graph.nodes_iter() {
+            index.entry(exact_key(value.get_coord())).or_insert(node);
+        }
+
+        self.exact_index = Some(index);
+    }
+
+    /// Returns the node at exactly `coord`.
+    ///
+    /// Unlike a nearest neighbour search this never returns a node somewhere else, e.g. when
+    /// attaching pois which are vertices of the same source data. If several nodes share
+    /// `coord` the one with the lowest id is returned when the index is built.
+    pub fn node_at_exact(&self, coord: &Coord) -> Option<usize> {
+        match &self.exact_index {
+            Some(index) => index.get(&exact_key(coord)).copied(),
+            None => self
+                .graph
+                .nearest_node(coord)
+                .filter(|node| self.graph.node_coord(*node).as_ref() == Some(coord)),
+        }
+    }
+
+    /// Sum of all edge weights in meters.
+    ///
+    /// Edges which also exist in the opposite direction are only counted once,
+    /// so this is the length of the road network rather than of the directed graph.
+    pub fn total_edge_length(&self) -> f64 {
+        self.graph
+            .nodes_iter()
+            .flat_map(|(node, _)| self.graph.out_neighbors(node).map(move |t| (node, t)))
+            .map(|(node, target)| {
+                let bidirectional = self
+                    .graph
+                    .out_neighbors(target.target())
+                    .any(|t| t.target() == node);
+                if bidirectional {
+                    *target.value() / 2.
+                } else {
+                    *target.value()
+                }
+            })
+            .sum()
+    }
+
+    /// Returns the centroid of the coordinates of `pois`, or `None` if none of them is in the
+    /// graph.
+    pub fn poi_centroid(&self, pois: &FxHashSet<usize>) -> Option<Coord<f64>> {
+        let points: MultiPoint = pois
+            .iter()
+            .filter_map(|poi| Some(Point::from(*self.graph.node_value(*poi)?.get_coord())))
+            .collect();
+
+        points.centroid().map(Coord::from)
+    }
+
+    /// Groups the pois into at most `k` clusters of nearby pois with k-means.
+    ///
+    /// Coordinates are compared as euclidean, which is fine for clusters of a city. The
+    /// initial centers are spread out by picking the poi farthest from all centers so far,
+    /// so the result is deterministic. Each cluster is sorted by node id.
+    pub fn poi_clusters(&self, k: usize) -> Vec<Vec<usize>> {
+        const MAX_ITERATIONS: usize = 100;
+
+        let mut pois: Vec<(usize, Coord<f64>)> = self
+            .poi_nodes
+            .iter()
+            .filter_map(|poi| Some((*poi, *self.graph.node_value(*poi)?.get_coord())))
+            .collect();
+        pois.sort_unstable_by_key(|(poi, _)| *poi);
+        let Some(first) = pois.first().filter(|_| k > 0) else {
+            return vec![];
+        };
+
+        let distance_2 = |a: Coord<f64>, b: Coord<f64>| {
+            let delta = a - b;
+            delta.x * delta.x + delta.y * delta.y
+        };
+        let nearest = |centers: &[Coord<f64>], coord: Coord<f64>| {
+            (0..centers.len())
+                .min_by(|a, b| {
+                    distance_2(centers[*a], coord).total_cmp(&distance_2(centers[*b], coord))
+                })
+                .unwrap()
+        };
+
+        let mut centers = vec![first.1];
+        while centers.len() < k.min(pois.len()) {
+            let farthest = pois
+                .iter()
+                .map(|(_, coord)| *coord)
+                .max_by(|a, b| {
+                    let a = distance_2(centers[nearest(&centers, *a)], *a);
+                    a.total_cmp(&distance_2(centers[nearest(&centers, *b)], *b))
+                })
+                .unwrap();
+            centers.push(farthest);
+        }
+
+        let mut assignment = vec![usize::MAX; pois.len()];
+        for _ in 0..MAX_ITERATIONS {
+            let next: Vec<usize> = pois
+                .iter()
+                .map(|(_, coord)| nearest(&centers, *coord))
+                .collect();
+            if next == assignment {
+                break;
+            }
+            assignment = next;
+
+            for (cluster, center) in centers.iter_mut().enumerate() {
+                let members: MultiPoint = pois
+                    .iter()
+                    .zip(&assignment)
+                    .filter(|(_, assigned)| **assigned == cluster)
+                    .map(|((_, coord), _)| Point::from(*coord))
+                    .collect();
+                // An empty cluster keeps its center.
+                if let Some(centroid) = members.centroid() {
+                    *center = centroid.into();
+                }
+            }
+        }
+
+        let mut clusters = vec![vec![]; centers.len()];
+        for ((poi, _), cluster) in pois.iter().zip(assignment) {
+            clusters[cluster].push(*poi);
+        }
+        clusters.retain(|cluster| !cluster.is_empty());
+        clusters
+    }
+
+    /// Adds a node to the graph and the spatial index.
+    ///
+    /// The node is isolated, so it only becomes a poi once an edge is added to it.
+    pub fn add_node(&mut self, node: CoordNode<f64, T>) -> usize {
+        let key = exact_key(node.get_coord());
+        let id = self.graph.add_node(node);
+        if let Some(index) = &mut self.exact_index {
+            index.entry(key).or_insert(id);
+        }
+
+        id
+    }
+
+    /// Adds an edge, registering endpoints with data as pois.
+    pub fn add_edge(&mut self, a: usize, b: usize, weight: f64) -> bool {
+        if !self.graph.add_edge(a, b, weight) {
+            return false;
+        }
+
+        for node in [a, b] {
+            if self
+                .graph
+                .node_value(node)
+                .is_some_and(|value| value.has_data())
+            {
+                self.poi_nodes.insert(node);
+            }
+        }
+
+        true
+    }
+
+    /// Removes an edge. Endpoints which become isolated are no longer pois.
+    pub fn remove_edge(&mut self, edge: (usize, usize)) -> Option<f64> {
+        let weight = self.graph.remove_edge(edge)?;
+
+        for node in [edge.0, edge.1] {
+            if self.graph.degree(node) == 0 {
+                self.poi_nodes.remove(&node);
+            }
+        }
+
+        Some(weight)
+    }
+
+    /// Removes a node together with its edges.
+    ///
+    /// All node ids after `node` shift down by one, the pois are remapped accordingly.
+    pub fn remove_node(&mut self, node: usize) -> Option<CoordNode<f64, T>> {
+        if node >= self.graph.node_count() {
+            return None;
+        }
+
+        let neighbors: Vec<usize> = self
+            .graph
+            .out_neighbors(node)
+            .chain(self.graph.in_neighbors(node))
+            .map(|target| target.target())
+            .filter(|neighbor| *neighbor != node)
+            .collect();
+
+        let value = self.graph.remove_node(node)?;
+        self.exact_index = None;
+
+        self.poi_nodes = self
+            .poi_nodes
+            .iter()
+            .filter(|poi| **poi != node)
+            .map(|poi| if *poi > node { poi - 1 } else { *poi })
+            .collect();
+
+        for neighbor in neighbors {
+            let neighbor = if neighbor > node {
+                neighbor - 1
+            } else {
+                neighbor
+            };
+            if self.graph.degree(neighbor) == 0 {
+                self.poi_nodes.remove(&neighbor);
+            }
+        }
+
+        Some(value)
+    }
+
+    /// Merges ways which were mapped twice, e.g. the two lines of a dual carriageway.
+    ///
+    /// An edge is a duplicate of another one if both its endpoints are within `tolerance`
+    /// meters of the other edge's endpoints and the edges are at most about 11° apart. The
+    /// endpoints of the duplicate are merged into the ones of the other edge, moving over
+    /// their edges and data. Moved edges are weighted with their new haversine length. This
+    /// is lossy, so every merge is logged.
+    ///
+    /// Nodes connected by a path of at most three times `tolerance` are on the same way and
+    /// never merged, so a densely sampled way is not folded onto itself.
+    ///
+    /// Returns the number of removed nodes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tolerance` is not positive.
+    pub fn merge_parallel_ways(&mut self, tolerance: f64) -> usize {
+        // Cosine of the largest angle between two parallel edges.
+        const PARALLEL_COS: f64 = 0.98;
+
+        fn find(parent: &mut [usize], mut node: usize) -> usize {
+            while parent[node] != node {
+                parent[node] = parent[parent[node]];
+                node = parent[node];
+            }
+            node
+        }
+
+        assert!(tolerance > 0., "tolerance must be positive");
+
+        let coord = |node| *self.graph.node_value(node).unwrap().get_coord();
+        let distance =
+            |a: usize, b: usize| Point::from(coord(a)).haversine_distance(&Point::from(coord(b)));
+        let near = |node| {
+            let envelope = haversine_envelope(Point::from(coord(node)), tolerance);
+            self.graph
+                .locate_in_envelope(&envelope)
+                .filter(|other| *other != node && distance(node, *other) <= tolerance)
+                .collect::<Vec<_>>()
+        };
+        let parallel = |(a, b): (usize, usize), (c, d): (usize, usize)| {
+            let (u, v): (Coord<f64>, Coord<f64>) = (coord(b) - coord(a), coord(d) - coord(c));
+            let length = (u.x * u.x + u.y * u.y).sqrt() * (v.x * v.x + v.y * v.y).sqrt();
+            length > 0. && (u.x * v.x + u.y * v.y) / length >= PARALLEL_COS
+        };
+        // Nodes reachable from `node` over edges in either direction within the length limit.
+        let along = |node: usize| {
+            let mut reached = FxHashMap::from_iter([(node, 0.)]);
+            let mut stack = vec![(node, 0.)];
+            while let Some((node, length)) = stack.pop() {
+                let neighbors = self
+                    .graph
+                    .out_neighbors(node)
+                    .chain(self.graph.in_neighbors(node))
+                    .map(|target| target.target());
+                for neighbor in neighbors {
+                    let length = length + distance(node, neighbor);
+                    if length <= 3. * tolerance
+                        && reached
+                            .get(&neighbor)
+                            .is_none_or(|reached| length < *reached)
+                    {
+                        reached.insert(neighbor, length);
+                        stack.push((neighbor, length));
+                    }
+                }
+            }
+            reached
+        };
+
+        let mut parent: Vec<usize> = (0..self.graph.node_count()).collect();
+        for (a, _) in self.graph.nodes_iter() {
+            let near_a = near(a);
+            if near_a.is_empty() {
+                continue;
+            }
+            let along_a = along(a);
+            for b in self.graph.out_neighbors(a).map(|target| target.target()) {
+                let near_b = near(b);
+                let along_b = along(b);
+                for &c in near_a.iter().filter(|c| !along_a.contains_key(c)) {
+                    let neighbors = self
+                        .graph
+                        .out_neighbors(c)
+                        .chain(self.graph.in_neighbors(c))
+                        .map(|target| target.target());
+                    for d in neighbors.filter(|d| near_b.contains(d) && !along_b.contains_key(d)) {
+                        if !parallel((a, b), (c, d)) {
+                            continue;
+                        }
+                        let (rep_a, rep_c) = (find(&mut parent, a), find(&mut parent, c));
+                        let (rep_b, rep_d) = (find(&mut parent, b), find(&mut parent, d));
+                        let already_merged = rep_a == rep_c && rep_b == rep_d;
+                        // Merging would collapse an edge into a single node.
+                        let collapses = [rep_a, rep_c]
+                            .iter()
+                            .any(|rep| [rep_b, rep_d].contains(rep));
+                        if already_merged || collapses {
+                            continue;
+                        }
+
+                        info!("Merging way ({c}, {d}) into parallel way ({a}, {b})");
+                        parent[rep_a.max(rep_c)] = rep_a.min(rep_c);
+                        parent[rep_b.max(rep_d)] = rep_b.min(rep_d);
+                    }
+                }
+            }
+        }
+
+        let mut removed = vec![false; parent.len()];
+        let mut neighbors = Vec::new();
+        for node in 0..parent.len() {
+            let rep = find(&mut parent, node);
+            if rep == node {
+                continue;
+            }
+
+            // The edges keep their other endpoint and are weighted with their new length.
+            let rep_point = Point::from(*self.graph.node_value(rep).unwrap().get_coord());
+            let length = |other: usize| {
+                rep_point.haversine_distance(&Point::from(
+                    *self.graph.node_value(other).unwrap().get_coord(),
+                ))
+            };
+            let out_edges: Vec<(usize, f64)> = self
+                .graph
+                .out_neighbors(node)
+                .map(|target| (target.target(), length(target.target())))
+                .collect();
+            let in_edges: Vec<(usize, f64)> = self
+                .graph
+                .in_neighbors(node)
+                .map(|target| (target.target(), length(target.target())))
+                .collect();
+
+            let mut data = std::mem::take(self.graph.node_value_mut(node).unwrap().data_mut());
+            let moves_data = !data.is_empty();
+            self.graph
+                .node_value_mut(rep)
+                .unwrap()
+                .append_data(&mut data);
+
+            for &(target, weight) in &out_edges {
+                if target != rep && target != node && self.add_edge(rep, target, weight) {
+                    self.graph
+                        .copy_edge_attributes((rep, target), (node, target));
+                }
+            }
+            for &(source, weight) in &in_edges {
+                if source != rep && source != node && self.add_edge(source, rep, weight) {
+                    self.graph
+                        .copy_edge_attributes((source, rep), (source, node));
+                }
+            }
+            if moves_data && self.graph.degree(rep) > 0 {
+                self.poi_nodes.insert(rep);
+            }
+
+            info!("Merged node {node} into node {rep}");
+            neighbors.extend(out_edges.into_iter().chain(in_edges).map(|(node, _)| node));
+            removed[node] = true;
+        }
+
+        let merged = removed.iter().filter(|removed| **removed).count();
+        if merged == 0 {
+            return 0;
+        }
+
+        // Removing the nodes one by one would shift the CSRs and rebuild the r-tree each time.
+        let graph = std::mem::take(&mut self.graph).into_graph();
+        self.graph = RTreeGraph::new_from_graph(graph.filter(|(node, _)| !removed[*node]));
+        self.exact_index = None;
+
+        let new_ids: Vec<usize> = removed
+            .iter()
+            .scan(0, |kept, removed| {
+                let id = *kept;
+                if !removed {
+                    *kept += 1;
+                }
+                Some(id)
+            })
+            .collect();
+        self.poi_nodes = self
+            .poi_nodes
+            .iter()
+            .filter(|poi| !removed[**poi])
+            .map(|poi| new_ids[*poi])
+            .collect();
+        for neighbor in neighbors.into_iter().filter(|node| !removed[*node]) {
+            if self.graph.degree(new_ids[neighbor]) == 0 {
+                self.poi_nodes.remove(&new_ids[neighbor]);
+            }
+        }
+
+        merged
+    }
+
+    /// Splits every edge longer than `max_len` meters into pieces of equal length, no longer
+    /// than `max_len`.
+    ///
+    /// The new nodes are appended along the great circle between the endpoints, so no node id
+    /// changes. An edge and its reverse share the new nodes, their weights are split evenly.
+    ///
+    /// Returns the number of added nodes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_len` is not positive.
+    pub fn subdivide_edges(&mut self, max_len: f64) -> usize {
+        assert!(max_len > 0., "max_len must be positive");
+
+        let long_edges: Vec<(usize, usize, f64)> = self
+            .graph
+            .nodes_iter()
+            .flat_map(|(node, _)| self.graph.out_neighbors(node).map(move |t| (node, t)))
+            .filter(|(node, target)| *node != target.target() && *target.value() > max_len)
+            .map(|(node, target)| (node, target.target(), *target.value()))
+            .collect();
+
+        let mut split = FxHashSet::default();
+        let mut added = 0;
+        for (a, b, weight) in long_edges {
+            if !split.insert((a.min(b), a.max(b))) {
+                continue;
+            }
+
+            let reverse = self
+                .graph
+                .out_neighbors(b)
+                .find(|target| target.target() == a)
+                .map(|target| *target.value());
+            let pieces = (weight.max(reverse.unwrap_or(0.)) / max_len).ceil() as usize;
+
+            let start = Point::from(*self.graph.node_value(a).unwrap().get_coord());
+            let end = Point::from(*self.graph.node_value(b).unwrap().get_coord());
+            let mut path = vec![a];
+            for piece in 1..pieces {
+                let coord = start.haversine_intermediate(&end, piece as f64 / pieces as f64);
+                path.push(self.add_node(CoordNode::new(coord.into(), vec![])));
+            }
+            path.push(b);
+            added += pieces - 1;
+
+            // The pieces take the provenance and class of the edge before it is removed.
+            for edge in path.windows(2) {
+                self.add_edge(edge[0], edge[1], weight / pieces as f64);
+                self.graph.copy_edge_attributes((edge[0], edge[1]), (a, b));
+            }
+            self.graph.remove_edge((a, b));
+            if let Some(reverse) = reverse {
+                for edge in path.windows(2) {
+                    self.add_edge(edge[1], edge[0], reverse / pieces as f64);
+                    self.graph.copy_edge_attributes((edge[1], edge[0]), (b, a));
+                }
+                self.graph.remove_edge((b, a));
+            }
+        }
+
+        added
+    }
+
     pub fn dijkstra(
         &self,
         start_node: usize,
@@ -190,12 +845,11 @@ impl<T: NodeTrait> PoiGraph<T> {
                 FxHashSet::from_iter([end_node]),
                 Direction::Outgoing,
             )
-            .get(end_node)?
-            .cost()
+            .cost(end_node)?
             * (1. + epsilon);
 
         for poi in pois {
-            let cost = start_result.get(*poi)?.cost() + end_result.get(*poi)?.cost();
+            let cost = start_result.cost(*poi)? + end_result.cost(*poi)?;
             if cost < bound {
                 result.insert(*poi, cost);
             }
@@ -405,12 +1059,16 @@ where
         let poi_nodes = graph
             .nodes_iter()
             .fold(HashSet::default(), |mut poi_nodes, node| {
-                if node.1.has_data() {
+                if node.1.has_data() && graph.degree(node.0) > 0 {
                     poi_nodes.insert(node.0);
                 }
                 poi_nodes
             });
-        Self { graph, poi_nodes }
+        Self {
+            graph,
+            poi_nodes,
+            exact_index: None,
+        }
     }
 }
 
@@ -439,21 +1097,39 @@ impl<T: FloatCore + Debug> BeerPathResult<T> {
         Some(start_path)
     }
 
+    /// Returns the cost of the beer path through `poi`.
+    pub fn cost(&self, poi: usize) -> Option<T> {
+        if !self.pois.contains(&poi) {
+            return None;
+        }
+
+        Some(self.start_result.cost(poi)? + self.end_result.cost(poi)?)
+    }
+
     pub fn shortest_path(&self) -> Option<Path<T>> {
-        let shortest = self.pois.iter().fold((0, T::zero()), |shortest, poi| {
-            if let Some(start) = self.start_result.get(*poi)
-                && let Some(end) = self.end_result.get(*poi)
-            {
-                let cost = *start.cost() + *end.cost();
-                if shortest.1 > cost {
-                    return (*poi, cost);
-                }
-            }
-            shortest
-        });
+        let shortest = self
+            .pois
+            .iter()
+            .filter_map(|poi| Some((*poi, self.cost(*poi)?)))
+            .min_by_key(|(poi, cost)| (OrderedFloat(*cost), *poi))?;
 
         self.path(shortest.0)
     }
+
+    /// Returns the `k` POIs with the cheapest beer paths and their costs, cheapest first.
+    ///
+    /// Ties are broken by the node id, so the first POI is the one of [Self::shortest_path].
+    pub fn ranked(&self, k: usize) -> Vec<(usize, T)> {
+        let mut ranked: Vec<_> = self
+            .pois
+            .iter()
+            .filter_map(|poi| Some((*poi, self.cost(*poi)?)))
+            .collect();
+        ranked.sort_unstable_by_key(|(poi, cost)| (OrderedFloat(*cost), *poi));
+        ranked.truncate(k);
+
+        ranked
+    }
 }
 
 #[derive(Debug)]
@@ -473,12 +1149,417 @@ impl std::error::Error for Error {}
 
 #[cfg(test)]
 mod test {
+    use geo::{HaversineDistance, Point, coord};
     use geozero::geojson::read_geojson;
-    use graph_rs::graph::rstar::RTreeGraph;
+    use graph_rs::{
+        CoordGraph, DirectedGraph, Graph,
+        algorithms::dijkstra::DijkstraResult,
+        graph::{Path, csr::DirectedCsrGraph, rstar::RTreeGraph},
+        types::{Direction, EdgeClass},
+    };
+    use rustc_hash::FxHashSet;
 
     use crate::{
         input::geo_zero::GraphWriter,
-        oracle::{self, PoiGraph},
-        types::Poi,
+        oracle::{self, BeerPathResult, PoiGraph},
+        types::{Amenity, CoordNode, Poi},
     };
+
+    #[test]
+    fn poi_centroid() {
+        let mut graph = DirectedCsrGraph::default();
+        for (x, y) in [(0., 0.), (2., 0.), (1., 3.), (10., 10.)] {
+            graph.add_node(CoordNode::new(coord! {x: x, y: y}, vec![]));
+        }
+        let graph: PoiGraph<Poi> = PoiGraph::new(RTreeGraph::new_from_graph(graph));
+
+        assert_eq!(
+            graph.poi_centroid(&FxHashSet::from_iter([0, 1, 2])),
+            Some(coord! {x: 1., y: 1.})
+        );
+        assert_eq!(
+            graph.poi_centroid(&FxHashSet::from_iter([3, 7])),
+            Some(coord! {x: 10., y: 10.})
+        );
+        assert_eq!(graph.poi_centroid(&FxHashSet::default()), None);
+    }
+
+    #[test]
+    fn poi_clusters() {
+        let mut graph = DirectedCsrGraph::default();
+        for (x, y) in [(0., 0.), (10., 10.), (0.1, 0.), (10., 10.1), (0., 0.1)] {
+            graph.add_node(CoordNode::new(coord! {x: x, y: y}, vec![]));
+        }
+        let mut graph: PoiGraph<Poi> = PoiGraph::new(RTreeGraph::new_from_graph(graph));
+        graph.add_node_pois((0..5).map(|node| (node, vec![])).collect());
+
+        let mut clusters = graph.poi_clusters(2);
+        clusters.sort();
+        assert_eq!(clusters, vec![vec![0, 2, 4], vec![1, 3]]);
+
+        assert_eq!(graph.poi_clusters(1), vec![vec![0, 1, 2, 3, 4]]);
+        assert_eq!(graph.poi_clusters(10).len(), 5);
+        assert!(graph.poi_clusters(0).is_empty());
+    }
+
+    #[test]
+    fn subdivide_edges() {
+        let mut graph = DirectedCsrGraph::default();
+        for x in [0., 0.003, 0.004] {
+            graph.add_node(CoordNode::new(coord! {x: x, y: 0.}, vec![]));
+        }
+        graph.add_edge(0, 1, 300.);
+        graph.add_edge(1, 0, 300.);
+        graph.add_edge(1, 2, 50.);
+        graph.set_edge_classes(vec![
+            (0, 1, EdgeClass::Footpath),
+            (1, 0, EdgeClass::Cycleway),
+            (1, 2, EdgeClass::Road),
+        ]);
+
+        let mut graph: PoiGraph<Poi> = PoiGraph::new(RTreeGraph::new_from_graph(graph));
+        assert_eq!(graph.subdivide_edges(100.), 2);
+
+        assert_eq!(graph.graph().node_count(), 5);
+        assert_eq!(graph.graph().edge_count(), 7);
+        assert_eq!(graph.total_edge_length(), 350.);
+        for (a, b) in [(0, 3), (3, 4), (4, 1), (1, 4), (4, 3), (3, 0)] {
+            let weight = graph
+                .graph()
+                .out_neighbors(a)
+                .find(|target| target.target() == b)
+                .map(|target| *target.value());
+            assert_eq!(weight, Some(100.), "edge ({a}, {b})");
+            let class = if [(0, 3), (3, 4), (4, 1)].contains(&(a, b)) {
+                EdgeClass::Footpath
+            } else {
+                EdgeClass::Cycleway
+            };
+            assert_eq!(graph.graph().graph().edge_class(a, b), Some(class));
+            assert!(
+                graph
+                    .graph()
+                    .in_neighbors(b)
+                    .any(|target| target.target() == a)
+            );
+        }
+        assert!(
+            graph
+                .graph()
+                .out_neighbors(0)
+                .all(|target| target.target() != 1)
+        );
+
+        let coord = graph.graph().node_value(3).unwrap().get_coord();
+        assert!((coord.x - 0.001).abs() < 1e-9 && coord.y.abs() < 1e-9);
+        assert_eq!(
+            graph.graph().nearest_node(&coord! {x: 0.0021, y: 0.}),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn beer_path_result() {
+        let mut graph = DirectedCsrGraph::default();
+        for x in 0..3 {
+            graph.add_node(CoordNode::new(coord! {x: x as f64, y: 0.}, vec![]));
+        }
+        for (a, b) in [(0, 1), (1, 2)] {
+            graph.add_edge(a, b, 1.);
+            graph.add_edge(b, a, 1.);
+        }
+        let graph: PoiGraph<Poi> = PoiGraph::new(RTreeGraph::new_from_graph(graph));
+
+        // graph-rs and the oracle module share one result type with f64 costs.
+        let start_result: DijkstraResult<f64> = graph.dijkstra_full(0, Direction::Outgoing);
+        let end_result: DijkstraResult<f64> = graph.dijkstra_full(2, Direction::Incoming);
+        let to_poi: Path<f64> = start_result.path(1).unwrap();
+
+        let result = BeerPathResult {
+            start_result,
+            end_result,
+            pois: FxHashSet::from_iter([1]),
+        };
+        let path: Path<f64> = result.path(1).unwrap();
+
+        let nodes: Vec<usize> = path.path.iter().map(|target| target.target()).collect();
+        assert_eq!(nodes.first(), Some(&0));
+        assert_eq!(nodes.last(), Some(&2));
+        assert!(nodes.starts_with(&[0, 1]));
+        assert_eq!(to_poi.cost(), 1.);
+        assert_eq!(result.cost(1), Some(2.));
+        assert_eq!(
+            result.shortest_path().map(|path| path.path.len()),
+            Some(nodes.len())
+        );
+    }
+
+    #[test]
+    fn ranked() {
+        let mut graph = DirectedCsrGraph::default();
+        for x in 0..5 {
+            graph.add_node(CoordNode::new(coord! {x: x as f64, y: 0.}, vec![]));
+        }
+        for a in 0..4 {
+            graph.add_edge(a, a + 1, 1.);
+            graph.add_edge(a + 1, a, 1.);
+        }
+        let graph: PoiGraph<Poi> = PoiGraph::new(RTreeGraph::new_from_graph(graph));
+
+        let result = BeerPathResult {
+            start_result: graph.dijkstra_full(0, Direction::Outgoing),
+            end_result: graph.dijkstra_full(2, Direction::Incoming),
+            pois: FxHashSet::from_iter([4, 1, 3]),
+        };
+
+        assert_eq!(result.ranked(2), vec![(1, 2.), (3, 4.)]);
+        assert_eq!(result.ranked(5), vec![(1, 2.), (3, 4.), (4, 6.)]);
+        assert!(result.ranked(0).is_empty());
+
+        let (first, _) = result.ranked(1)[0];
+        let shortest = result.shortest_path().unwrap();
+        assert_eq!(shortest, result.path(first).unwrap());
+    }
+
+    #[test]
+    fn node_at_exact() {
+        let mut graph = DirectedCsrGraph::default();
+        for x in 0..3 {
+            graph.add_node(CoordNode::new(coord! {x: x as f64 * 0.001, y: 0.}, vec![]));
+        }
+        graph.add_edge(0, 1, 1.);
+        graph.add_edge(1, 2, 1.);
+
+        let mut graph: PoiGraph<Poi> = PoiGraph::new(RTreeGraph::new_from_graph(graph));
+        let exact = coord! {x: 0.001, y: 0.};
+        let close = coord! {x: 0.001, y: 1e-9};
+
+        assert_eq!(graph.node_at_exact(&exact), Some(1));
+        assert_eq!(graph.node_at_exact(&close), None);
+
+        graph.build_exact_index();
+        assert_eq!(graph.node_at_exact(&exact), Some(1));
+        assert_eq!(graph.node_at_exact(&close), None);
+
+        let added = graph.add_node(CoordNode::new(close, vec![]));
+        assert_eq!(graph.node_at_exact(&close), Some(added));
+
+        graph.remove_node(0);
+        assert_eq!(graph.node_at_exact(&exact), Some(0));
+    }
+
+    #[test]
+    fn isolated_poi() {
+        let mut graph = DirectedCsrGraph::default();
+        for x in 0..3 {
+            let poi = Poi::new(format!("{x}"), Amenity::Pub);
+            graph.add_node(CoordNode::new(coord! {x: x as f64, y: 0.}, vec![poi]));
+        }
+        graph.add_edge(0, 1, 1.);
+        graph.add_edge(1, 0, 1.);
+
+        let graph: PoiGraph<Poi> = PoiGraph::new(RTreeGraph::new_from_graph(graph));
+
+        assert_eq!(graph.poi_nodes().len(), 2);
+        assert!(!graph.poi_nodes().contains(&2));
+    }
+
+    #[test]
+    fn add_coord_pois_batch() {
+        // Nodes about 111 meters apart, node 3 is isolated.
+        let mut graph = DirectedCsrGraph::default();
+        for x in 0..4 {
+            graph.add_node(CoordNode::new(coord! {x: x as f64 * 0.001, y: 0.}, vec![]));
+        }
+        graph.add_edge(0, 1, 1.);
+        graph.add_edge(1, 2, 1.);
+
+        let mut graph: PoiGraph<Poi> = PoiGraph::new(RTreeGraph::new_from_graph(graph));
+
+        let poi = |x, y| {
+            CoordNode::new(
+                coord! {x: x, y: y},
+                vec![Poi::new(format!("{x}"), Amenity::Pub)],
+            )
+        };
+        let pois = [
+            poi(0.00005, 0.),
+            poi(5., 5.),
+            poi(0.00195, 0.00005),
+            poi(0.0015, 0.),
+            poi(0.003, 0.),
+        ];
+
+        let errors = graph.add_coord_pois_batch(&pois, 20.).unwrap_err();
+
+        assert_eq!(
+            errors.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            vec![1, 3, 4]
+        );
+        let mut poi_nodes: Vec<_> = graph.poi_nodes().iter().copied().collect();
+        poi_nodes.sort();
+        assert_eq!(poi_nodes, vec![0, 2]);
+        assert_eq!(graph.graph().node_value(2).unwrap().data().len(), 1);
+    }
+
+    #[test]
+    fn add_coord_pois_dedup() {
+        let graph = || {
+            let mut graph = DirectedCsrGraph::default();
+            for x in 0..3 {
+                graph.add_node(CoordNode::new(coord! {x: x as f64, y: 0.}, vec![]));
+            }
+            graph.add_edge(0, 1, 1.);
+            graph.add_edge(1, 2, 1.);
+            PoiGraph::new(RTreeGraph::new_from_graph(graph))
+        };
+
+        let poi = |x, name: &str, amenity| {
+            CoordNode::new(
+                coord! {x: x, y: 0.},
+                vec![Poi::new(name.to_string(), amenity)],
+            )
+        };
+        // The last two pois snap to the same node, but are not equal.
+        let pois = [
+            poi(0.1, "a", Amenity::Pub),
+            poi(1.9, "b", Amenity::Bar),
+            poi(2.1, "b", Amenity::Cafe),
+        ];
+
+        let mut once: PoiGraph<Poi> = graph();
+        assert_eq!(once.add_coord_pois_dedup(&pois).unwrap(), 0);
+        let mut twice: PoiGraph<Poi> = graph();
+        assert_eq!(twice.add_coord_pois_dedup(&pois).unwrap(), 0);
+        assert_eq!(twice.add_coord_pois_dedup(&pois).unwrap(), 3);
+
+        assert_eq!(twice.poi_nodes(), once.poi_nodes());
+        for node in 0..3 {
+            assert_eq!(
+                twice.graph().node_value(node).unwrap().data(),
+                once.graph().node_value(node).unwrap().data()
+            );
+        }
+        assert_eq!(once.graph().node_value(2).unwrap().data().len(), 2);
+
+        // Without dedup the payloads grow.
+        once.add_coord_pois(&pois).unwrap();
+        assert_eq!(once.graph().node_value(2).unwrap().data().len(), 4);
+    }
+
+    #[test]
+    fn edit_graph() {
+        let mut graph = DirectedCsrGraph::default();
+        for x in 0..4 {
+            let poi = Poi::new(format!("{x}"), Amenity::Pub);
+            graph.add_node(CoordNode::new(coord! {x: x as f64, y: 0.}, vec![poi]));
+        }
+        for x in 0..3 {
+            graph.add_edge(x, x + 1, 1.);
+            graph.add_edge(x + 1, x, 1.);
+        }
+
+        let mut graph: PoiGraph<Poi> = PoiGraph::new(RTreeGraph::new_from_graph(graph));
+        assert_eq!(graph.poi_nodes().len(), 4);
+
+        graph.remove_node(1).unwrap();
+
+        // Node 0 is isolated now, nodes 2 and 3 became 1 and 2.
+        let mut poi_nodes: Vec<_> = graph.poi_nodes().iter().copied().collect();
+        poi_nodes.sort();
+        assert_eq!(poi_nodes, vec![1, 2]);
+        assert_eq!(graph.graph().nearest_node(&coord! {x: 3., y: 0.}), Some(2));
+
+        let poi = Poi::new("new".to_string(), Amenity::Bar);
+        let node = graph.add_node(CoordNode::new(coord! {x: 1., y: 1.}, vec![poi]));
+        assert!(!graph.poi_nodes().contains(&node));
+
+        assert!(graph.add_edge(0, node, 1.));
+        assert!(graph.poi_nodes().contains(&0));
+        assert!(graph.poi_nodes().contains(&node));
+        assert_eq!(
+            graph.graph().nearest_node(&coord! {x: 1., y: 1.}),
+            Some(node)
+        );
+
+        assert_eq!(graph.remove_edge((0, node)), Some(1.));
+        assert!(!graph.poi_nodes().contains(&0));
+        assert!(!graph.poi_nodes().contains(&node));
+    }
+
+    #[test]
+    fn merge_parallel_ways() {
+        let mut graph = DirectedCsrGraph::default();
+        // A way along y = 0 and a copy of it about 56 m to the north, drawn in the other
+        // direction.
+        for y in [0., 0.0005] {
+            for x in 0..3 {
+                graph.add_node(CoordNode::new(coord! {x: x as f64, y: y}, vec![]));
+            }
+        }
+        let poi = Poi::new("copy".to_string(), Amenity::Cafe);
+        graph.add_node(CoordNode::new(coord! {x: 3., y: 0.}, vec![poi]));
+        graph.add_edge(0, 1, 1.);
+        graph.add_edge(1, 2, 1.);
+        graph.add_edge(5, 4, 1.);
+        graph.add_edge(4, 3, 1.);
+        // A way which only continues the copy.
+        graph.add_edge(5, 6, 1.);
+        graph.add_edge(6, 5, 1.);
+        graph.set_edge_provenance(vec![(0, 1, 1), (1, 2, 1), (5, 4, 2), (4, 3, 2), (5, 6, 3)]);
+
+        let mut graph: PoiGraph<Poi> = PoiGraph::new(RTreeGraph::new_from_graph(graph));
+        assert_eq!(graph.graph().node_count(), 7);
+        assert_eq!(graph.graph().edge_count(), 6);
+
+        assert_eq!(graph.merge_parallel_ways(100.), 3);
+
+        // Node 6 became 3 and is now connected to the end of the first way.
+        assert_eq!(graph.graph().node_count(), 4);
+        assert_eq!(graph.graph().edge_count(), 6);
+        for (a, b) in [(0, 1), (1, 2), (2, 1), (1, 0), (2, 3), (3, 2)] {
+            assert!(
+                graph.graph().out_neighbors(a).any(|t| t.target() == b),
+                "({a}, {b})"
+            );
+        }
+        assert_eq!(graph.graph().graph().edge_provenance(2, 1), Some(2));
+        assert_eq!(graph.graph().graph().edge_provenance(2, 3), Some(3));
+        // The edge moved from node 5 to node 2 is weighted with its new length.
+        let length = Point::new(2., 0.).haversine_distance(&Point::new(3., 0.));
+        assert_eq!(
+            graph
+                .graph()
+                .out_neighbors(2)
+                .find(|t| t.target() == 3)
+                .map(|t| *t.value()),
+            Some(length)
+        );
+        assert_eq!(
+            graph.poi_nodes().iter().copied().collect::<Vec<_>>(),
+            vec![3]
+        );
+        assert_eq!(graph.merge_parallel_ways(100.), 0);
+    }
+
+    #[test]
+    fn merge_parallel_ways_dense_way() {
+        let mut graph = DirectedCsrGraph::default();
+        // A one-way way with nodes about 22 m apart, much closer than the tolerance.
+        for x in 0..10 {
+            graph.add_node(CoordNode::new(coord! {x: x as f64 * 0.0002, y: 0.}, vec![]));
+        }
+        for a in 0..9 {
+            graph.add_edge(a, a + 1, 1.);
+        }
+
+        let mut graph: PoiGraph<Poi> = PoiGraph::new(RTreeGraph::new_from_graph(graph));
+        assert_eq!(graph.merge_parallel_ways(100.), 0);
+
+        assert_eq!(graph.graph().node_count(), 10);
+        assert_eq!(graph.graph().edge_count(), 9);
+        for a in 0..9 {
+            assert!(graph.graph().out_neighbors(a).all(|t| t.target() == a + 1));
+        }
+    }
 }
@@ -2,13 +2,15 @@ use std::{
     cmp::max,
     collections::{HashSet, VecDeque},
     fmt::Debug,
+    fs::{self, File},
+    io::{BufReader, Read, Write},
     marker::PhantomData,
     ops::{Deref, DerefMut},
     pin::Pin,
     sync::{Arc, Weak},
 };
 
-use geo::{Contains, Coord, CoordFloat, Rect};
+use geo::{Coord, CoordFloat, Intersects, Rect};
 use graph_rs::{
     CoordGraph, Coordinate, DirectedGraph, Graph,
     algorithms::dijkstra::{Dijkstra, ResultNode},
@@ -19,24 +21,67 @@ use indicatif::{ProgressBar, ProgressIterator};
 use log::{debug, error, info, trace};
 use num_traits::{AsPrimitive, Num, NumCast};
 use ordered_float::{FloatCore, OrderedFloat};
+use rand::{Rng, rng};
+use rayon::prelude::*;
 use rstar::{
-    AABB, Envelope, RTree, RTreeNum, RTreeObject,
+    AABB, DefaultParams, Envelope, RTree, RTreeNum, RTreeObject, RTreeParams,
     primitives::{GeomWithData, Rectangle},
 };
 use rustc_hash::{FxHashMap, FxHashSet};
-use serde::{Deserialize, Serialize, de::DeserializeOwned, ser::SerializeStruct};
+use serde::{
+    Deserialize, Serialize,
+    de::{DeserializeOwned, IgnoredAny},
+    ser::SerializeStruct,
+};
 use tracing::instrument;
 use tracing_subscriber::filter::combinator::Or;
 
 use crate::{
-    oracle::{OracleParams, SplitStrategy, block_pair, split_strategy::SimpleSplitStrategy},
+    events::Events,
+    oracle::{
+        NodeTrait, OracleParams, PoiGraph, RTreeGraphType, SplitStrategy, block_pair,
+        split_strategy::SimpleSplitStrategy,
+    },
     tree::{Tree, node::Node},
-    types::RTreeObjectArc,
     util::r_tree_size,
 };
 
 use super::block_pair::BlockPair;
 
+/// Reasons building an oracle can fail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OracleError {
+    /// Some node with edges does not reach the poi or is not reached from it, so distances
+    /// in the oracle would be undefined.
+    GraphDisconnected,
+    NodeNotFound(usize),
+    EmptyGraph,
+    /// A block pair of another poi was added to the oracle.
+    PoiMismatch {
+        oracle: usize,
+        block_pair: usize,
+    },
+    /// An equal block pair is already in the oracle.
+    DuplicateBlockPair,
+}
+
+impl std::fmt::Display for OracleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GraphDisconnected => write!(f, "graph is not strongly connected"),
+            Self::NodeNotFound(node) => write!(f, "node {node} is not in the graph"),
+            Self::EmptyGraph => write!(f, "graph is empty"),
+            Self::PoiMismatch { oracle, block_pair } => write!(
+                f,
+                "block pair of poi {block_pair} added to the oracle of poi {oracle}"
+            ),
+            Self::DuplicateBlockPair => write!(f, "block pair is already in the oracle"),
+        }
+    }
+}
+
+impl std::error::Error for OracleError {}
+
 pub trait Radius: CoordGraph {
     fn radius(
         &self,
@@ -78,28 +123,158 @@ where
     }
 }
 
+/// Answers which pois lie on a near-shortest path between two coordinates.
+///
+/// `R` are the parameters of the r-tree indexing the blocks, see [Oracle::with_params].
+/// They only change build and query time, never the answers.
 #[derive(Default, Serialize, Deserialize)]
-pub struct Oracle<EV, C>
+#[serde(
+    from = "OracleSerde<EV, C>",
+    bound(
+        serialize = "EV: FloatCore + Serialize, C: RTreeNum + CoordFloat + Serialize",
+        deserialize = "EV: FloatCore + Debug + Deserialize<'de>, \
+            C: RTreeNum + CoordFloat + Deserialize<'de>"
+    )
+)]
+pub struct Oracle<EV, C, R = DefaultParams>
 where
     EV: FloatCore,
     C: RTreeNum + CoordFloat,
+    R: RTreeParams,
 {
     poi: usize,
 
-    r_tree: RTree<GeomWithData<Rectangle<Coord<C>>, Weak<BlockPair<EV, C>>>>,
+    r_tree: RTree<GeomWithData<Rectangle<Coord<C>>, Weak<BlockPair<EV, C>>>, R>,
 
     block_pairs: Vec<Arc<BlockPair<EV, C>>>,
 }
 
+/// Serialized form of [Oracle].
+///
+/// Deserialized [Weak] pointers can never be upgraded, so the stored r-tree
+/// is skipped and rebuilt from the block pairs.
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "EV: FloatCore + Deserialize<'de>, \
+    C: RTreeNum + CoordFloat + Deserialize<'de>"))]
+struct OracleSerde<EV, C>
+where
+    EV: FloatCore,
+    C: RTreeNum + CoordFloat,
+{
+    poi: usize,
+
+    r_tree: IgnoredAny,
+
+    block_pairs: Vec<Arc<BlockPair<EV, C>>>,
+}
+
+impl<EV, C, R> From<OracleSerde<EV, C>> for Oracle<EV, C, R>
+where
+    EV: FloatCore + Debug,
+    C: RTreeNum + CoordFloat,
+    R: RTreeParams,
+{
+    fn from(value: OracleSerde<EV, C>) -> Self {
+        let r_tree = RTree::bulk_load_with_params(
+            value
+                .block_pairs
+                .iter()
+                .flat_map(|block_pair| {
+                    [
+                        GeomWithData::new(
+                            block_pair.s_block_as_rectangle(),
+                            Arc::downgrade(block_pair),
+                        ),
+                        GeomWithData::new(
+                            block_pair.t_block_as_rectangle(),
+                            Arc::downgrade(block_pair),
+                        ),
+                    ]
+                })
+                .collect(),
+        );
+
+        let oracle = Oracle {
+            poi: value.poi,
+            r_tree,
+            block_pairs: value.block_pairs,
+        };
+        oracle.debug_assert_entries_live();
+
+        oracle
+    }
+}
+
 impl<EV, C> Oracle<EV, C>
 where
     EV: FloatCore + Debug,
     C: RTreeNum + CoordFloat,
 {
+    /// Creates an empty oracle for `poi` using rstar's [DefaultParams].
     pub fn new(poi: usize) -> Self {
+        Self::with_params(poi, DefaultParams)
+    }
+
+    /// Creates the oracle for `poi` from a split tree returned by [Oracle::build_for_node].
+    ///
+    /// The nodes marked as in-path are exactly the block pairs of the oracle, so no Dijkstra
+    /// is run. The tree can be edited before, e.g. to drop block pairs.
+    pub fn from_split_tree(tree: &id_tree::Tree<(BlockPair<EV, C>, bool)>, poi: usize) -> Self {
+        let block_pairs: Vec<_> = tree
+            .root_node_id()
+            .into_iter()
+            .flat_map(|root| tree.traverse_pre_order(root).unwrap())
+            .filter(|node| node.data().1)
+            .map(|node| Arc::new(node.data().0.clone()))
+            .collect();
+        debug_assert!(
+            block_pairs
+                .iter()
+                .all(|block_pair| block_pair.poi_id() == poi)
+        );
+
+        Self::from(OracleSerde {
+            poi,
+            r_tree: IgnoredAny,
+            block_pairs,
+        })
+    }
+
+    /// Builds the oracle for `node` using rstar's [DefaultParams].
+    ///
+    /// See [Oracle::build_for_node_with_params].
+    pub fn build_for_node<G, P>(
+        node: usize,
+        epsilon: G::EV,
+        graph: &G,
+        params: P,
+    ) -> Result<(Self, id_tree::Tree<(BlockPair<G::EV, G::C>, bool)>), OracleError>
+    where
+        G: CoordGraph<C = C, EV = EV> + Dijkstra + Radius + Sync,
+        EV: Send + Sync,
+        C: Send + Sync,
+        P: OracleParams,
+    {
+        Self::build_for_node_with_params(node, epsilon, graph, params, DefaultParams)
+    }
+}
+
+impl<EV, C, R> Oracle<EV, C, R>
+where
+    EV: FloatCore + Debug,
+    C: RTreeNum + CoordFloat,
+    R: RTreeParams,
+{
+    /// Creates an empty oracle for `poi` whose blocks are indexed with the r-tree parameters `R`.
+    ///
+    /// rstar's parameters are associated constants, so the value only selects `R`.
+    /// [DefaultParams] (3 to 6 children per node, 2 reinsertions) are a sane default,
+    /// [SmallNodeParams](super::SmallNodeParams) and [LargeNodeParams](super::LargeNodeParams)
+    /// trade build time against query time.
+    pub fn with_params(poi: usize, _params: R) -> Self {
         Oracle {
             poi,
-            r_tree: RTree::new(),
+            r_tree: RTree::new_with_params(),
             block_pairs: vec![],
         }
     }
@@ -109,6 +284,11 @@ where
         self.block_pairs.len()
     }
 
+    /// Iterates over all block-pairs stored in the oracle.
+    pub fn block_pairs(&self) -> impl Iterator<Item = &Arc<BlockPair<EV, C>>> {
+        self.block_pairs.iter()
+    }
+
     pub fn avg_block_ocupancy<G>(&self, graph: &G) -> f64
     where
         G: CoordGraph<C = C, EV = EV>,
@@ -127,8 +307,32 @@ where
             .1
     }
 
+    /// Adds `block_pair` to the oracle and returns the stored pair.
+    ///
+    /// Fails without changing the oracle if `block_pair` belongs to another poi or an equal
+    /// block pair is already stored.
     #[instrument(level = "trace", skip(self))]
-    fn add_block_pair(&mut self, block_pair: BlockPair<EV, C>) -> Arc<BlockPair<EV, C>> {
+    pub fn add_block_pair(
+        &mut self,
+        block_pair: BlockPair<EV, C>,
+    ) -> Result<Arc<BlockPair<EV, C>>, OracleError> {
+        if block_pair.poi_id() != self.poi {
+            return Err(OracleError::PoiMismatch {
+                oracle: self.poi,
+                block_pair: block_pair.poi_id(),
+            });
+        }
+        // An equal block pair has an entry with the same rectangle.
+        let s_envelope = block_pair.s_block_as_rectangle().envelope();
+        if self
+            .r_tree
+            .locate_in_envelope(&s_envelope)
+            .filter_map(|geom| geom.data.upgrade())
+            .any(|other| *other == block_pair)
+        {
+            return Err(OracleError::DuplicateBlockPair);
+        }
+
         let block_pair = Arc::new(block_pair);
 
         let s_rect = GeomWithData::new(
@@ -140,22 +344,14 @@ where
             Arc::downgrade(&block_pair),
         );
 
-        assert_eq!(
-            &s_rect.data.upgrade().unwrap().s_block_as_rectangle(),
-            s_rect.geom()
-        );
         self.r_tree.insert(s_rect);
         self.r_tree.insert(t_rect);
         trace!("block paris length: {}", self.block_pairs.len());
-        assert!(
-            !self.block_pairs.contains(&block_pair),
-            "block pair already exists\n{:#?}",
-            &block_pair
-        );
         self.block_pairs.push(block_pair.clone());
+        debug_assert_eq!(self.r_tree.size(), 2 * self.block_pairs.len());
         trace!("Added block pair");
 
-        block_pair
+        Ok(block_pair)
     }
 
     pub fn get_block_pairs(
@@ -172,9 +368,7 @@ where
             .locate_all_at_point(s_coord)
             .filter_map(|geom| {
                 if let Some(block_pair) = geom.data.upgrade() {
-                    if block_pair.s_block().contains(s_coord)
-                        && block_pair.t_block().contains(t_coord)
-                    {
+                    if block_pair.contains(s_coord, t_coord) {
                         trace!("Found block pair {:#?}", block_pair);
                         return Some(block_pair);
                     }
@@ -194,6 +388,67 @@ where
         block_pairs.into_iter().map(|b| b.poi_id()).collect()
     }
 
+    /// Returns a beer path of a block pair containing `s_coord` and `t_coord`.
+    ///
+    /// The path runs between nodes of the blocks, which are not necessarily the nodes nearest
+    /// to the coordinates. Returns `None` if no such block pair stores a path, see
+    /// [OracleParams::store_paths].
+    pub fn get_beer_path(&self, s_coord: &Coord<C>, t_coord: &Coord<C>) -> Option<Path<EV>> {
+        self.get_block_pairs(s_coord, t_coord)
+            .into_iter()
+            .find_map(|block_pair| block_pair.path().cloned())
+    }
+
+    /// Like [Oracle::get_beer_pois], but for the nodes `s` and `t` of `graph`.
+    ///
+    /// Unlike [BlockPair::contains], the boundary of a block counts as inside, so a node on the
+    /// border of two blocks is looked up in both. Returns an empty set if a node has no
+    /// coordinate.
+    pub fn get_pois_for_nodes<G>(&self, s: usize, t: usize, graph: &G) -> HashSet<usize>
+    where
+        G: CoordGraph<C = C>,
+    {
+        let (Some(s_coord), Some(t_coord)) = (graph.node_coord(s), graph.node_coord(t)) else {
+            return HashSet::new();
+        };
+
+        self.r_tree
+            .locate_all_at_point(&s_coord)
+            .filter_map(|geom| geom.data.upgrade())
+            .filter(|block_pair| {
+                block_pair.s_block().intersects(&s_coord)
+                    && block_pair.t_block().intersects(&t_coord)
+            })
+            .map(|block_pair| block_pair.poi_id())
+            .collect()
+    }
+
+    /// Returns the fraction of `samples` random node pairs of `graph` which hit a block pair,
+    /// see [Oracle::get_pois_for_nodes].
+    ///
+    /// Pairs without a block pair are left to an exact Dijkstra, so a low coverage hints at
+    /// an epsilon too tight or blocks too small. Returns `0.` for an empty graph.
+    pub fn coverage<G>(&self, graph: &G, samples: usize) -> f64
+    where
+        G: CoordGraph<C = C>,
+    {
+        let node_count = graph.node_count();
+        if node_count == 0 || samples == 0 {
+            return 0.;
+        }
+
+        let mut rng = rng();
+        let hits = (0..samples)
+            .filter(|_| {
+                let s = rng.random_range(0..node_count);
+                let t = rng.random_range(0..node_count);
+                !self.get_pois_for_nodes(s, t, graph).is_empty()
+            })
+            .count();
+
+        hits as f64 / samples as f64
+    }
+
     pub fn get_blocks_at(&self, coord: &Coord<C>) -> Vec<Arc<BlockPair<EV, C>>> {
         self.r_tree
             .locate_all_at_point(coord)
@@ -201,24 +456,53 @@ where
             .collect()
     }
 
-    #[instrument(skip(graph))]
-    pub fn build_for_node<G, P>(
+    #[instrument(skip(graph, r_tree_params))]
+    pub fn build_for_node_with_params<G, P>(
         node: usize,
         epsilon: G::EV,
         graph: &G,
         params: P,
-    ) -> Result<(Self, id_tree::Tree<(BlockPair<G::EV, G::C>, bool)>), String>
+        r_tree_params: R,
+    ) -> Result<(Self, id_tree::Tree<(BlockPair<G::EV, G::C>, bool)>), OracleError>
     where
-        G: CoordGraph<C = C, EV = EV> + Dijkstra + Radius,
+        G: CoordGraph<C = C, EV = EV> + Dijkstra + Radius + Sync,
+        EV: Send + Sync,
+        C: Send + Sync,
         P: OracleParams,
     {
-        let mut oracle = Oracle::new(node);
+        let mut oracle = Oracle::with_params(node, r_tree_params);
         debug!("Building oracle for node {:#?}", &node);
+        if graph.node_count() == 0 {
+            return Err(OracleError::EmptyGraph);
+        }
+        if node >= graph.node_count() {
+            return Err(OracleError::NodeNotFound(node));
+        }
+        // An isolated node can not lie on any path.
+        if graph.degree(node) == 0 {
+            info!("Node {node} is isolated, skipping oracle");
+            return Ok((oracle, id_tree::Tree::new()));
+        }
+        // Block pairs take their distances from any node through the poi to any other node.
+        // Isolated nodes lie on no path and are never picked for a block pair.
+        let connected = (0..graph.node_count())
+            .filter(|node| graph.degree(*node) > 0)
+            .count();
+        for direction in [Direction::Outgoing, Direction::Incoming] {
+            if graph.dijkstra_full(node, direction).iter().count() < connected {
+                return Err(OracleError::GraphDisconnected);
+            }
+        }
         let Some(root) = graph.bounding_rect() else {
-            return Err("Could not get bounding rect of graph".to_string());
+            return Err(OracleError::EmptyGraph);
         };
 
         let root = BlockPair::new(root, root, node, epsilon, graph);
+        let Split {
+            block_pair: root,
+            children,
+            in_path,
+        } = Split::new(root, graph, params, 0);
 
         let mut tree = id_tree::TreeBuilder::new()
             .with_node_capacity(100000)
@@ -231,21 +515,29 @@ where
             )
             .unwrap();
 
-        oracle.process_block_pair(&root, &mut tree, graph, params);
+        // An in-path root is only possible with `max_depth` 0 or after merging all blocks.
+        if oracle.process_block_pair(&root, children, in_path, &mut tree, graph, params)? == 1 {
+            oracle.add_block_pair(tree.get(&root).unwrap().data().0.clone())?;
+        }
+        oracle.debug_assert_entries_live();
 
         Ok((oracle, tree))
     }
 
-    /// Process the block pair in 'node'.
+    /// Process the block pair in 'node' in the split tree, whose `children` and `in_path` state
+    /// come from [Split::new].
     ///
-    /// Returns 1 if it is in-path, -1 if not-in-path and 0 if neither.
+    /// Returns 1 if it is in-path, -1 if not-in-path and 0 if neither, or the error of adding a
+    /// block pair to the oracle.
     fn process_block_pair<G, P>(
         &mut self,
         node: &id_tree::NodeId,
+        children: Vec<Split<EV, C>>,
+        in_path: i32,
         tree: &mut id_tree::Tree<(BlockPair<EV, C>, bool)>,
         graph: &G,
         params: P,
-    ) -> i32
+    ) -> Result<i32, OracleError>
     where
         G: CoordGraph<C = C, EV = EV> + Dijkstra + Radius,
         P: OracleParams,
@@ -254,15 +546,15 @@ where
 
         tracing::trace!(tree_capacity = ?tree.capacity());
 
-        if block_pair.values().in_path() {
+        if in_path == 1 {
             log::trace!("Found in-path block pair:\n{:#?}", block_pair,);
 
-            tree.get_mut(node).unwrap().data_mut().1 = true;
+            Self::mark_in_path(node, tree, graph, params);
 
-            return 1;
+            return Ok(1);
         }
 
-        if block_pair.values().not_in_path() {
+        if in_path == -1 {
             log::trace!("Found not in-path block pair:\n{:#?}", block_pair,);
 
             if params.merge_blocks() {
@@ -271,30 +563,33 @@ where
                     .inspect_err(|e| tracing::error!("Coud not remove node. Reason: {e}"));
             }
 
-            return -1;
+            return Ok(-1);
         }
 
-        let children = P::SplitStrategy::split(block_pair, graph);
-
-        let children_ids: Vec<_> = children
+        let (children_ids, grandchildren): (Vec<_>, Vec<_>) = children
             .into_iter()
             .map(|child| {
-                tree.insert(
-                    id_tree::Node::new((child, false)),
-                    id_tree::InsertBehavior::UnderNode(node),
-                )
-                .unwrap()
+                let id = tree
+                    .insert(
+                        id_tree::Node::new((child.block_pair, false)),
+                        id_tree::InsertBehavior::UnderNode(node),
+                    )
+                    .unwrap();
+                (id, (child.children, child.in_path))
             })
-            .collect();
+            .unzip();
 
         let children_in_path: Vec<_> = children_ids
             .iter()
-            .map(|child| self.process_block_pair(child, tree, graph, params))
-            .collect();
+            .zip(grandchildren)
+            .map(|(child, (children, in_path))| {
+                self.process_block_pair(child, children, in_path, tree, graph, params)
+            })
+            .collect::<Result<_, _>>()?;
 
         if children_in_path.iter().all(|in_path| *in_path == 1) && params.merge_blocks() {
             log::trace!("All children are in-path");
-            tree.get_mut(node).unwrap().data_mut().1 = true;
+            Self::mark_in_path(node, tree, graph, params);
 
             for child in children_ids {
                 let _ = tree
@@ -302,7 +597,7 @@ where
                     .inspect_err(|e| tracing::error!("Could not remove node. Reason: {e}"));
             }
 
-            return 1;
+            return Ok(1);
         }
 
         if children_in_path.iter().all(|in_path| *in_path == -1) && params.merge_blocks() {
@@ -312,18 +607,74 @@ where
                 .remove_node(node.clone(), id_tree::RemoveBehavior::DropChildren)
                 .inspect_err(|e| tracing::error!("Could not remove node. Reason: {e}"));
 
-            return -1;
+            return Ok(-1);
         }
 
         if let Ok(children) = tree.children(node) {
             for child in children {
                 if child.data().1 {
-                    self.add_block_pair(child.data().0.clone());
+                    self.add_block_pair(child.data().0.clone())?;
                 }
             }
         }
 
-        0
+        Ok(0)
+    }
+
+    /// Marks the block pair in `node` as in-path and stores its path if `params` ask for it.
+    fn mark_in_path<G, P>(
+        node: &id_tree::NodeId,
+        tree: &mut id_tree::Tree<(BlockPair<EV, C>, bool)>,
+        graph: &G,
+        params: P,
+    ) where
+        G: CoordGraph<C = C, EV = EV> + Dijkstra,
+        P: OracleParams,
+    {
+        let data = tree.get_mut(node).unwrap().data_mut();
+        data.1 = true;
+        if params.store_paths() {
+            data.0.store_path(graph);
+        }
+    }
+
+    /// Removes the r-tree entries whose block pair is no longer stored in the oracle.
+    ///
+    /// Queries skip such entries once nothing else holds the block pair, so they only cost
+    /// memory and time. Returns the number of removed entries.
+    pub fn compact(&mut self) -> usize {
+        let size = self.r_tree.size();
+        let stored = self.stored_block_pairs();
+        let r_tree = std::mem::replace(&mut self.r_tree, RTree::new_with_params());
+        self.r_tree = RTree::bulk_load_with_params(
+            r_tree
+                .into_iter()
+                .filter(|geom| stored.contains(&geom.data.as_ptr()))
+                .collect(),
+        );
+        self.debug_assert_entries_live();
+
+        size - self.r_tree.size()
+    }
+
+    /// Addresses of the stored block pairs, to tell r-tree entries of stored block pairs from
+    /// ones of block pairs which are only kept alive elsewhere.
+    fn stored_block_pairs(&self) -> FxHashSet<*const BlockPair<EV, C>> {
+        self.block_pairs.iter().map(Arc::as_ptr).collect()
+    }
+
+    /// Checks in debug builds that every r-tree entry points to a stored block pair.
+    fn debug_assert_entries_live(&self) {
+        debug_assert!(
+            {
+                let stored = self.stored_block_pairs();
+                self.r_tree
+                    .iter()
+                    .all(|geom| stored.contains(&geom.data.as_ptr()))
+            },
+            "oracle for poi {} has r-tree entries of dropped block pairs",
+            self.poi
+        );
     }
 
     pub fn invariant<G>(&self, node: usize, graph: &G) -> bool
@@ -352,6 +703,95 @@ where
     }
 }
 
+/// A block pair with its children, split before they are added to the split tree.
+///
+/// Splitting runs the Dijkstras of the children and is the bulk of a build. It only reads the
+/// graph, so siblings are split in parallel if [OracleParams::parallel] is set.
+///
+/// The whole split is held in memory until [Oracle::process_block_pair] moves it into the
+/// split tree. With [OracleParams::merge_blocks], children are merged while splitting, so
+/// children are only kept if they are not all in-path or all not-in-path.
+struct Split<EV, C>
+where
+    EV: FloatCore,
+    C: RTreeNum + CoordFloat,
+{
+    block_pair: BlockPair<EV, C>,
+    children: Vec<Split<EV, C>>,
+    /// 1 if the block pair is in-path, -1 if it is not-in-path and 0 if neither, like the
+    /// return value of [Oracle::process_block_pair].
+    in_path: i32,
+}
+
+impl<EV, C> Split<EV, C>
+where
+    EV: FloatCore + Debug + Send + Sync,
+    C: RTreeNum + CoordFloat + Send + Sync,
+{
+    /// Splits `block_pair` at `depth` until it is in-path, not-in-path or at the maximum depth.
+    ///
+    /// With [OracleParams::merge_blocks], a block pair whose children are all in-path or all
+    /// not-in-path drops them and takes their state.
+    fn new<G, P>(block_pair: BlockPair<EV, C>, graph: &G, params: P, depth: usize) -> Self
+    where
+        G: CoordGraph<C = C, EV = EV> + Dijkstra + Radius + Sync,
+        P: OracleParams,
+    {
+        let values = block_pair.values();
+        let in_path = if values.in_path() {
+            1
+        } else if values.not_in_path() {
+            -1
+        } else if params
+            .max_depth()
+            .is_some_and(|max_depth| depth >= max_depth)
+        {
+            log::trace!("Reached max depth, treating as in-path:\n{:#?}", block_pair);
+            1
+        } else {
+            0
+        };
+        if in_path != 0 {
+            return Self {
+                block_pair,
+                children: vec![],
+                in_path,
+            };
+        }
+
+        let children = P::SplitStrategy::split(&block_pair, graph);
+        let children: Vec<Self> = if params.parallel() {
+            children
+                .into_par_iter()
+                .map(|child| Self::new(child, graph, params, depth + 1))
+                .collect()
+        } else {
+            children
+                .into_iter()
+                .map(|child| Self::new(child, graph, params, depth + 1))
+                .collect()
+        };
+
+        if params.merge_blocks() {
+            for in_path in [1, -1] {
+                if children.iter().all(|child| child.in_path == in_path) {
+                    return Self {
+                        block_pair,
+                        children: vec![],
+                        in_path,
+                    };
+                }
+            }
+        }
+
+        Self {
+            block_pair,
+            children,
+            in_path: 0,
+        }
+    }
+}
+
 // impl<C> Oracle<C>
 // where
 //     C: RTreeNum + CoordFloat + Send + Sync + Serialize + DeserializeOwned,
@@ -372,33 +812,70 @@ where
 //     }
 // }
 
-#[derive(Default, Serialize, Deserialize)]
-pub struct OracleCollection<G>
+#[derive(Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "Oracle<G::EV, G::C, R>: Serialize",
+    deserialize = "Oracle<G::EV, G::C, R>: Deserialize<'de>"
+))]
+pub struct OracleCollection<G, R = DefaultParams>
 where
     G: CoordGraph,
     G::NV: Coordinate<G::C>,
     G::EV: FloatCore + Serialize + DeserializeOwned,
     G::C: RTreeNum + CoordFloat + Serialize + DeserializeOwned,
+    R: RTreeParams,
 {
-    oracle: FxHashMap<usize, Oracle<G::EV, G::C>>,
+    oracle: FxHashMap<usize, Oracle<G::EV, G::C, R>>,
     phantom: PhantomData<G>,
 }
 
-impl<G> OracleCollection<G>
+impl<G> Default for OracleCollection<G>
+where
+    G: CoordGraph,
+    G::NV: Coordinate<G::C>,
+    G::EV: FloatCore + Serialize + DeserializeOwned,
+    G::C: RTreeNum + CoordFloat + Serialize + DeserializeOwned,
+{
+    fn default() -> Self {
+        Self {
+            oracle: FxHashMap::default(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<G, R> OracleCollection<G, R>
 where
     G: CoordGraph + Dijkstra + Radius,
     G::NV: Coordinate<G::C>,
     G::EV: FloatCore + Debug + Serialize + DeserializeOwned,
     G::C: RTreeNum + CoordFloat + Serialize + DeserializeOwned,
+    R: RTreeParams + Default,
 {
+    /// Creates an empty collection whose oracles are built with the r-tree parameters `R`.
+    ///
+    /// See [Oracle::with_params].
+    pub fn with_params(_params: R) -> Self {
+        Self {
+            oracle: FxHashMap::default(),
+            phantom: PhantomData,
+        }
+    }
+
     pub fn build_for_node<P: OracleParams>(
         &mut self,
         node: usize,
         epsilon: G::EV,
         graph: &G,
         params: P,
-    ) -> Result<(usize, id_tree::Tree<(BlockPair<G::EV, G::C>, bool)>), String> {
-        let oracle = Oracle::build_for_node(node, epsilon, graph, params)?;
+    ) -> Result<(usize, id_tree::Tree<(BlockPair<G::EV, G::C>, bool)>), OracleError>
+    where
+        G: Sync,
+        G::EV: Send + Sync,
+        G::C: Send + Sync,
+    {
+        let oracle =
+            Oracle::build_for_node_with_params(node, epsilon, graph, params, R::default())?;
 
         self.oracle.insert(node, oracle.0);
         Ok((node, oracle.1))
@@ -410,21 +887,70 @@ where
         epsilon: G::EV,
         graph: &G,
         params: P,
-    ) -> Result<FxHashMap<usize, id_tree::Tree<(BlockPair<G::EV, G::C>, bool)>>, String> {
+    ) -> Result<FxHashMap<usize, id_tree::Tree<(BlockPair<G::EV, G::C>, bool)>>, OracleError>
+    where
+        G: Sync,
+        G::EV: Send + Sync,
+        G::C: Send + Sync,
+    {
+        self.build_for_nodes_with_epsilon(nodes, |_| epsilon, graph, params)
+    }
+
+    /// Builds the oracles for `nodes` with the epsilon `epsilon(node)` for each node.
+    ///
+    /// This allows a tighter approximation for some pois at the cost of a larger oracle.
+    pub fn build_for_nodes_with_epsilon<P: OracleParams>(
+        &mut self,
+        nodes: &FxHashSet<usize>,
+        epsilon: impl Fn(usize) -> G::EV,
+        graph: &G,
+        params: P,
+    ) -> Result<FxHashMap<usize, id_tree::Tree<(BlockPair<G::EV, G::C>, bool)>>, OracleError>
+    where
+        G: Sync,
+        G::EV: Send + Sync,
+        G::C: Send + Sync,
+    {
+        self.build_for_nodes_with_progress(nodes, epsilon, graph, params, |_| {})
+    }
+
+    /// Like [OracleCollection::build_for_nodes_with_epsilon], but calls `progress` with an
+    /// [Events::OracleProgress] after each node.
+    pub fn build_for_nodes_with_progress<P: OracleParams>(
+        &mut self,
+        nodes: &FxHashSet<usize>,
+        epsilon: impl Fn(usize) -> G::EV,
+        graph: &G,
+        params: P,
+        mut progress: impl FnMut(Events),
+    ) -> Result<FxHashMap<usize, id_tree::Tree<(BlockPair<G::EV, G::C>, bool)>>, OracleError>
+    where
+        G: Sync,
+        G::EV: Send + Sync,
+        G::C: Send + Sync,
+    {
         let mut split_trees = FxHashMap::default();
-        for node in nodes {
-            let split_tree = self.build_for_node(*node, epsilon, graph, params)?;
-            split_trees.insert(split_tree.0, split_tree.1);
+        for (done, node) in nodes.iter().enumerate() {
+            if *node < graph.node_count() && graph.degree(*node) == 0 {
+                info!("Node {node} is isolated, skipping oracle");
+            } else {
+                let split_tree = self.build_for_node(*node, epsilon(*node), graph, params)?;
+                split_trees.insert(split_tree.0, split_tree.1);
+            }
+            progress(Events::OracleProgress {
+                done: done + 1,
+                total: nodes.len(),
+            });
         }
 
         Ok(split_trees)
     }
 
-    pub fn insert(&mut self, oracle: Oracle<G::EV, G::C>) -> Option<Oracle<G::EV, G::C>> {
+    pub fn insert(&mut self, oracle: Oracle<G::EV, G::C, R>) -> Option<Oracle<G::EV, G::C, R>> {
         self.oracle.insert(oracle.poi(), oracle)
     }
 
-    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&Oracle<G::EV, G::C>>
+    pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&Oracle<G::EV, G::C, R>>
     where
         usize: std::borrow::Borrow<Q>,
         Q: std::hash::Hash + Eq,
@@ -432,7 +958,7 @@ where
         self.oracle.get(k)
     }
 
-    pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<Oracle<G::EV, G::C>>
+    pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<Oracle<G::EV, G::C, R>>
     where
         usize: std::borrow::Borrow<Q>,
         Q: std::hash::Hash + Eq,
@@ -440,26 +966,740 @@ where
         self.oracle.remove(k)
     }
 
-    pub fn iter(&self) -> std::collections::hash_map::Iter<'_, usize, Oracle<G::EV, G::C>> {
+    pub fn iter(&self) -> std::collections::hash_map::Iter<'_, usize, Oracle<G::EV, G::C, R>> {
         self.oracle.iter()
     }
+
+    /// Iterates over the block pairs of the oracle for `poi`.
+    ///
+    /// Yields nothing if there is no oracle for `poi`.
+    pub fn block_pairs_for_poi(
+        &self,
+        poi: usize,
+    ) -> impl Iterator<Item = &Arc<BlockPair<G::EV, G::C>>> {
+        self.oracle
+            .get(&poi)
+            .into_iter()
+            .flat_map(|oracle| oracle.block_pairs())
+    }
+
+    /// Loads the oracles written by the `build` command from `dir`.
+    ///
+    /// Every `*_<poi>.omp` file in `dir` is read, other files are ignored.
+    pub fn load_dir(dir: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let mut collection = Self {
+            oracle: FxHashMap::default(),
+            phantom: PhantomData,
+        };
+
+        for entry in fs::read_dir(dir).map_err(|err| err.to_string())? {
+            let path = entry.map_err(|err| err.to_string())?.path();
+            if path.extension().is_none_or(|ext| ext != "omp") {
+                continue;
+            }
+            let Some(poi) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.rsplit_once('_'))
+                .and_then(|(_, poi)| poi.parse::<usize>().ok())
+            else {
+                continue;
+            };
+
+            let file = File::open(&path).map_err(|err| format!("{}: {err}", path.display()))?;
+            let oracle: Oracle<G::EV, G::C, R> = rmp_serde::from_read(BufReader::new(file))
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+            if oracle.poi() != poi {
+                return Err(format!(
+                    "{}: contains the oracle for poi {}",
+                    path.display(),
+                    oracle.poi()
+                ));
+            }
+
+            debug!("Loaded oracle for poi {poi} from {}", path.display());
+            collection.insert(oracle);
+        }
+
+        Ok(collection)
+    }
+
+    /// Writes all oracles to `writer` as one file.
+    ///
+    /// The file starts with the number of oracles, followed by the oracles in poi order,
+    /// all encoded as MessagePack. This is the '.ocmp' format, read it with
+    /// [OracleCollection::read_from].
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), String> {
+        let mut serializer = rmp_serde::Serializer::new(writer);
+
+        let mut pois: Vec<_> = self.oracle.keys().collect();
+        pois.sort_unstable();
+
+        (pois.len() as u64)
+            .serialize(&mut serializer)
+            .map_err(|err| err.to_string())?;
+        for poi in pois {
+            self.oracle[poi]
+                .serialize(&mut serializer)
+                .map_err(|err| format!("poi {poi}: {err}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a collection written by [OracleCollection::write_to].
+    pub fn read_from<Rd: Read>(reader: Rd) -> Result<Self, String> {
+        let mut deserializer = rmp_serde::Deserializer::new(reader);
+
+        let count = u64::deserialize(&mut deserializer).map_err(|err| err.to_string())?;
+        let mut collection = Self {
+            oracle: FxHashMap::default(),
+            phantom: PhantomData,
+        };
+        for i in 0..count {
+            let oracle: Oracle<G::EV, G::C, R> = Oracle::deserialize(&mut deserializer)
+                .map_err(|err| format!("oracle {i} of {count}: {err}"))?;
+            collection.insert(oracle);
+        }
+
+        Ok(collection)
+    }
+
+    /// Combines both collections.
+    ///
+    /// Oracles in `other` replace the ones in `self` for the same poi.
+    pub fn merge(mut self, other: Self) -> Self {
+        self.oracle.extend(other.oracle);
+        self
+    }
+}
+
+impl<T, R> OracleCollection<RTreeGraphType<T>, R>
+where
+    T: NodeTrait + Serialize + DeserializeOwned,
+    R: RTreeParams,
+{
+    /// Returns the cost and poi of the cheapest beer path from `s` to `t`, and whether the
+    /// oracles answered the query.
+    ///
+    /// The pois of the oracles with a block pair for `s` and `t` are the candidates, see
+    /// [Oracle::get_pois_for_nodes]. If there are none, all pois of `graph` are searched with
+    /// [PoiGraph::beer_path_dijkstra_base] instead. Ties are broken by the poi id. Returns `None`
+    /// if `s == t` or no beer path exists.
+    pub fn beer_path_with_fallback(
+        &self,
+        s: usize,
+        t: usize,
+        graph: &PoiGraph<T>,
+    ) -> Option<(f64, usize, bool)> {
+        if s == t {
+            return None;
+        }
+
+        let cheapest = |pois: &FxHashSet<usize>| {
+            graph
+                .beer_path_dijkstra_base(s, t, pois, f64::INFINITY)?
+                .into_iter()
+                .min_by_key(|(poi, cost)| (OrderedFloat(*cost), *poi))
+                .map(|(poi, cost)| (cost, poi))
+        };
+
+        let candidates: FxHashSet<usize> = self
+            .oracle
+            .values()
+            .flat_map(|oracle| oracle.get_pois_for_nodes(s, t, graph.graph()))
+            .collect();
+        if !candidates.is_empty()
+            && let Some((cost, poi)) = cheapest(&candidates)
+        {
+            return Some((cost, poi, true));
+        }
+
+        debug!("Oracle miss for ({s}, {t}), falling back to Dijkstra");
+        cheapest(graph.poi_nodes()).map(|(cost, poi)| (cost, poi, false))
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use std::{f64, ops::Bound};
+    use std::{
+        f64,
+        fs::{self, File},
+        io::BufWriter,
+        ops::Bound,
+        sync::Arc,
+    };
 
-    use geo::{Coord, Rect};
+    use geo::{Coord, Intersects, Rect};
     use graph_rs::{
-        DirectedGraph, Graph,
+        CoordGraph, DirectedGraph, Graph,
         graph::{csr::DirectedCsrGraph, rstar::RTreeGraph},
     };
     use rand::random;
+    use rustc_hash::FxHashSet;
     use serde::{Deserialize, Serialize};
 
-    use crate::oracle::block_pair::BlockPair;
+    use crate::{
+        oracle::{
+            DefaultOracleParams, LargeNodeParams, PoiGraph, RTreeGraphType, SmallNodeParams,
+            block_pair::BlockPair,
+        },
+        types::{CoordNode, Poi},
+    };
+
+    use super::{Oracle, OracleCollection, OracleError};
+
+    const SQUARE: [(f64, f64); 4] = [(0., 0.), (1., 0.), (1., 1.), (0., 1.)];
+    const SQUARE_EDGES: [(usize, usize); 4] = [(0, 1), (1, 2), (2, 3), (3, 0)];
+
+    fn coords(points: &[(f64, f64)]) -> Vec<Coord<f64>> {
+        points.iter().map(|&(x, y)| Coord { x, y }).collect()
+    }
+
+    /// Adds every edge in both directions with weight 1.
+    fn both_ways(edges: &[(usize, usize)]) -> Vec<(usize, usize, f64)> {
+        edges
+            .iter()
+            .flat_map(|&(a, b)| [(a, b, 1.), (b, a, 1.)])
+            .collect()
+    }
+
+    /// The unit square with all four sides usable in both directions.
+    fn square_graph() -> RTreeGraph<DirectedCsrGraph<f64, Coord<f64>>, f64> {
+        RTreeGraph::new_from_graph(DirectedCsrGraph::from_coords_and_edges(
+            coords(&SQUARE),
+            both_ways(&SQUARE_EDGES),
+        ))
+    }
+
+    #[test]
+    fn skip_isolated_node() {
+        let mut graph: DirectedCsrGraph<f64, Coord<f64>> = DirectedCsrGraph::default();
+        for x in 0..4 {
+            graph.add_node(Coord { x: x as f64, y: 0. });
+        }
+        graph.add_edge(0, 1, 1.);
+        graph.add_edge(1, 2, 1.);
+        graph.add_edge(2, 0, 1.);
+        let graph = RTreeGraph::new_from_graph(graph);
+
+        let mut oracles = OracleCollection::default();
+        let split_trees = oracles
+            .build_for_nodes(
+                &FxHashSet::from_iter([3]),
+                0.2,
+                &graph,
+                DefaultOracleParams::default(),
+            )
+            .unwrap();
+
+        assert!(split_trees.is_empty());
+        assert!(oracles.get(&3).is_none());
+
+        let (oracle, split_tree) =
+            Oracle::build_for_node(3, 0.2, &graph, DefaultOracleParams::default()).unwrap();
+        assert_eq!(oracle.poi(), 3);
+        assert!(split_tree.root_node_id().is_none());
+    }
+
+    #[test]
+    fn build_with_isolated_node() {
+        // Node 4 in the middle of the square has no edges.
+        let mut points = SQUARE.to_vec();
+        points.push((0.5, 0.5));
+        let graph = RTreeGraph::new_from_graph(DirectedCsrGraph::from_coords_and_edges(
+            coords(&points),
+            both_ways(&SQUARE_EDGES),
+        ));
+
+        let (oracle, _) =
+            Oracle::build_for_node(0, 0.2, &graph, DefaultOracleParams::default()).unwrap();
+        assert_eq!(oracle.poi(), 0);
+        assert!(oracle.size() > 0);
+        assert!(
+            oracle
+                .block_pairs()
+                .all(|block_pair| block_pair.values().s != 4 && block_pair.values().t != 4)
+        );
+    }
+
+    #[test]
+    fn build_errors() {
+        let empty: RTreeGraph<DirectedCsrGraph<f64, Coord<f64>>, f64> = RTreeGraph::default();
+        let result = Oracle::build_for_node(0, 0.2, &empty, DefaultOracleParams::default());
+        assert_eq!(result.err(), Some(OracleError::EmptyGraph));
+
+        let mut edges = both_ways(&SQUARE_EDGES);
+        // Node 4 can be reached, but there is no way back.
+        edges.push((1, 4, 1.));
+        let graph = RTreeGraph::new_from_graph(DirectedCsrGraph::from_coords_and_edges(
+            coords(&[(0., 0.), (1., 0.), (1., 1.), (0., 1.), (2., 0.)]),
+            edges,
+        ));
+
+        let result = Oracle::build_for_node(7, 0.2, &graph, DefaultOracleParams::default());
+        assert_eq!(result.err(), Some(OracleError::NodeNotFound(7)));
+        let result = Oracle::build_for_node(0, 0.2, &graph, DefaultOracleParams::default());
+        assert_eq!(result.err(), Some(OracleError::GraphDisconnected));
+
+        let mut oracles = OracleCollection::default();
+        let result = oracles.build_for_nodes(
+            &FxHashSet::from_iter([7]),
+            0.2,
+            &graph,
+            DefaultOracleParams::default(),
+        );
+        assert_eq!(result.err(), Some(OracleError::NodeNotFound(7)));
+        assert!(oracles.get(&7).is_none());
+    }
+
+    #[test]
+    fn load_dir() {
+        type TestGraph = RTreeGraph<DirectedCsrGraph<f64, Coord<f64>>, f64>;
+
+        let graph = square_graph();
+
+        let mut oracles = OracleCollection::default();
+        oracles
+            .build_for_nodes(
+                &FxHashSet::from_iter([0, 2]),
+                0.2,
+                &graph,
+                DefaultOracleParams::default(),
+            )
+            .unwrap();
+
+        let dir = std::env::temp_dir().join(format!("burp_load_dir_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("graph.gmp"), []).unwrap();
+        for (poi, oracle) in oracles.iter() {
+            let file = File::create(dir.join(format!("graph_{poi}.omp"))).unwrap();
+            oracle
+                .serialize(&mut rmp_serde::Serializer::new(BufWriter::new(file)))
+                .unwrap();
+        }
+
+        let loaded: Result<OracleCollection<TestGraph>, _> = OracleCollection::load_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+        let loaded = loaded.unwrap();
+
+        for poi in [0, 2] {
+            let oracle = loaded.get(&poi).unwrap();
+            let expected = oracles.get(&poi).unwrap();
+            assert_eq!(oracle.poi(), poi);
+            assert_eq!(oracle.size(), expected.size());
+            assert_eq!(oracle.r_tree.size(), 2 * oracle.size());
+
+            for (_, s) in graph.nodes_iter() {
+                for (_, t) in graph.nodes_iter() {
+                    assert_eq!(oracle.get_beer_pois(s, t), expected.get_beer_pois(s, t));
+                }
+            }
+        }
+
+        let mut first = OracleCollection::default();
+        first.insert(Oracle::new(1));
+        let merged = first.merge(loaded);
+        assert_eq!(merged.iter().count(), 3);
+    }
+
+    #[test]
+    fn single_file() {
+        type TestGraph = RTreeGraph<DirectedCsrGraph<f64, Coord<f64>>, f64>;
+
+        let graph = square_graph();
+
+        let mut oracles = OracleCollection::default();
+        oracles
+            .build_for_nodes(
+                &FxHashSet::from_iter([1, 3]),
+                0.2,
+                &graph,
+                DefaultOracleParams::default(),
+            )
+            .unwrap();
+
+        let mut buf = Vec::new();
+        oracles.write_to(&mut buf).unwrap();
+        let loaded: OracleCollection<TestGraph> = OracleCollection::read_from(&buf[..]).unwrap();
+
+        assert_eq!(loaded.iter().count(), 2);
+        for poi in [1, 3] {
+            let oracle = loaded.get(&poi).unwrap();
+            let expected = oracles.get(&poi).unwrap();
+            assert_eq!(oracle.poi(), poi);
+            assert_eq!(oracle.size(), expected.size());
+
+            for (_, s) in graph.nodes_iter() {
+                for (_, t) in graph.nodes_iter() {
+                    assert_eq!(oracle.get_beer_pois(s, t), expected.get_beer_pois(s, t));
+                }
+            }
+        }
+
+        assert!(OracleCollection::<TestGraph>::read_from(&buf[..buf.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn epsilon_per_poi() {
+        let graph = square_graph();
+        let pois = FxHashSet::from_iter([0, 2]);
+
+        let mut oracles = OracleCollection::default();
+        oracles
+            .build_for_nodes_with_epsilon(
+                &pois,
+                |poi| if poi == 0 { 0.2 } else { 2. },
+                &graph,
+                DefaultOracleParams::default(),
+            )
+            .unwrap();
 
-    use super::Oracle;
+        // Every block holds a single node. With epsilon 0.2 the pairs on a shortest path
+        // through the poi are in-path, with 2 also the neighbours on the far side.
+        assert_eq!(oracles.get(&0).unwrap().size(), 9);
+        assert_eq!(oracles.get(&2).unwrap().size(), 13);
+
+        let mut uniform = OracleCollection::default();
+        uniform
+            .build_for_nodes(&pois, 0.2, &graph, DefaultOracleParams::default())
+            .unwrap();
+        assert_eq!(uniform.get(&0).unwrap().size(), 9);
+        assert_eq!(uniform.get(&2).unwrap().size(), 9);
+    }
+
+    #[test]
+    fn block_pairs() {
+        let graph = square_graph();
+
+        let (oracle, _) =
+            Oracle::build_for_node(0, 0.2, &graph, DefaultOracleParams::default()).unwrap();
+        assert_eq!(oracle.block_pairs().count(), oracle.size());
+
+        let mut oracles = OracleCollection::default();
+        oracles
+            .build_for_nodes(
+                &FxHashSet::from_iter([0]),
+                0.2,
+                &graph,
+                DefaultOracleParams::default(),
+            )
+            .unwrap();
+        assert_eq!(oracles.block_pairs_for_poi(0).count(), oracle.size());
+        assert_eq!(oracles.block_pairs_for_poi(99).count(), 0);
+    }
+
+    #[test]
+    fn store_paths() {
+        let graph = square_graph();
+
+        let params = DefaultOracleParams {
+            store_paths: true,
+            ..Default::default()
+        };
+        let (oracle, _) = Oracle::build_for_node(0, 0.2, &graph, params).unwrap();
+        assert!(oracle.size() > 0);
+
+        for block_pair in oracle.block_pairs() {
+            let values = block_pair.values();
+            let path = block_pair.path().unwrap();
+            let nodes: Vec<usize> = path.path.iter().map(|target| target.target()).collect();
+
+            assert_eq!(nodes.first(), Some(&values.s));
+            assert_eq!(nodes.last(), Some(&values.t));
+            assert!(nodes.contains(&0));
+            assert!(
+                block_pair
+                    .s_block()
+                    .intersects(&graph.node_coord(values.s).unwrap())
+            );
+            assert!(
+                block_pair
+                    .t_block()
+                    .intersects(&graph.node_coord(values.t).unwrap())
+            );
+            assert_eq!(path.cost(), values.d_sp + values.d_pt);
+
+            let (s, t) = (block_pair.s_block().center(), block_pair.t_block().center());
+            assert!(oracle.get_beer_path(&s, &t).is_some());
+        }
+
+        let (oracle, _) =
+            Oracle::build_for_node(0, 0.2, &graph, DefaultOracleParams::default()).unwrap();
+        assert!(
+            oracle
+                .block_pairs()
+                .all(|block_pair| block_pair.path().is_none())
+        );
+    }
+
+    #[test]
+    fn compact() {
+        let graph = square_graph();
+
+        let (mut oracle, _) =
+            Oracle::build_for_node(0, 0.2, &graph, DefaultOracleParams::default()).unwrap();
+        assert_eq!(oracle.compact(), 0);
+        let size = oracle.size();
+        assert_eq!(oracle.r_tree.size(), 2 * size);
+
+        // The removed block pair is still alive, but its entries go anyway.
+        let removed = oracle.block_pairs.pop().unwrap();
+        let (s, t) = (removed.s_block().center(), removed.t_block().center());
+
+        assert_eq!(oracle.compact(), 2);
+        assert_eq!(oracle.r_tree.size(), 2 * (size - 1));
+        assert!(oracle.get_block_pairs(&s, &t).is_empty());
+        assert!(
+            oracle
+                .r_tree
+                .iter()
+                .all(|geom| !Arc::ptr_eq(&geom.data.upgrade().unwrap(), &removed))
+        );
+    }
+
+    #[test]
+    fn max_depth() {
+        let graph = square_graph();
+        let build = |max_depth| {
+            let params = DefaultOracleParams {
+                max_depth,
+                ..Default::default()
+            };
+            Oracle::build_for_node(0, 0.2, &graph, params).unwrap().0
+        };
+
+        let full = build(None);
+        // The children of the root already hold single nodes, so they are all decided.
+        assert_eq!(build(Some(1)).size(), full.size());
+
+        // The undecided root is stored as a whole.
+        let limited = build(Some(0));
+        assert_eq!(limited.size(), 1);
+        assert!(limited.size() < full.size());
+
+        // Points inside the blocks of the nodes, blocks do not contain their boundary.
+        let points = [(0.25, 0.25), (0.75, 0.25), (0.75, 0.75), (0.25, 0.75)].map(Coord::from);
+        for s in &points {
+            for t in &points {
+                assert!(
+                    limited
+                        .get_beer_pois(s, t)
+                        .is_superset(&full.get_beer_pois(s, t))
+                );
+            }
+        }
+        assert!(!full.get_beer_pois(&points[1], &points[2]).contains(&0));
+        assert!(limited.get_beer_pois(&points[1], &points[2]).contains(&0));
+    }
+
+    #[test]
+    fn parallel() {
+        let mut graph: DirectedCsrGraph<f64, Coord<f64>> = DirectedCsrGraph::default();
+        for y in 0..4 {
+            for x in 0..4 {
+                graph.add_node(Coord {
+                    x: x as f64,
+                    y: y as f64,
+                });
+            }
+        }
+        for node in 0..16 {
+            let right = (node % 4 < 3).then_some(node + 1);
+            let up = (node < 12).then_some(node + 4);
+            for neighbor in right.into_iter().chain(up) {
+                graph.add_edge(node, neighbor, 1. + (node % 3) as f64);
+                graph.add_edge(neighbor, node, 1. + (neighbor % 3) as f64);
+            }
+        }
+        let graph = RTreeGraph::new_from_graph(graph);
+
+        for merge_blocks in [false, true] {
+            let build = |parallel| {
+                let params = DefaultOracleParams {
+                    merge_blocks,
+                    parallel,
+                    ..Default::default()
+                };
+                Oracle::build_for_node(5, 0.2, &graph, params).unwrap()
+            };
+            let (sequential, sequential_tree) = build(false);
+            let (parallel, parallel_tree) = build(true);
+
+            assert!(sequential.size() > 1);
+            assert!(sequential.block_pairs().eq(parallel.block_pairs()));
+            let nodes = |tree: &id_tree::Tree<(BlockPair<f64, f64>, bool)>| {
+                let root = tree.root_node_id().unwrap();
+                tree.traverse_pre_order(root)
+                    .unwrap()
+                    .map(|node| (node.data().0.clone(), node.data().1))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(nodes(&sequential_tree), nodes(&parallel_tree));
+        }
+    }
+
+    #[test]
+    fn from_split_tree() {
+        let graph = RTreeGraph::new_from_graph(DirectedCsrGraph::from_coords_and_edges(
+            coords(&[(0., 0.), (1., 0.), (1., 1.), (0., 1.), (0.5, 0.5)]),
+            both_ways(&[(0, 1), (1, 2), (2, 3), (3, 0), (0, 4), (4, 2)]),
+        ));
+
+        for merge_blocks in [false, true] {
+            let params = DefaultOracleParams {
+                merge_blocks,
+                ..Default::default()
+            };
+            let (oracle, tree) = Oracle::build_for_node(4, 0.2, &graph, params).unwrap();
+            let derived = Oracle::from_split_tree(&tree, 4);
+
+            assert_eq!(derived.poi(), 4);
+            assert_eq!(derived.size(), oracle.size());
+            for _ in 0..100 {
+                let s = Coord::from((random::<f64>(), random::<f64>()));
+                let t = Coord::from((random::<f64>(), random::<f64>()));
+                assert_eq!(derived.get_beer_pois(&s, &t), oracle.get_beer_pois(&s, &t));
+            }
+        }
+
+        let empty = Oracle::<f64, f64>::from_split_tree(&id_tree::Tree::new(), 0);
+        assert_eq!(empty.size(), 0);
+    }
+
+    #[test]
+    fn get_pois_for_nodes() {
+        let graph = square_graph();
+        let oracle = Oracle::build_for_node(0, 0.2, &graph, DefaultOracleParams::default())
+            .unwrap()
+            .0;
+
+        // Every node sits on the corner of its block, and points inside the same blocks give
+        // the same pois.
+        let points = [(0.25, 0.25), (0.75, 0.25), (0.75, 0.75), (0.25, 0.75)].map(Coord::from);
+        for s in 0..4 {
+            for t in 0..4 {
+                assert_eq!(
+                    oracle.get_pois_for_nodes(s, t, &graph),
+                    oracle.get_beer_pois(&points[s], &points[t]),
+                    "({s}, {t})"
+                );
+            }
+        }
+
+        // Both shortest paths from 1 to 3 are as long as the one over 0.
+        assert!(oracle.get_pois_for_nodes(1, 3, &graph).contains(&0));
+        assert!(!oracle.get_pois_for_nodes(1, 2, &graph).contains(&0));
+        // The coordinates of the nodes are on the boundary of their blocks.
+        let (s, t) = (graph.node_coord(1).unwrap(), graph.node_coord(3).unwrap());
+        assert!(oracle.get_beer_pois(&s, &t).is_empty());
+
+        assert!(oracle.get_pois_for_nodes(1, 4, &graph).is_empty());
+    }
+
+    #[test]
+    fn beer_path_with_fallback() {
+        let nodes = coords(&SQUARE)
+            .into_iter()
+            .map(|coord| CoordNode::<f64, Poi>::new(coord, vec![]))
+            .collect();
+        let mut graph = PoiGraph::new(RTreeGraph::new_from_graph(
+            DirectedCsrGraph::from_coords_and_edges(nodes, both_ways(&SQUARE_EDGES)),
+        ));
+        graph.add_node_pois(vec![(0, vec![])]);
+
+        let mut oracles = OracleCollection::default();
+        oracles
+            .build_for_nodes(
+                graph.poi_nodes(),
+                0.2,
+                graph.graph(),
+                DefaultOracleParams::default(),
+            )
+            .unwrap();
+
+        // The poi lies on a shortest path from 1 to 3, so the oracle has a block pair.
+        assert_eq!(
+            oracles.beer_path_with_fallback(1, 3, &graph),
+            Some((2., 0, true))
+        );
+        // The detour over the poi is not within epsilon, so only Dijkstra finds it.
+        assert_eq!(
+            oracles.beer_path_with_fallback(1, 2, &graph),
+            Some((3., 0, false))
+        );
+        let empty: OracleCollection<RTreeGraphType<Poi>> = OracleCollection::default();
+        assert_eq!(
+            empty.beer_path_with_fallback(1, 3, &graph),
+            Some((2., 0, false))
+        );
+        assert_eq!(oracles.beer_path_with_fallback(1, 1, &graph), None);
+        assert_eq!(oracles.beer_path_with_fallback(0, 0, &graph), None);
+    }
+
+    #[test]
+    fn coverage() {
+        let graph = square_graph();
+        let oracle = Oracle::build_for_node(0, 0.2, &graph, DefaultOracleParams::default())
+            .unwrap()
+            .0;
+
+        // Pairs straddling the poi like (1, 3) hit, others like (1, 2) do not.
+        let coverage = oracle.coverage(&graph, 1000);
+        assert!(coverage > 0.);
+        assert!(coverage < 1.);
+
+        assert_eq!(oracle.coverage(&graph, 0), 0.);
+        assert_eq!(Oracle::<f64, f64>::new(0).coverage(&graph, 100), 0.);
+    }
+
+    #[test]
+    fn r_tree_params() {
+        let nodes = (0..9)
+            .map(|node| Coord {
+                x: (node % 3) as f64,
+                y: (node / 3) as f64,
+            })
+            .collect();
+        let sides: Vec<_> = (0..9)
+            .flat_map(|a| [(a, a + 1), (a, a + 3)])
+            .filter(|&(a, b)| b < 9 && (b != a + 1 || b % 3 != 0))
+            .collect();
+        let graph = RTreeGraph::new_from_graph(DirectedCsrGraph::from_coords_and_edges(
+            nodes,
+            both_ways(&sides),
+        ));
+        let pois = FxHashSet::from_iter([0, 4, 8]);
+
+        let mut default = OracleCollection::default();
+        default
+            .build_for_nodes(&pois, 0.2, &graph, DefaultOracleParams::default())
+            .unwrap();
+        let mut small = OracleCollection::with_params(SmallNodeParams);
+        small
+            .build_for_nodes(&pois, 0.2, &graph, DefaultOracleParams::default())
+            .unwrap();
+        let mut large = OracleCollection::with_params(LargeNodeParams);
+        large
+            .build_for_nodes(&pois, 0.2, &graph, DefaultOracleParams::default())
+            .unwrap();
+
+        for poi in pois {
+            let expected = default.get(&poi).unwrap();
+            let small = small.get(&poi).unwrap();
+            let large = large.get(&poi).unwrap();
+            assert_eq!(small.size(), expected.size());
+            assert_eq!(large.size(), expected.size());
+
+            for (_, s) in graph.nodes_iter() {
+                for (_, t) in graph.nodes_iter() {
+                    let beer_pois = expected.get_beer_pois(s, t);
+                    assert_eq!(small.get_beer_pois(s, t), beer_pois);
+                    assert_eq!(large.get_beer_pois(s, t), beer_pois);
+                }
+            }
+        }
+    }
 
     #[test]
     fn add_block_pair_test() {
@@ -475,7 +1715,23 @@ mod test {
             &graph,
         );
 
-        oracle.add_block_pair(block_pair);
+        let added = oracle.add_block_pair(block_pair.clone()).unwrap();
+        assert_eq!(oracle.size(), 1);
+
+        assert_eq!(
+            oracle.add_block_pair(block_pair),
+            Err(OracleError::DuplicateBlockPair)
+        );
+        let other_poi = BlockPair::new(*added.s_block(), *added.t_block(), 1, 0.2, &graph);
+        assert_eq!(
+            oracle.add_block_pair(other_poi),
+            Err(OracleError::PoiMismatch {
+                oracle: 0,
+                block_pair: 1
+            })
+        );
+        assert_eq!(oracle.size(), 1);
+        assert_eq!(oracle.r_tree.size(), 2);
     }
 
     #[test]
@@ -492,7 +1748,7 @@ mod test {
             &graph,
         );
 
-        let expected = oracle.add_block_pair(block_pair);
+        let expected = oracle.add_block_pair(block_pair).unwrap();
 
         let block_pairs = oracle.get_block_pairs(&(0.6, 0.8).into(), &(10.5, 10.).into());
 
@@ -513,7 +1769,7 @@ mod test {
             &graph,
         );
 
-        oracle.add_block_pair(block_pair);
+        oracle.add_block_pair(block_pair).unwrap();
 
         let mut buf = vec![];
 
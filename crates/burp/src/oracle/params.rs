@@ -1,20 +1,48 @@
 use std::fmt::Debug;
 
+use rstar::{RStarInsertionStrategy, RTreeParams};
+
 use crate::oracle::{
     MinimalSplitStrategy, SimpleSplitStrategy, SplitStrategy as SplitStrategyTrait,
 };
 
-pub trait OracleParams: Copy + Clone + Debug + Default {
+pub trait OracleParams: Copy + Clone + Debug + Default + Send + Sync {
     /// The split strategy which is used for spliting the block pairs.
     type SplitStrategy: SplitStrategyTrait;
 
     /// Wheater to merge blocks into their parents if they all are in-path.
     fn merge_blocks(&self) -> bool;
+
+    /// Depth from which block pairs are no longer split but stored as in-path.
+    ///
+    /// The root block pair has depth 0. Undecided block pairs are over-included, so the
+    /// oracle never misses a poi but may report some which are not on a near-shortest path.
+    fn max_depth(&self) -> Option<usize> {
+        None
+    }
+
+    /// Whether in-path block pairs keep one beer path, see
+    /// [BlockPair::path](super::block_pair::BlockPair::path).
+    ///
+    /// Makes the oracle larger and its build slower, as every stored path is searched.
+    fn store_paths(&self) -> bool {
+        false
+    }
+
+    /// Whether sibling block pairs are split in parallel on the rayon thread pool.
+    ///
+    /// The oracle is the same either way, this only speeds up building a single oracle.
+    fn parallel(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
 pub struct DefaultOracleParams {
     pub merge_blocks: bool,
+    pub max_depth: Option<usize>,
+    pub store_paths: bool,
+    pub parallel: bool,
 }
 
 impl OracleParams for DefaultOracleParams {
@@ -23,11 +51,26 @@ impl OracleParams for DefaultOracleParams {
     fn merge_blocks(&self) -> bool {
         self.merge_blocks
     }
+
+    fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    fn store_paths(&self) -> bool {
+        self.store_paths
+    }
+
+    fn parallel(&self) -> bool {
+        self.parallel
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
 pub struct MinSplitParams {
     pub merge_blocks: bool,
+    pub max_depth: Option<usize>,
+    pub store_paths: bool,
+    pub parallel: bool,
 }
 
 impl OracleParams for MinSplitParams {
@@ -36,4 +79,44 @@ impl OracleParams for MinSplitParams {
     fn merge_blocks(&self) -> bool {
         self.merge_blocks
     }
+
+    fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    fn store_paths(&self) -> bool {
+        self.store_paths
+    }
+
+    fn parallel(&self) -> bool {
+        self.parallel
+    }
+}
+
+/// R-tree parameters with small nodes (2 to 4 children, 1 reinsertion).
+///
+/// Queries visit fewer entries per node, inserts split nodes more often.
+/// See [Oracle::with_params](super::oracle::Oracle::with_params).
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct SmallNodeParams;
+
+impl RTreeParams for SmallNodeParams {
+    const MIN_SIZE: usize = 2;
+    const MAX_SIZE: usize = 4;
+    const REINSERTION_COUNT: usize = 1;
+    type DefaultInsertionStrategy = RStarInsertionStrategy;
+}
+
+/// R-tree parameters with large nodes (8 to 24 children, 6 reinsertions).
+///
+/// Inserts are cheaper and the tree is shallower, queries check more entries per node.
+/// See [Oracle::with_params](super::oracle::Oracle::with_params).
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct LargeNodeParams;
+
+impl RTreeParams for LargeNodeParams {
+    const MIN_SIZE: usize = 8;
+    const MAX_SIZE: usize = 24;
+    const REINSERTION_COUNT: usize = 6;
+    type DefaultInsertionStrategy = RStarInsertionStrategy;
 }
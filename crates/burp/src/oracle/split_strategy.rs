@@ -1,5 +1,6 @@
 use std::fmt::{Debug, Display};
 
+use geo::{CoordFloat, Rect};
 use graph_rs::{CoordGraph, algorithms::dijkstra::Dijkstra};
 
 use crate::oracle::{block_pair::BlockPair, oracle::Radius};
@@ -13,7 +14,43 @@ pub trait SplitStrategy: Display {
         G::C: rstar::RTreeNum + geo::CoordFloat;
 }
 
-/// Split both blocks into 4 children.
+/// Ratio of the width to the height of `rect` in meters, taking coordinates as longitude and
+/// latitude.
+///
+/// A degree of longitude shrinks with the cosine of the latitude, so the width is scaled by it
+/// at the center of `rect`.
+pub fn metric_aspect_ratio<C: CoordFloat>(rect: &Rect<C>) -> C {
+    let latitude = rect.center().y.to_radians();
+    rect.width() * latitude.cos() / rect.height()
+}
+
+/// Splits `rect` into children which are as close to square in meters as possible.
+///
+/// A block which is more than `sqrt(2)` times longer than wide in meters is halved on its long
+/// edge, otherwise it is split into 4.
+pub fn split_metric_square<C: CoordFloat>(rect: &Rect<C>) -> Vec<Rect<C>> {
+    let aspect_ratio = metric_aspect_ratio(rect);
+    let sqrt_2 = (C::one() + C::one()).sqrt();
+    if aspect_ratio > sqrt_2 {
+        rect.split_x().to_vec()
+    } else if aspect_ratio < sqrt_2.recip() {
+        rect.split_y().to_vec()
+    } else {
+        rect.split_y()
+            .into_iter()
+            .flat_map(|split| split.split_x())
+            .collect()
+    }
+}
+
+/// Returns `true` if a node with edges lies in `block`. Other blocks are on no path.
+fn has_edges<G: CoordGraph>(block: &Rect<G::C>, graph: &G) -> bool {
+    graph
+        .locate_in_envelope(block)
+        .any(|node| graph.degree(node) > 0)
+}
+
+/// Split both blocks into 4 children, or into 2 if they are much longer than wide in meters.
 #[derive(Debug, Clone, Copy)]
 pub struct SimpleSplitStrategy;
 
@@ -31,26 +68,20 @@ impl SplitStrategy for SimpleSplitStrategy {
         G::C: rstar::RTreeNum + geo::CoordFloat,
     {
         let children = (
-            block_pair
-                .s_block()
-                .split_y()
-                .into_iter()
-                .flat_map(|split| split.split_x()),
-            block_pair
-                .t_block()
-                .split_y()
-                .into_iter()
-                .flat_map(|split| split.split_x()),
+            split_metric_square(block_pair.s_block()),
+            split_metric_square(block_pair.t_block()),
         );
 
         let children = (
             children
                 .0
-                .filter(|block| graph.locate_in_envelope(block).peekable().peek().is_some())
+                .into_iter()
+                .filter(|block| has_edges(block, graph))
                 .collect::<Vec<_>>(),
             children
                 .1
-                .filter(|block| graph.locate_in_envelope(block).peekable().peek().is_some())
+                .into_iter()
+                .filter(|block| has_edges(block, graph))
                 .collect::<Vec<_>>(),
         );
         children
@@ -71,7 +102,7 @@ impl SplitStrategy for SimpleSplitStrategy {
     }
 }
 
-/// Split only one block on its long edge.
+/// Split only one block on its long edge in meters.
 #[derive(Debug, Clone, Copy)]
 pub struct MinimalSplitStrategy;
 
@@ -92,7 +123,7 @@ impl SplitStrategy for MinimalSplitStrategy {
         let r_b = block_pair.values().r_bf.cost() + block_pair.values().r_bb.cost();
 
         let children = if r_a < r_b {
-            if block_pair.t_block().width() < block_pair.t_block().height() {
+            if metric_aspect_ratio(block_pair.t_block()) < G::C::one() {
                 (
                     vec![*block_pair.s_block()],
                     block_pair.t_block().split_y().to_vec(),
@@ -104,7 +135,7 @@ impl SplitStrategy for MinimalSplitStrategy {
                 )
             }
         } else {
-            if block_pair.s_block().width() < block_pair.s_block().height() {
+            if metric_aspect_ratio(block_pair.s_block()) < G::C::one() {
                 (
                     block_pair.s_block().split_y().to_vec(),
                     vec![*block_pair.t_block()],
@@ -121,12 +152,12 @@ impl SplitStrategy for MinimalSplitStrategy {
             children
                 .0
                 .into_iter()
-                .filter(|block| graph.locate_in_envelope(block).peekable().peek().is_some())
+                .filter(|block| has_edges(block, graph))
                 .collect::<Vec<_>>(),
             children
                 .1
                 .into_iter()
-                .filter(|block| graph.locate_in_envelope(block).peekable().peek().is_some())
+                .filter(|block| has_edges(block, graph))
                 .collect::<Vec<_>>(),
         );
         children
@@ -146,3 +177,38 @@ impl SplitStrategy for MinimalSplitStrategy {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use geo::{Coord, Rect};
+
+    use super::{metric_aspect_ratio, split_metric_square};
+
+    /// Distance of an aspect ratio from a square, the same for `r` and `1 / r`.
+    fn skew(aspect_ratio: f64) -> f64 {
+        aspect_ratio.ln().abs()
+    }
+
+    #[test]
+    fn metric_square_children() {
+        // One by one degree at 60° north is about twice as high as wide.
+        let block = Rect::new(Coord { x: 13., y: 59.5 }, Coord { x: 14., y: 60.5 });
+        assert!((metric_aspect_ratio(&block) - 0.5).abs() < 0.01);
+
+        let children = split_metric_square(&block);
+        assert_eq!(children.len(), 2);
+        let degree_squares: Vec<_> = block
+            .split_y()
+            .into_iter()
+            .flat_map(|split| split.split_x())
+            .collect();
+        for (child, degree_square) in children.iter().zip(&degree_squares) {
+            assert!(skew(metric_aspect_ratio(child)) < skew(metric_aspect_ratio(degree_square)));
+            assert!(skew(metric_aspect_ratio(child)) < 0.05);
+        }
+
+        // Close to the equator degrees are about square, so the block is split into 4.
+        let block = Rect::new(Coord { x: 0., y: 0. }, Coord { x: 1., y: 1. });
+        assert_eq!(split_metric_square(&block).len(), 4);
+    }
+}
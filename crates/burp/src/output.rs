@@ -0,0 +1,77 @@
+use geo_types::Geometry;
+use geozero::ToJson;
+use graph_rs::{CoordGraph, graph::Path};
+use num_traits::Num;
+use serde::Serialize;
+
+/// Converts a [Path] into a GeoJSON `Feature` with a `LineString` geometry.
+///
+/// The total cost of the path is stored in the `cost` property.
+/// Returns `None` if a coordinate could not be retrieved for a node of the path.
+pub fn path_to_geojson<G, EV>(path: &Path<EV>, graph: &G) -> Option<String>
+where
+    G: CoordGraph<C = f64>,
+    EV: Num + Copy + Serialize,
+{
+    let line_string = path.line_string(graph)?;
+    let geometry: serde_json::Value =
+        serde_json::from_str(&Geometry::LineString(line_string).to_json().ok()?).ok()?;
+
+    Some(
+        serde_json::json!({
+            "type": "Feature",
+            "geometry": geometry,
+            "properties": {
+                "cost": path.cost(),
+            },
+        })
+        .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use geo::{Coord, CoordsIter};
+    use geozero::{geo_types::GeoWriter, geojson::read_geojson};
+    use graph_rs::{
+        Graph,
+        graph::{Path, Target, csr::DirectedCsrGraph, rstar::RTreeGraph},
+    };
+
+    use super::path_to_geojson;
+
+    #[test]
+    fn path_to_geojson_round_trip() {
+        let mut graph: DirectedCsrGraph<f64, Coord<f64>> = DirectedCsrGraph::default();
+        graph.add_node(Coord { x: 0., y: 0. });
+        graph.add_node(Coord { x: 1., y: 0. });
+        graph.add_node(Coord { x: 1., y: 1. });
+        graph.add_edge(0, 1, 1.);
+        graph.add_edge(1, 2, 1.);
+        let graph = RTreeGraph::new_from_graph(graph);
+
+        let path = Path::new(vec![
+            Target::new(0, 0.),
+            Target::new(1, 1.),
+            Target::new(2, 2.),
+        ]);
+
+        let geojson = path_to_geojson(&path, &graph).unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&geojson).unwrap();
+        assert_eq!(value["properties"]["cost"], 2.);
+
+        let mut writer = GeoWriter::new();
+        read_geojson(geojson.as_bytes(), &mut writer).unwrap();
+
+        let geometry = writer.take_geometry().unwrap();
+        assert_eq!(
+            geometry.coords_iter().collect::<Vec<_>>(),
+            vec![
+                Coord { x: 0., y: 0. },
+                Coord { x: 1., y: 0. },
+                Coord { x: 1., y: 1. }
+            ]
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Geojson to oracles in one call, for using burp as a library.
+
+use std::{
+    cell::RefCell,
+    io::{BufReader, Read},
+    rc::Rc,
+};
+
+use geozero::geojson::read_geojson;
+use graph_rs::{Graph, graph::rstar::RTreeGraph};
+use log::info;
+use rand::{rng, seq::index::sample};
+use rstar::{DefaultParams, RTreeParams};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    events::Events,
+    input::{self, filters::Properties, geo_zero::GraphWriter},
+    oracle::{
+        OracleParams, PoiGraph, RTreeGraphType, block_pair::BlockPair, oracle::OracleCollection,
+    },
+    types::Poi,
+};
+
+/// Split tree of each poi, as returned by [build_oracles].
+pub type SplitTrees = FxHashMap<usize, id_tree::Tree<(BlockPair<f64, f64>, bool)>>;
+
+/// The nodes an oracle is built for.
+#[derive(Clone, Debug)]
+pub enum PoiSelection {
+    /// Up to this many nodes sampled uniformly.
+    Sample(usize),
+    /// These node ids of the built graph.
+    Nodes(FxHashSet<usize>),
+}
+
+/// Reads a road network from geojson and builds the oracles for the selected pois.
+///
+/// Gzip compressed geojson is decompressed on the fly. Only features accepted by `filter` are
+/// read, see [crate::input::filters]. Like the `graph` command the graph is reduced to its
+/// biggest strongly connected component, so node ids in [PoiSelection::Nodes] refer to the
+/// reduced graph.
+pub fn build_oracle_from_geojson<R, P>(
+    reader: R,
+    filter: impl Fn(&Properties) -> bool + 'static,
+    pois: PoiSelection,
+    epsilon: f64,
+    params: P,
+) -> Result<(PoiGraph<Poi>, OracleCollection<RTreeGraphType<Poi>>), String>
+where
+    R: Read,
+    P: OracleParams,
+{
+    build_oracle_from_geojson_with_progress(reader, filter, pois, epsilon, params, |_| {})
+}
+
+/// Like [build_oracle_from_geojson], but reports its stages and progress to `progress`.
+///
+/// Features read are reported as [Events::ImportProgress] and built oracles as
+/// [Events::OracleProgress], each stage is announced with an [Events::Stage] first.
+pub fn build_oracle_from_geojson_with_progress<R, P>(
+    reader: R,
+    filter: impl Fn(&Properties) -> bool + 'static,
+    pois: PoiSelection,
+    epsilon: f64,
+    params: P,
+    progress: impl FnMut(Events) + 'static,
+) -> Result<(PoiGraph<Poi>, OracleCollection<RTreeGraphType<Poi>>), String>
+where
+    R: Read,
+    P: OracleParams,
+{
+    // Shared with the graph writer, which keeps its callback until it is dropped.
+    let progress = Rc::new(RefCell::new(progress));
+    let report = |event: Events| (*progress.borrow_mut())(event);
+
+    report(Events::Stage("Reading geojson"));
+    let mut graph_writer = GraphWriter::new(filter).with_progress({
+        let progress = progress.clone();
+        move |features| (*progress.borrow_mut())(Events::ImportProgress { features })
+    });
+    read_geojson_into(reader, &mut graph_writer)?;
+
+    report(Events::Stage("Building graph"));
+    let mut graph = build_graph(graph_writer);
+
+    let nodes: Vec<usize> = match pois {
+        PoiSelection::Sample(count) => {
+            let node_count = graph.graph().node_count();
+            sample(&mut rng(), node_count, count.min(node_count)).into_vec()
+        }
+        PoiSelection::Nodes(nodes) => nodes.into_iter().collect(),
+    };
+    if let Some(node) = nodes
+        .iter()
+        .find(|node| **node >= graph.graph().node_count())
+    {
+        return Err(format!("Node {node} is not in the graph"));
+    }
+    graph.add_node_pois(nodes.into_iter().map(|node| (node, vec![])).collect());
+
+    report(Events::Stage("Building oracles"));
+    let (oracles, _) = build_oracles(&graph, epsilon, params, DefaultParams, report)?;
+
+    Ok((graph, oracles))
+}
+
+/// Reads one geojson file into `graph_writer`, decompressing it if it is gzip compressed.
+///
+/// Several files read into the same writer are joined into one graph, see
+/// [GraphWriter::get_graph].
+pub fn read_geojson_into<R: Read>(reader: R, graph_writer: &mut GraphWriter) -> Result<(), String> {
+    let reader = input::decompressed(BufReader::new(reader)).map_err(|err| err.to_string())?;
+    read_geojson(reader, graph_writer).map_err(|err| err.to_string())
+}
+
+/// Builds the graph of everything read by `graph_writer` and indexes its nodes for spatial
+/// queries. The graph has no pois yet.
+pub fn build_graph(graph_writer: GraphWriter) -> PoiGraph<Poi> {
+    info!("Read {}", graph_writer.stats());
+    PoiGraph::new(RTreeGraph::new_from_graph(graph_writer.get_graph()))
+}
+
+/// Builds an oracle for every poi of `graph`, with blocks indexed by an r-tree with
+/// `r_tree_params`.
+///
+/// Returns the oracles and the split tree of each poi. Each built oracle is reported as
+/// [Events::OracleProgress].
+pub fn build_oracles<P, R>(
+    graph: &PoiGraph<Poi>,
+    epsilon: f64,
+    params: P,
+    r_tree_params: R,
+    progress: impl FnMut(Events),
+) -> Result<(OracleCollection<RTreeGraphType<Poi>, R>, SplitTrees), String>
+where
+    P: OracleParams,
+    R: RTreeParams + Default,
+{
+    let mut oracles = OracleCollection::with_params(r_tree_params);
+    let split_trees = oracles
+        .build_for_nodes_with_progress(
+            graph.poi_nodes(),
+            |_| epsilon,
+            graph.graph(),
+            params,
+            progress,
+        )
+        .map_err(|err| err.to_string())?;
+
+    Ok((oracles, split_trees))
+}
@@ -1,15 +1,14 @@
 use core::fmt;
-use std::{
-    fmt::{Debug, Display},
-    sync::Arc,
-};
+use std::fmt::{Debug, Display};
 
 use crate::oracle::NodeTrait;
-use geo::{coord, CoordNum};
+use geo::{CoordNum, coord};
 use geo_types::Coord;
 use graph_rs::Coordinate;
-use rstar::{PointDistance, RTreeObject};
-use serde::{Deserialize, Serialize};
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{self, Unexpected, Visitor},
+};
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct Poi {
@@ -33,7 +32,12 @@ impl Poi {
 
 impl NodeTrait for Poi {}
 
-#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+/// Serialized as a single byte, as graphs and oracles store one per poi.
+///
+/// Deserializing also accepts the variant name, which files written before the
+/// byte encoding contain.
+#[derive(Serialize, PartialEq, Debug, Clone)]
+#[serde(into = "u8")]
 pub enum Amenity {
     None,
     Bar,
@@ -46,6 +50,138 @@ pub enum Amenity {
     Restaurant,
 }
 
+impl Amenity {
+    /// Maps the value of an OSM `amenity` tag.
+    pub fn from_osm_tag(tag: &str) -> Self {
+        match tag {
+            "bar" => Amenity::Bar,
+            "biergarten" => Amenity::Biergarten,
+            "cafe" => Amenity::Cafe,
+            "fast_food" => Amenity::FastFood,
+            "food_court" => Amenity::FoodCourt,
+            "pub" => Amenity::Pub,
+            "ice_cream" => Amenity::IceCream,
+            "restaurant" => Amenity::Restaurant,
+            _ => Amenity::None,
+        }
+    }
+}
+
+impl From<Amenity> for u8 {
+    fn from(value: Amenity) -> Self {
+        match value {
+            Amenity::None => 0,
+            Amenity::Bar => 1,
+            Amenity::Biergarten => 2,
+            Amenity::Cafe => 3,
+            Amenity::FastFood => 4,
+            Amenity::FoodCourt => 5,
+            Amenity::IceCream => 6,
+            Amenity::Pub => 7,
+            Amenity::Restaurant => 8,
+        }
+    }
+}
+
+impl TryFrom<u8> for Amenity {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Amenity::None,
+            1 => Amenity::Bar,
+            2 => Amenity::Biergarten,
+            3 => Amenity::Cafe,
+            4 => Amenity::FastFood,
+            5 => Amenity::FoodCourt,
+            6 => Amenity::IceCream,
+            7 => Amenity::Pub,
+            8 => Amenity::Restaurant,
+            _ => return Err(format!("Unknown amenity {value}")),
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Amenity {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(AmenityVisitor)
+    }
+}
+
+struct AmenityVisitor;
+
+impl Visitor<'_> for AmenityVisitor {
+    type Value = Amenity;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an amenity byte or variant name")
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        u8::try_from(v)
+            .ok()
+            .and_then(|byte| Amenity::try_from(byte).ok())
+            .ok_or_else(|| E::invalid_value(Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(match v {
+            "None" => Amenity::None,
+            "Bar" => Amenity::Bar,
+            "Biergarten" => Amenity::Biergarten,
+            "Cafe" => Amenity::Cafe,
+            "FastFood" => Amenity::FastFood,
+            "FoodCourt" => Amenity::FoodCourt,
+            "IceCream" => Amenity::IceCream,
+            "Pub" => Amenity::Pub,
+            "Restaurant" => Amenity::Restaurant,
+            _ => return Err(E::unknown_variant(v, AMENITY_VARIANTS)),
+        })
+    }
+}
+
+const AMENITY_VARIANTS: &[&str] = &[
+    "None",
+    "Bar",
+    "Biergarten",
+    "Cafe",
+    "FastFood",
+    "FoodCourt",
+    "IceCream",
+    "Pub",
+    "Restaurant",
+];
+
+/// Writes the OSM `amenity` tag, see [Amenity::from_osm_tag].
+impl Display for Amenity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Amenity::None => "none",
+                Amenity::Bar => "bar",
+                Amenity::Biergarten => "biergarten",
+                Amenity::Cafe => "cafe",
+                Amenity::FastFood => "fast_food",
+                Amenity::FoodCourt => "food_court",
+                Amenity::IceCream => "ice_cream",
+                Amenity::Pub => "pub",
+                Amenity::Restaurant => "restaurant",
+            }
+        )
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct CoordNode<C, T>
 where
@@ -150,42 +286,70 @@ where
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct RTreeObjectArc<T: RTreeObject> {
-    pub inner: Arc<T>,
-}
+#[cfg(test)]
+mod test {
+    use serde::Serialize;
 
-impl<T: RTreeObject> RTreeObject for RTreeObjectArc<T> {
-    type Envelope = T::Envelope;
-    fn envelope(&self) -> Self::Envelope {
-        self.inner.envelope()
-    }
-}
+    use super::{Amenity, Poi};
 
-impl<T: PointDistance> PointDistance for RTreeObjectArc<T> {
-    fn distance_2(
-        &self,
-        point: &<Self::Envelope as rstar::Envelope>::Point,
-    ) -> <<Self::Envelope as rstar::Envelope>::Point as rstar::Point>::Scalar {
-        self.inner.distance_2(point)
+    const AMENITIES: [Amenity; 9] = [
+        Amenity::None,
+        Amenity::Bar,
+        Amenity::Biergarten,
+        Amenity::Cafe,
+        Amenity::FastFood,
+        Amenity::FoodCourt,
+        Amenity::IceCream,
+        Amenity::Pub,
+        Amenity::Restaurant,
+    ];
+
+    /// A [Poi] with the amenity serialized by name, like serde does for enums by default.
+    #[derive(Serialize)]
+    struct NamedPoi<'a> {
+        name: &'a str,
+        amenity: String,
     }
 
-    fn contains_point(&self, point: &<Self::Envelope as rstar::Envelope>::Point) -> bool {
-        self.inner.contains_point(point)
+    #[test]
+    fn amenity_round_trip() {
+        for amenity in AMENITIES {
+            assert_eq!(Amenity::from_osm_tag(&amenity.to_string()), amenity);
+            let bytes = rmp_serde::to_vec(&amenity).unwrap();
+            assert_eq!(bytes.len(), 1);
+            assert_eq!(rmp_serde::from_slice::<Amenity>(&bytes).unwrap(), amenity);
+        }
+        assert!(rmp_serde::from_slice::<Amenity>(&rmp_serde::to_vec(&9u8).unwrap()).is_err());
     }
 
-    fn distance_2_if_less_or_equal(
-        &self,
-        point: &<Self::Envelope as rstar::Envelope>::Point,
-        max_distance_2: <<Self::Envelope as rstar::Envelope>::Point as rstar::Point>::Scalar,
-    ) -> Option<<<Self::Envelope as rstar::Envelope>::Point as rstar::Point>::Scalar> {
-        self.inner
-            .distance_2_if_less_or_equal(point, max_distance_2)
+    #[test]
+    fn amenity_old_encoding() {
+        for amenity in AMENITIES {
+            let name = rmp_serde::to_vec(&format!("{amenity:?}")).unwrap();
+            assert_eq!(rmp_serde::from_slice::<Amenity>(&name).unwrap(), amenity);
+        }
+        assert!(rmp_serde::from_slice::<Amenity>(&rmp_serde::to_vec("Bakery").unwrap()).is_err());
     }
-}
 
-impl<T: RTreeObject> RTreeObjectArc<T> {
-    pub fn new(inner: Arc<T>) -> Self {
-        Self { inner }
+    #[test]
+    fn compact_pois() {
+        let pois: Vec<Poi> = (0..1000)
+            .map(|i| Poi::new(format!("{i}"), AMENITIES[i % AMENITIES.len()].clone()))
+            .collect();
+        let named: Vec<NamedPoi> = pois
+            .iter()
+            .map(|poi| NamedPoi {
+                name: poi.name(),
+                amenity: format!("{:?}", poi.amenity()),
+            })
+            .collect();
+
+        let compact = rmp_serde::to_vec(&pois).unwrap();
+        assert!(compact.len() < rmp_serde::to_vec(&named).unwrap().len());
+        assert_eq!(rmp_serde::from_slice::<Vec<Poi>>(&compact).unwrap(), pois);
+
+        let json = serde_json::to_string(&pois).unwrap();
+        assert!(json.len() < serde_json::to_string(&named).unwrap().len());
+        assert_eq!(serde_json::from_str::<Vec<Poi>>(&json).unwrap(), pois);
     }
 }
@@ -1,4 +1,8 @@
+use geo::{Coord, Rect};
+use graph_rs::CoordGraph;
+use rand::{Rng, seq::SliceRandom};
 use rstar::{ParentNode, RTreeNode, RTreeObject};
+use rustc_hash::FxHashSet;
 
 pub fn r_tree_size<T: RTreeObject>(root: &ParentNode<T>) -> usize {
     root.children()
@@ -9,3 +13,112 @@ pub fn r_tree_size<T: RTreeObject>(root: &ParentNode<T>) -> usize {
         })
         .sum()
 }
+
+/// Samples up to `sample_size` nodes spread evenly over the area of `graph`.
+///
+/// The bounding rect of the graph is divided into a grid with roughly `sample_size`
+/// cells. Non-empty cells are visited round-robin in random order and a random node
+/// is drawn from each visited cell.
+pub fn sample_spatial<G, R>(graph: &G, sample_size: usize, rng: &mut R) -> Vec<usize>
+where
+    G: CoordGraph<C = f64>,
+    R: Rng + ?Sized,
+{
+    let Some(bounding_rect) = graph.bounding_rect() else {
+        return vec![];
+    };
+
+    let grid_size = (sample_size as f64).sqrt().ceil().max(1.) as usize;
+    let cell_width = bounding_rect.width() / grid_size as f64;
+    let cell_height = bounding_rect.height() / grid_size as f64;
+
+    let mut cells: Vec<Vec<usize>> = (0..grid_size * grid_size)
+        .map(|cell| {
+            let min = Coord {
+                x: bounding_rect.min().x + (cell % grid_size) as f64 * cell_width,
+                y: bounding_rect.min().y + (cell / grid_size) as f64 * cell_height,
+            };
+            let max = Coord {
+                x: min.x + cell_width,
+                y: min.y + cell_height,
+            };
+            graph.locate_in_envelope(&Rect::new(min, max)).collect()
+        })
+        .filter(|nodes: &Vec<usize>| !nodes.is_empty())
+        .collect();
+    cells.shuffle(rng);
+
+    let mut sample = FxHashSet::default();
+    while sample.len() < sample_size && !cells.is_empty() {
+        cells.retain_mut(|nodes| {
+            while !nodes.is_empty() && sample.len() < sample_size {
+                let node = nodes.swap_remove(rng.random_range(0..nodes.len()));
+                // Nodes on cell borders are contained in multiple cells.
+                if sample.insert(node) {
+                    break;
+                }
+            }
+            !nodes.is_empty()
+        });
+    }
+
+    sample.into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use geo::Coord;
+    use graph_rs::{
+        CoordGraph, Graph,
+        graph::{csr::DirectedCsrGraph, rstar::RTreeGraph},
+    };
+    use rand::{SeedableRng, rngs::SmallRng, seq::index::sample};
+    use rustc_hash::FxHashSet;
+
+    use super::sample_spatial;
+
+    /// Number of cells of a 5x5 grid over [0, 10]² which contain a sampled node.
+    fn covered_cells<G: CoordGraph<C = f64>>(graph: &G, nodes: &[usize]) -> usize {
+        nodes
+            .iter()
+            .map(|node| {
+                let coord = graph.node_coord(*node).unwrap();
+                ((coord.x / 2.) as usize).min(4) + 5 * ((coord.y / 2.) as usize).min(4)
+            })
+            .collect::<FxHashSet<_>>()
+            .len()
+    }
+
+    #[test]
+    fn sample_spatial_coverage() {
+        let mut graph: DirectedCsrGraph<f64, Coord<f64>> = DirectedCsrGraph::default();
+        // Dense cluster in the lower left corner.
+        for i in 0..30 {
+            for j in 0..30 {
+                graph.add_node(Coord {
+                    x: i as f64 / 30.,
+                    y: j as f64 / 30.,
+                });
+            }
+        }
+        // Sparse nodes over the whole area.
+        for i in 0..10 {
+            for j in 0..10 {
+                graph.add_node(Coord {
+                    x: i as f64 + 0.5,
+                    y: j as f64 + 0.5,
+                });
+            }
+        }
+        let graph = RTreeGraph::new_from_graph(graph);
+        let mut rng = SmallRng::seed_from_u64(42);
+
+        let spatial = sample_spatial(&graph, 20, &mut rng);
+        let uniform = sample(&mut rng, graph.node_count(), 20).into_vec();
+
+        assert_eq!(spatial.len(), 20);
+        assert_eq!(spatial.iter().collect::<FxHashSet<_>>().len(), 20);
+        assert!(covered_cells(&graph, &spatial) >= 15);
+        assert!(covered_cells(&graph, &spatial) > covered_cells(&graph, &uniform));
+    }
+}
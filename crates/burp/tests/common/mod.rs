@@ -1,29 +1,38 @@
-use std::{fs::File, io::Read, path::PathBuf, str::FromStr};
+use std::fs::File;
 
 use burp::{
-    oracle::{PoiGraph, oracle::Oracle},
-    types::{CoordNode, Poi},
-};
-use graph_rs::{
-    CoordGraph,
-    graph::{csr::DirectedCsrGraph, rstar::RTreeGraph},
+    oracle::{DefaultOracleParams, PoiGraph, RTreeGraphType, oracle::OracleCollection},
+    types::Poi,
 };
 use memmap2::MmapOptions;
 use rmp_serde::Deserializer;
 use serde::Deserialize;
 
-pub fn setup() -> (PoiGraph<Poi>, Oracle<f64, f64>) {
-    let graph_file = File::open("../resources/small_poi.gmp").unwrap();
-    let oracle_file = File::open("../resources/small_poi.omp").unwrap();
+/// Epsilon the oracles of [setup] are built with.
+pub const EPSILON: f64 = 0.25;
 
+/// Loads the bundled fixture graph with its pois.
+pub fn graph() -> PoiGraph<Poi> {
+    let graph_file = File::open("../resources/small_poi.gmp").unwrap();
     let graph_mmap = unsafe { MmapOptions::new().map(&graph_file).unwrap() };
-    let oracle_mmap = unsafe { MmapOptions::new().map(&oracle_file).unwrap() };
-
     let mut graph_deser = Deserializer::from_read_ref(&graph_mmap);
-    let mut oracle_deser = Deserializer::from_read_ref(&oracle_mmap);
 
-    let graph: PoiGraph<Poi> = PoiGraph::deserialize(&mut graph_deser).unwrap();
-    let oracle = Oracle::deserialize(&mut oracle_deser).unwrap();
+    PoiGraph::deserialize(&mut graph_deser).unwrap()
+}
+
+/// Loads the fixture graph and builds the oracles for all of its pois.
+pub fn setup() -> (PoiGraph<Poi>, OracleCollection<RTreeGraphType<Poi>>) {
+    let graph = graph();
+
+    let mut oracles = OracleCollection::default();
+    oracles
+        .build_for_nodes(
+            graph.poi_nodes(),
+            EPSILON,
+            graph.graph(),
+            DefaultOracleParams::default(),
+        )
+        .unwrap();
 
-    (graph, oracle)
+    (graph, oracles)
 }
@@ -1,16 +1,20 @@
 use std::{collections::HashSet, fs::File, iter};
 
-use burp::oracle::oracle::Oracle;
 use geo::Coord;
-use graph_rs::CoordGraph;
+use graph_rs::{
+    CoordGraph, DirectedGraph, Graph,
+    algorithms::dijkstra::{Frontier, search_with},
+    types::Direction,
+};
 use log::info;
 use rand::Rng;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 mod common;
 
 #[test]
 fn oracle() {
-    let (graph, oracle) = common::setup();
+    let (graph, oracles) = common::setup();
 
     let mut rng = rand::rng();
 
@@ -39,11 +43,14 @@ fn oracle() {
                 graph.graph().nearest_node(&s_t_pair.0).unwrap(),
                 graph.graph().nearest_node(&s_t_pair.1).unwrap(),
                 graph.poi_nodes(),
-                0.25,
+                common::EPSILON,
             )
             .unwrap();
 
-        let oracle_result = oracle.get_beer_pois(&s_t_pair.0, &s_t_pair.1);
+        let oracle_result: HashSet<usize> = oracles
+            .iter()
+            .flat_map(|(_, oracle)| oracle.get_beer_pois(&s_t_pair.0, &s_t_pair.1))
+            .collect();
 
         info!("Dijkstra result: {:?}", dijkstra_result);
         info!("Oracle result: {:?}", oracle_result);
@@ -69,3 +76,80 @@ fn oracle_invariant() {
     //     );
     // }
 }
+
+#[test]
+fn dijkstra_frontiers() {
+    let graph = common::graph();
+    let graph = graph.graph().graph();
+    let targets: FxHashSet<usize> = (0..graph.node_count()).collect();
+
+    for start_node in (0..graph.node_count()).step_by(7) {
+        let [queue, heap] = [Frontier::PriorityQueue, Frontier::BinaryHeap].map(|frontier| {
+            search_with(
+                graph,
+                start_node,
+                targets.clone(),
+                Direction::Outgoing,
+                frontier,
+            )
+        });
+
+        for node in 0..graph.node_count() {
+            assert_eq!(
+                queue.cost(node),
+                heap.cost(node),
+                "cost from {start_node} to {node}"
+            );
+        }
+    }
+}
+
+#[test]
+fn total_edge_length() {
+    let graph = common::graph();
+
+    // Every road once, no matter in how many directions it can be used.
+    let mut roads = FxHashMap::default();
+    for (node, _) in graph.graph().nodes_iter() {
+        for target in graph.graph().out_neighbors(node) {
+            let road = (node.min(target.target()), node.max(target.target()));
+            roads.insert(road, *target.value());
+        }
+    }
+    let expected: f64 = roads.values().sum();
+
+    let total = graph.total_edge_length();
+    assert!(total > 0.);
+    assert!(
+        (total - expected).abs() < 1e-6 * expected,
+        "{total} != {expected}"
+    );
+}
+
+/// Node 92 to 53 passes pois 1, 3 and 24 on its shortest path. Every other poi is a detour of
+/// more than 45%, well outside of [common::EPSILON].
+#[test]
+fn known_pair() {
+    let (graph, oracles) = common::setup();
+    let (s, t) = (92, 53);
+    let expected = HashSet::from([1, 3, 24]);
+
+    let dijkstra_result = graph
+        .beer_path_dijkstra_base(s, t, graph.poi_nodes(), common::EPSILON)
+        .unwrap();
+    assert_eq!(
+        dijkstra_result.keys().copied().collect::<HashSet<_>>(),
+        expected
+    );
+
+    let oracle_result: HashSet<usize> = oracles
+        .iter()
+        .flat_map(|(_, oracle)| oracle.get_pois_for_nodes(s, t, graph.graph()))
+        .collect();
+    assert_eq!(oracle_result, expected);
+
+    let (cost, poi, answered) = oracles.beer_path_with_fallback(s, t, &graph).unwrap();
+    assert!(answered);
+    assert!(expected.contains(&poi));
+    assert!((cost - 315.8).abs() < 0.01, "cost {cost}");
+}
@@ -0,0 +1,186 @@
+use std::{cell::RefCell, collections::HashSet, io::Write, rc::Rc};
+
+use burp::{
+    events::Events,
+    input,
+    oracle::DefaultOracleParams,
+    pipeline::{PoiSelection, build_oracle_from_geojson, build_oracle_from_geojson_with_progress},
+};
+use flate2::{Compression, write::GzEncoder};
+use geo::Coord;
+use graph_rs::Graph;
+use rustc_hash::FxHashSet;
+
+const GEOJSON: &str = r#"{
+  "type": "FeatureCollection",
+  "features": [{
+    "type": "Feature",
+    "properties": {},
+    "geometry": {"type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 0.0]]}
+  }, {
+    "type": "Feature",
+    "properties": {},
+    "geometry": {"type": "LineString", "coordinates": [[1.0, 0.0], [1.0, 1.0]]}
+  }]
+}"#;
+
+#[test]
+fn geojson_to_oracle() {
+    let (graph, oracles) = build_oracle_from_geojson(
+        GEOJSON.as_bytes(),
+        |_| true,
+        PoiSelection::Nodes(FxHashSet::from_iter([1])),
+        0.2,
+        DefaultOracleParams::default(),
+    )
+    .unwrap();
+
+    assert_eq!(graph.graph().node_count(), 3);
+    assert_eq!(graph.graph().edge_count(), 4);
+    assert_eq!(graph.poi_nodes(), &FxHashSet::from_iter([1]));
+
+    // The only path from (0, 0) to (1, 1) passes the poi at (1, 0). Blocks do not contain
+    // their boundary, so the nodes are queried through points inside their blocks.
+    let oracle = oracles.get(&1).unwrap();
+    assert_eq!(
+        oracle.get_beer_pois(&Coord { x: 0.1, y: 0.1 }, &Coord { x: 0.9, y: 0.9 }),
+        HashSet::from_iter([1])
+    );
+}
+
+#[test]
+fn gzipped_geojson() {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(GEOJSON.as_bytes()).unwrap();
+    let gzipped = encoder.finish().unwrap();
+    assert!(input::is_gzip(&gzipped));
+    assert!(!input::is_gzip(GEOJSON.as_bytes()));
+
+    let build = |geojson: &[u8]| {
+        build_oracle_from_geojson(
+            geojson,
+            |_| true,
+            PoiSelection::Nodes(FxHashSet::from_iter([1])),
+            0.2,
+            DefaultOracleParams::default(),
+        )
+        .unwrap()
+    };
+    let (graph, oracles) = build(&gzipped);
+    let (expected_graph, expected_oracles) = build(GEOJSON.as_bytes());
+
+    assert!(graph == expected_graph);
+    assert_eq!(
+        oracles.get(&1).unwrap().size(),
+        expected_oracles.get(&1).unwrap().size()
+    );
+}
+
+#[test]
+fn sampled_pois() {
+    let (graph, oracles) = build_oracle_from_geojson(
+        GEOJSON.as_bytes(),
+        |_| true,
+        PoiSelection::Sample(2),
+        0.2,
+        DefaultOracleParams::default(),
+    )
+    .unwrap();
+
+    assert_eq!(graph.poi_nodes().len(), 2);
+    assert_eq!(oracles.iter().count(), 2);
+
+    let err = build_oracle_from_geojson(
+        GEOJSON.as_bytes(),
+        |_| true,
+        PoiSelection::Nodes(FxHashSet::from_iter([3])),
+        0.2,
+        DefaultOracleParams::default(),
+    );
+    assert!(err.is_err());
+}
+
+#[test]
+fn thread_count() {
+    let build = |threads| {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        let (_, oracles) = pool
+            .install(|| {
+                build_oracle_from_geojson(
+                    GEOJSON.as_bytes(),
+                    |_| true,
+                    PoiSelection::Nodes(FxHashSet::from_iter([0, 1, 2])),
+                    0.2,
+                    DefaultOracleParams::default(),
+                )
+            })
+            .unwrap();
+        oracles
+    };
+
+    let single = build(1);
+    let multi = build(4);
+
+    for poi in [0, 1, 2] {
+        let single = single.get(&poi).unwrap();
+        let multi = multi.get(&poi).unwrap();
+        assert_eq!(single.size(), multi.size());
+        assert!(
+            single
+                .block_pairs()
+                .all(|block_pair| multi.block_pairs().any(|other| other == block_pair))
+        );
+    }
+}
+
+#[test]
+fn progress_events() {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    build_oracle_from_geojson_with_progress(
+        GEOJSON.as_bytes(),
+        |_| true,
+        PoiSelection::Sample(3),
+        0.2,
+        DefaultOracleParams::default(),
+        {
+            let events = events.clone();
+            move |event| events.borrow_mut().push(event)
+        },
+    )
+    .unwrap();
+    let events = events.take();
+
+    let stages: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Events::Stage(name) => Some(*name),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        stages,
+        vec!["Reading geojson", "Building graph", "Building oracles"]
+    );
+
+    let features: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Events::ImportProgress { features } => Some(*features),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(features.len(), 2);
+    assert!(features.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let oracles: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Events::OracleProgress { done, total } => Some((*done, *total)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(oracles, vec![(1, 3), (2, 3), (3, 3)]);
+}
@@ -0,0 +1,75 @@
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{
+    DirectedGraph,
+    algorithms::dijkstra::{DijkstraResult, Weight, search},
+    types::Direction,
+};
+
+/// Runs a full outgoing Dijkstra from every node in `sources` across rayon threads, or one
+/// after another without the `rayon` feature.
+///
+/// The searches bypass [Dijkstra](super::dijkstra::Dijkstra), so no cache of
+/// `graph` is read or filled and every task only holds its own frontier.
+pub fn distances_from<G>(graph: &G, sources: &[usize]) -> FxHashMap<usize, DijkstraResult<G::EV>>
+where
+    G: DirectedGraph + Sync,
+    G::EV: Weight + Send,
+{
+    let targets = FxHashSet::from_iter(0..graph.node_count());
+
+    #[cfg(feature = "rayon")]
+    let sources = sources.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let sources = sources.iter();
+
+    sources
+        .map(|&source| {
+            (
+                source,
+                search(graph, source, targets.clone(), Direction::Outgoing),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algorithms::dijkstra::Dijkstra, graph::csr::DirectedCsrGraph, input::edgelist::EdgeList,
+        types::Direction,
+    };
+
+    use super::distances_from;
+
+    #[test]
+    fn matches_sequential_dijkstra() {
+        let graph: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(vec![
+            (0, 1, 2.),
+            (0, 2, 5.),
+            (1, 2, 1.),
+            (2, 3, 3.),
+            (3, 0, 1.),
+            (4, 3, 1.),
+        ]));
+
+        let sources = [0, 2, 4];
+        let distances = distances_from(&graph, &sources);
+
+        assert_eq!(distances.len(), sources.len());
+        for source in sources {
+            let expected = graph.dijkstra_full(source, Direction::Outgoing);
+            let result = &distances[&source];
+
+            assert_eq!(result.0.len(), expected.0.len());
+            for node in expected.0.iter() {
+                assert_eq!(
+                    result.get(node.node_id()).map(|n| *n.cost()),
+                    Some(*node.cost())
+                );
+            }
+        }
+    }
+}
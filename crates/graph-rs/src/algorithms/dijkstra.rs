@@ -1,22 +1,86 @@
 use std::{
     cmp::{Ordering, Reverse},
-    fmt::Debug,
+    collections::BinaryHeap,
+    fmt::{Debug, Display},
     hash::Hash,
+    io::{self, Write},
 };
 
+use geo::{Coord, CoordFloat, HaversineDistance, Point};
 use log::{debug, trace};
-use num_traits::{Num, Zero};
-use ordered_float::{FloatCore, OrderedFloat};
+use num_traits::{FromPrimitive, Num, Zero};
+use ordered_float::OrderedFloat;
 use priority_queue::PriorityQueue;
-use rustc_hash::{FxBuildHasher, FxHashSet};
+use rustc_hash::{FxBuildHasher, FxHashMap, FxHashSet};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    DirectedGraph, Graph,
+    CoordGraph, DirectedGraph, Graph,
     graph::{Path, Target},
     types::Direction,
 };
 
+/// Edge weights Dijkstra can add up and order.
+///
+/// Floats are ordered through [OrderedFloat]. Integers are exact, so their sums are checked
+/// and a path cost which does not fit into the type panics instead of wrapping around.
+pub trait Weight: Num + Copy + Debug {
+    /// Total order of the weights, used as priority.
+    type Key: Ord + Copy + Debug;
+
+    fn key(self) -> Self::Key;
+
+    /// Returns `self + rhs`, or `None` if it does not fit into `Self`.
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_float_weight {
+    ($($t:ty),*) => {$(
+        impl Weight for $t {
+            type Key = OrderedFloat<$t>;
+
+            fn key(self) -> Self::Key {
+                OrderedFloat(self)
+            }
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                Some(self + rhs)
+            }
+        }
+
+        impl Weight for OrderedFloat<$t> {
+            type Key = Self;
+
+            fn key(self) -> Self::Key {
+                self
+            }
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                Some(self + rhs)
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_integer_weight {
+    ($($t:ty),*) => {$(
+        impl Weight for $t {
+            type Key = Self;
+
+            fn key(self) -> Self::Key {
+                self
+            }
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+        }
+    )*};
+}
+
+impl_float_weight!(f32, f64);
+impl_integer_weight!(u32, u64, usize);
+
 pub trait Dijkstra: Graph
 where
     Self::EV: Num,
@@ -31,80 +95,213 @@ where
     fn dijkstra_full(&self, start_node: usize, direction: Direction) -> DijkstraResult<Self::EV>;
 }
 
-default impl<G> Dijkstra for G
+impl<G> Dijkstra for G
 where
     G: DirectedGraph,
-    G::EV: FloatCore,
+    G::EV: Weight,
 {
-    fn dijkstra(
+    default fn dijkstra(
         &self,
         start_node: usize,
-        mut target_set: FxHashSet<usize>,
+        target_set: FxHashSet<usize>,
         direction: Direction,
     ) -> DijkstraResult<Self::EV> {
-        let mut frontier = PriorityQueue::with_hasher(FxBuildHasher);
-        let mut result = FxHashSet::default();
-        let mut visited = FxHashSet::default();
-        frontier.push(
-            ResultNode::new(Target::new(start_node, Self::EV::zero()), None),
-            Reverse(OrderedFloat(Self::EV::zero())),
-        );
+        search(self, start_node, target_set, direction)
+    }
 
-        while !target_set.is_empty() && !frontier.is_empty() {
-            let node = frontier.pop().expect("This is a bug").0;
-            if visited.contains(&node.node_id()) {
-                continue;
+    default fn dijkstra_full(
+        &self,
+        start_node: usize,
+        direction: Direction,
+    ) -> DijkstraResult<Self::EV> {
+        self.dijkstra(
+            start_node,
+            FxHashSet::from_iter(0..self.node_count()),
+            direction,
+        )
+    }
+}
+
+/// Runs Dijkstra from `start_node` until all nodes in `target_set` are settled.
+///
+/// This keeps all state local to the call, so it is safe to run many searches
+/// on the same graph in parallel.
+///
+/// # Panics
+///
+/// Panics if a path cost overflows [Weight::checked_add].
+pub(crate) fn search<G>(
+    graph: &G,
+    start_node: usize,
+    mut target_set: FxHashSet<usize>,
+    direction: Direction,
+) -> DijkstraResult<G::EV>
+where
+    G: DirectedGraph,
+    G::EV: Weight,
+{
+    let mut frontier = PriorityQueue::with_hasher(FxBuildHasher);
+    let mut best: FxHashMap<usize, (G::EV, Option<usize>)> = FxHashMap::default();
+    let mut result = FxHashSet::default();
+    let mut visited = FxHashSet::default();
+    best.insert(start_node, (G::EV::zero(), None));
+    frontier.push(start_node, Reverse(G::EV::zero().key()));
+
+    while !target_set.is_empty()
+        && let Some((node_id, _)) = frontier.pop()
+    {
+        visited.insert(node_id);
+        let (cost, predecessor) = best[&node_id];
+
+        let neighbours: Box<dyn Iterator<Item = &Target<G::EV>>> = match direction {
+            Direction::Outgoing => Box::new(graph.out_neighbors(node_id)),
+            Direction::Incoming => Box::new(graph.in_neighbors(node_id)),
+            Direction::Undirected => Box::new(graph.neighbors(node_id)),
+        };
+
+        neighbours.for_each(|n| {
+            if visited.contains(&n.target()) {
+                return;
+            }
+            let path_cost = cost
+                .checked_add(*n.value())
+                .unwrap_or_else(|| panic!("path cost to node {} overflows", n.target()));
+            if best
+                .get(&n.target())
+                .is_none_or(|(c, _)| path_cost.key() < c.key())
+            {
+                best.insert(n.target(), (path_cost, Some(node_id)));
+                frontier.push_increase(n.target(), Reverse(path_cost.key()));
             }
+        });
 
-            let neighbours: Box<dyn Iterator<Item = &Target<Self::EV>>> = match direction {
-                Direction::Outgoing => Box::new(self.out_neighbors(node.node_id())),
-                Direction::Incoming => Box::new(self.in_neighbors(node.node_id())),
-                Direction::Undirected => Box::new(self.neighbors(node.node_id())),
-            };
-
-            neighbours.for_each(|n| {
-                let path_cost = *node.cost() + *n.value();
-                let new_node =
-                    ResultNode::new(Target::new(n.target(), path_cost), Some(node.node_id()));
-                let path_cost = Reverse(OrderedFloat(path_cost));
-                if let Some(priority) = frontier.get_priority(&new_node) {
-                    if priority < &path_cost {
-                        frontier.change_priority(&new_node, path_cost);
-                    }
-                } else {
-                    frontier.push(new_node, path_cost);
-                }
-                // if !frontier.change_priority_by(&new_node, |p| {
-                //     if p.0 > path_cost {
-                //         p.0 = path_cost
-                //     }
-                // }) {
-                //     frontier.push(new_node, Reverse(path_cost));
-                // }
-            });
-
-            visited.insert(node.node_id());
-
-            target_set.take(&node.node_id()).inspect(|node| {
-                trace!("found path to node {}", node);
-            });
-            result.insert(node);
+        target_set.take(&node_id).inspect(|node| {
+            trace!("found path to node {}", node);
+        });
+        result.insert(ResultNode::new(Target::new(node_id, cost), predecessor));
+    }
+
+    if !target_set.is_empty() {
+        debug!("could not find a path to these nodes: {:?}", target_set);
+    }
+
+    DijkstraResult::new(result)
+}
+
+/// The queue holding the frontier of a Dijkstra search.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Frontier {
+    /// An indexed [PriorityQueue], which lowers the priority of a queued node in place.
+    #[default]
+    PriorityQueue,
+    /// A [BinaryHeap] with lazy deletion, a node is pushed again for every cheaper path found
+    /// and stale entries are skipped when popped. This avoids hashing the node on every
+    /// update, at the cost of a larger heap.
+    BinaryHeap,
+}
+
+/// Like [search], but with the frontier held in `frontier`.
+///
+/// Both frontiers settle nodes at the same costs. If several shortest paths exist, the
+/// predecessors can differ.
+///
+/// # Panics
+///
+/// Panics if a path cost overflows [Weight::checked_add].
+pub fn search_with<G>(
+    graph: &G,
+    start_node: usize,
+    target_set: FxHashSet<usize>,
+    direction: Direction,
+    frontier: Frontier,
+) -> DijkstraResult<G::EV>
+where
+    G: DirectedGraph,
+    G::EV: Weight,
+{
+    match frontier {
+        Frontier::PriorityQueue => search(graph, start_node, target_set, direction),
+        Frontier::BinaryHeap => search_heap(graph, start_node, target_set, direction),
+    }
+}
+
+/// Entry of the [BinaryHeap] frontier, ordered so the cheapest entry is popped first.
+struct HeapEntry<T: Weight>(T::Key, ResultNode<T>);
+
+impl<T: Weight> PartialEq for HeapEntry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Weight> Eq for HeapEntry<T> {}
+
+impl<T: Weight> PartialOrd for HeapEntry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Weight> Ord for HeapEntry<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.cmp(&self.0)
+    }
+}
+
+/// [search] with a [BinaryHeap] with lazy deletion as frontier, see [Frontier::BinaryHeap].
+fn search_heap<G>(
+    graph: &G,
+    start_node: usize,
+    mut target_set: FxHashSet<usize>,
+    direction: Direction,
+) -> DijkstraResult<G::EV>
+where
+    G: DirectedGraph,
+    G::EV: Weight,
+{
+    let mut frontier = BinaryHeap::new();
+    let mut result = FxHashSet::default();
+    let mut visited = FxHashSet::default();
+    frontier.push(HeapEntry(
+        G::EV::zero().key(),
+        ResultNode::new(Target::new(start_node, G::EV::zero()), None),
+    ));
+
+    while !target_set.is_empty()
+        && let Some(HeapEntry(_, node)) = frontier.pop()
+    {
+        if !visited.insert(node.node_id()) {
+            continue;
         }
 
-        if !target_set.is_empty() {
-            debug!("could not find a path to these nodes: {:?}", target_set);
+        let neighbours: Box<dyn Iterator<Item = &Target<G::EV>>> = match direction {
+            Direction::Outgoing => Box::new(graph.out_neighbors(node.node_id())),
+            Direction::Incoming => Box::new(graph.in_neighbors(node.node_id())),
+            Direction::Undirected => Box::new(graph.neighbors(node.node_id())),
+        };
+
+        for n in neighbours.filter(|n| !visited.contains(&n.target())) {
+            let path_cost = node
+                .cost()
+                .checked_add(*n.value())
+                .unwrap_or_else(|| panic!("path cost to node {} overflows", n.target()));
+            frontier.push(HeapEntry(
+                path_cost.key(),
+                ResultNode::new(Target::new(n.target(), path_cost), Some(node.node_id())),
+            ));
         }
 
-        DijkstraResult::new(result)
+        target_set.take(&node.node_id()).inspect(|node| {
+            trace!("found path to node {}", node);
+        });
+        result.insert(node);
     }
 
-    fn dijkstra_full(&self, start_node: usize, direction: Direction) -> DijkstraResult<Self::EV> {
-        self.dijkstra(
-            start_node,
-            FxHashSet::from_iter(0..self.node_count()),
-            direction,
-        )
+    if !target_set.is_empty() {
+        debug!("could not find a path to these nodes: {:?}", target_set);
     }
+
+    DijkstraResult::new(result)
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -145,6 +342,71 @@ impl<T: Num + Clone> DijkstraResult<T> {
             .get(&ResultNode::new(Target::new(node_id, T::zero()), None))
     }
 
+    /// Returns the cost of the shortest path to `node_id` without rebuilding the path.
+    pub fn cost(&self, node_id: usize) -> Option<T> {
+        self.get(node_id).map(|node| node.cost().clone())
+    }
+
+    /// Returns the settled node closest to `target` and the path to it.
+    ///
+    /// Shows how far the search got if the node at `target` was not reached, e.g. because it
+    /// is in another component. Distances are haversine distances, like in
+    /// [CoordGraph::nearest_node].
+    pub fn closest_reached_toward<G>(
+        &self,
+        target: &Coord<G::C>,
+        graph: &G,
+    ) -> Option<(usize, Path<T>)>
+    where
+        G: CoordGraph,
+        G::C: CoordFloat + FromPrimitive,
+    {
+        let target = Point::from(*target);
+
+        // The nearest node of all is also the nearest settled one if it was reached.
+        let nearest = graph
+            .nearest_node(&target.0)
+            .filter(|node| self.get(*node).is_some())
+            .or_else(|| {
+                self.iter()
+                    .filter_map(|node| {
+                        let coord = graph.node_coord(node.node_id())?;
+                        Some((node.node_id(), target.haversine_distance(&coord.into())))
+                    })
+                    .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+                    .map(|(node, _)| node)
+            })?;
+
+        Some((nearest, self.path(nearest)?))
+    }
+
+    /// Iterates over all settled nodes in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = &ResultNode<T>> {
+        self.0.iter()
+    }
+
+    /// Writes a `node_id,cost,predecessor` row for every settled node, sorted by node id.
+    ///
+    /// The predecessor of the start node is left empty.
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()>
+    where
+        T: Display,
+    {
+        let mut nodes: Vec<_> = self.iter().collect();
+        nodes.sort_by_key(|node| node.node_id());
+
+        writeln!(writer, "node_id,cost,predecessor")?;
+        for node in nodes {
+            write!(writer, "{},{},", node.node_id(), node.cost())?;
+            if let Some(prev_node_id) = node.prev_node_id() {
+                write!(writer, "{prev_node_id}")?;
+            }
+            writeln!(writer)?;
+        }
+
+        writer.flush()
+    }
+
     pub fn convert_to_path(mut self, node_id: usize) -> Vec<ResultNode<T>> {
         let mut node_id = node_id;
         let mut path = vec![];
@@ -230,7 +492,14 @@ mod test {
 
     use ordered_float::OrderedFloat;
 
-    use crate::{algorithms::dijkstra::ResultNode, graph::Target};
+    use rustc_hash::FxHashSet;
+
+    use crate::{
+        algorithms::dijkstra::{Dijkstra, DijkstraResult, Frontier, ResultNode, search_with},
+        graph::{Target, csr::DirectedCsrGraph},
+        input::edgelist::EdgeList,
+        types::Direction,
+    };
 
     #[test]
     fn result_node_hash() {
@@ -241,4 +510,172 @@ mod test {
         ResultNode::new(Target::new(34, OrderedFloat(4.9)), Some(45)).hash(&mut h_2);
         assert_eq!(h_1.finish(), h_2.finish());
     }
+
+    #[test]
+    fn integer_weights() {
+        let graph: DirectedCsrGraph<u32, ()> = DirectedCsrGraph::from(EdgeList::new(vec![
+            (0, 1, 4),
+            (0, 2, 1),
+            (2, 1, 2),
+            (1, 3, 1),
+            (2, 3, 5),
+        ]));
+
+        let result = graph.dijkstra_full(0, Direction::Outgoing);
+
+        let costs: Vec<_> = (0..4).map(|node| result.cost(node)).collect();
+        assert_eq!(costs, vec![Some(0), Some(3), Some(1), Some(4)]);
+        let path: Vec<_> = result
+            .path(3)
+            .unwrap()
+            .path
+            .iter()
+            .map(|target| target.target())
+            .collect();
+        assert_eq!(path, vec![0, 2, 1, 3]);
+    }
+
+    #[test]
+    fn frontiers() {
+        // Node 5 can not be reached, and there are two shortest paths from 0 to 3.
+        let graph: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(vec![
+            (0, 1, 4.),
+            (0, 2, 1.),
+            (2, 1, 2.),
+            (1, 3, 1.),
+            (2, 3, 3.),
+            (3, 4, 2.),
+            (4, 0, 1.),
+            (5, 4, 1.),
+        ]));
+        let targets = FxHashSet::from_iter(0..6);
+
+        for direction in [
+            Direction::Outgoing,
+            Direction::Incoming,
+            Direction::Undirected,
+        ] {
+            for start_node in 0..6 {
+                let [queue, heap] =
+                    [Frontier::PriorityQueue, Frontier::BinaryHeap].map(|frontier| {
+                        search_with(&graph, start_node, targets.clone(), direction, frontier)
+                    });
+
+                for node in 0..6 {
+                    assert_eq!(
+                        queue.cost(node),
+                        heap.cost(node),
+                        "{direction:?} {start_node}"
+                    );
+                    assert_eq!(
+                        heap.path(node).map(|path| path.cost()),
+                        heap.cost(node),
+                        "{direction:?} {start_node}"
+                    );
+                }
+            }
+        }
+
+        let heap = search_with(
+            &graph,
+            0,
+            FxHashSet::from_iter([2]),
+            Direction::Outgoing,
+            Frontier::BinaryHeap,
+        );
+        assert_eq!(heap.cost(2), Some(1.));
+        assert_eq!(heap.cost(4), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflows")]
+    fn integer_weights_overflow() {
+        let graph: DirectedCsrGraph<u32, ()> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, u32::MAX), (1, 2, 1)]));
+
+        graph.dijkstra_full(0, Direction::Outgoing);
+    }
+
+    #[test]
+    fn cost() {
+        let result = DijkstraResult::new(FxHashSet::from_iter([
+            ResultNode::new(Target::new(0, 0.), None),
+            ResultNode::new(Target::new(1, 2.), Some(0)),
+            ResultNode::new(Target::new(2, 5.), Some(1)),
+        ]));
+
+        assert_eq!(result.cost(2), Some(5.));
+        assert_eq!(result.cost(2), result.path(2).map(|path| path.cost()));
+        assert_eq!(result.cost(3), None);
+    }
+
+    #[cfg(feature = "rstar")]
+    #[test]
+    fn closest_reached_toward() {
+        use geo::Coord;
+
+        use crate::{
+            CoordGraph,
+            algorithms::dijkstra::Dijkstra,
+            graph::{csr::DirectedCsrGraph, rstar::RTreeGraph},
+            types::Direction,
+        };
+
+        // Nodes 0 to 2 and nodes 3 and 4 are separate components.
+        let coords = (0..5).map(|x| Coord { x: x as f64, y: 0. }).collect();
+        let edges = vec![(0, 1, 1.), (1, 2, 1.), (3, 4, 1.)];
+        let graph: RTreeGraph<DirectedCsrGraph<f64, Coord<f64>>, f64> =
+            RTreeGraph::new_from_graph(DirectedCsrGraph::from_edges_and_values(edges, coords));
+
+        let result = graph.dijkstra(0, FxHashSet::from_iter([4]), Direction::Outgoing);
+        assert!(result.path(4).is_none());
+
+        let target = graph.node_coord(4).unwrap();
+        let (node, path) = result.closest_reached_toward(&target, &graph).unwrap();
+        assert_eq!(node, 2);
+        assert_eq!(
+            path.path.iter().map(|t| t.target()).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+
+        // If the target was reached, it is its own closest node.
+        let (node, _) = result
+            .closest_reached_toward(&Coord { x: 1.1, y: 0. }, &graph)
+            .unwrap();
+        assert_eq!(node, 1);
+
+        // At 80° north a degree of longitude is far shorter than one of latitude, so node 1
+        // is closer to node 3 than node 2, although not in degrees.
+        let coords = vec![
+            Coord { x: 0., y: 79. },
+            Coord { x: 1., y: 80. },
+            Coord { x: 0., y: 80.5 },
+            Coord { x: 0., y: 80. },
+        ];
+        let edges = vec![(0, 1, 1.), (0, 2, 1.)];
+        let graph: RTreeGraph<DirectedCsrGraph<f64, Coord<f64>>, f64> =
+            RTreeGraph::new_from_graph(DirectedCsrGraph::from_edges_and_values(edges, coords));
+
+        let result = graph.dijkstra(0, FxHashSet::from_iter([3]), Direction::Outgoing);
+        let target = graph.node_coord(3).unwrap();
+        let (node, _) = result.closest_reached_toward(&target, &graph).unwrap();
+        assert_eq!(node, 1);
+    }
+
+    #[test]
+    fn write_csv() {
+        let result = DijkstraResult::new(FxHashSet::from_iter([
+            ResultNode::new(Target::new(2, 5.5), Some(1)),
+            ResultNode::new(Target::new(0, 0.), None),
+            ResultNode::new(Target::new(1, 2.), Some(0)),
+        ]));
+
+        let mut csv = Vec::new();
+        result.write_csv(&mut csv).unwrap();
+
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "node_id,cost,predecessor\n0,0,\n1,2,0\n2,5.5,1\n"
+        );
+    }
 }
@@ -1,2 +1,3 @@
+pub mod apsp;
 pub mod dijkstra;
 pub mod trajan_scc;
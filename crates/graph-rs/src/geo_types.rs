@@ -29,3 +29,17 @@ impl<C: CoordNum> Coordinate<C> for Coord<C> {
         Coord::zero()
     }
 }
+
+impl<C: CoordNum> Coordinate<C> for (C, C) {
+    fn x_y(&self) -> (C, C) {
+        *self
+    }
+
+    fn as_coord(&self) -> Coord<C> {
+        Coord::from(*self)
+    }
+
+    fn zero() -> Self {
+        (C::zero(), C::zero())
+    }
+}
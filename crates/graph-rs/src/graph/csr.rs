@@ -1,28 +1,35 @@
 use std::{
     cell::RefCell,
     cmp::Reverse,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
     fmt::Debug,
     rc::Rc,
     vec,
 };
 
-use geo::Coord;
+use cached::{Cached, SizedCache};
 use log::{debug, info, trace};
+use num_traits::Float;
 use ordered_float::{FloatCore, OrderedFloat};
 use parking_lot::Mutex;
 use priority_queue::PriorityQueue;
+#[cfg(feature = "rayon")]
 use rayon::iter::{
-    IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator,
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    IntoParallelRefMutIterator, ParallelIterator,
 };
 use rustc_hash::{FxBuildHasher, FxHashMap, FxHashSet};
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
-use crate::{DirectedGraph, Graph, GraphError, graph::Target, input::edgelist::EdgeList};
 use crate::{
-    algorithms::dijkstra::{Dijkstra, DijkstraResult, ResultNode},
-    types::Direction,
+    Coordinate, DirectedGraph, Graph, GraphError,
+    graph::{Path, Target},
+    input::edgelist::EdgeList,
+};
+use crate::{
+    algorithms::dijkstra::{Dijkstra, DijkstraResult, Frontier, ResultNode, Weight},
+    types::{Direction, EdgeClass},
 };
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -40,14 +47,87 @@ impl<EV> Csr<EV> {
         Self { offsets, targets }
     }
 
+    /// Create an empty `CSR` with space for `node_capacity` nodes and `edge_capacity` edges.
+    pub fn with_capacity(node_capacity: usize, edge_capacity: usize) -> Csr<EV> {
+        let mut offsets = Vec::with_capacity(node_capacity + 1);
+        offsets.push(0);
+
+        Self {
+            offsets,
+            targets: Vec::with_capacity(edge_capacity),
+        }
+    }
+
+    /// Reserves space for at least `additional_nodes` more nodes and `additional_edges` more
+    /// edges.
+    pub fn reserve(&mut self, additional_nodes: usize, additional_edges: usize) {
+        self.offsets.reserve(additional_nodes);
+        self.targets.reserve(additional_edges);
+    }
+
+    /// Frees the capacity beyond the current nodes and edges.
+    pub fn shrink_to_fit(&mut self) {
+        self.offsets.shrink_to_fit();
+        self.targets.shrink_to_fit();
+    }
+
+    /// Removes all nodes and edges while keeping the allocated memory.
+    pub fn clear(&mut self) {
+        self.offsets.clear();
+        self.offsets.push(0);
+        self.targets.clear();
+    }
+
     pub fn node_count(&self) -> usize {
         self.offsets.len() - 1
     }
 
+    /// Checks that the offsets start at `0`, are monotonic and end at the number of targets,
+    /// and that all targets are nodes of the `CSR`.
+    pub fn validate(&self) -> Result<(), GraphError> {
+        if self.offsets.first() != Some(&0) {
+            return Err(GraphError::InvalidOffsets(0));
+        }
+        if let Some(index) = self.offsets.windows(2).position(|w| w[0] > w[1]) {
+            return Err(GraphError::InvalidOffsets(index + 1));
+        }
+        if self.offsets.last() != Some(&self.targets.len()) {
+            return Err(GraphError::InvalidOffsets(self.offsets.len() - 1));
+        }
+
+        let node_count = self.node_count();
+        for node in 0..node_count {
+            if let Some(target) = self.targets(node).iter().find(|t| t.target() >= node_count) {
+                return Err(GraphError::TargetOutOfRange {
+                    node,
+                    target: target.target(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn edge_count(&self) -> usize {
         self.targets.len()
     }
 
+    /// Compares the rows of both `CSR`s as sets of `(target, value)`, ignoring their order.
+    fn rows_eq_unordered(&self, other: &Self) -> bool
+    where
+        EV: PartialEq,
+    {
+        self.node_count() == other.node_count()
+            && (0..self.node_count()).all(|node| {
+                let (lhs, rhs) = (self.targets(node), other.targets(node));
+                lhs.len() == rhs.len()
+                    && lhs.iter().all(|a| {
+                        rhs.iter()
+                            .any(|b| a.target() == b.target() && a.value() == b.value())
+                    })
+            })
+    }
+
     // TODO: Use Result<usize> as return value.
     pub fn degree(&self, i: usize) -> usize {
         let from = self.offsets[i];
@@ -64,6 +144,52 @@ impl<EV> Csr<EV> {
         &self.targets[from..to]
     }
 
+    /// Returns the first target of node `a` pointing to `b`.
+    fn target_mut(&mut self, a: usize, b: usize) -> Option<&mut Target<EV>> {
+        let (&start, &end) = (self.offsets.get(a)?, self.offsets.get(a + 1)?);
+        self.targets[start..end]
+            .iter_mut()
+            .find(|target| target.target() == b)
+    }
+
+    /// Replaces the value of every target by `f(node, target)`, in parallel over the rows if
+    /// the `rayon` feature is enabled.
+    fn map_values<F>(&mut self, f: F)
+    where
+        F: Fn(usize, &Target<EV>) -> EV + Sync,
+        EV: Send + Sync,
+    {
+        let mut rows = Vec::with_capacity(self.node_count());
+        let mut rest = self.targets.as_mut_slice();
+        for offsets in self.offsets.windows(2) {
+            let (row, tail) = rest.split_at_mut(offsets[1] - offsets[0]);
+            rows.push(row);
+            rest = tail;
+        }
+
+        #[cfg(feature = "rayon")]
+        let rows = rows.into_par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let rows = rows.into_iter();
+
+        rows.enumerate().for_each(|(node, row)| {
+            for target in row {
+                target.value = f(node, target);
+            }
+        });
+    }
+
+    /// Applies `f` to every offset from index `from` on.
+    fn update_offsets<F>(&mut self, from: usize, f: F)
+    where
+        F: Fn(&mut usize) + Send + Sync,
+    {
+        #[cfg(feature = "rayon")]
+        self.offsets[from..].par_iter_mut().for_each(f);
+        #[cfg(not(feature = "rayon"))]
+        self.offsets[from..].iter_mut().for_each(f);
+    }
+
     pub fn add_node(&mut self) -> usize {
         self.offsets.push(*self.offsets.last().unwrap_or(&0));
         self.offsets.len() - 2
@@ -79,11 +205,7 @@ impl<EV> Csr<EV> {
         }
         self.targets.insert(self.offsets[a], Target::new(b, weight));
 
-        self.offsets.par_iter_mut().enumerate().for_each(|(i, x)| {
-            if i > a {
-                *x += 1;
-            }
-        });
+        self.update_offsets(a + 1, |x| *x += 1);
 
         true
     }
@@ -112,26 +234,19 @@ impl<EV> Csr<EV> {
         let offset_upper = self.offsets[node + 1];
         let n_out_edges = offset_upper - offset_lower;
 
-        if targets_len <= offset_upper {
+        if targets_len < offset_upper {
             return false;
         }
 
         self.targets.drain(offset_lower..offset_upper);
 
-        let targets_len = self.targets.len();
-
-        self.targets[offset_lower..targets_len]
-            .iter_mut()
-            .for_each(|target| {
-                if target.target > node {
-                    target.target -= 1;
-                }
-            });
+        self.targets.iter_mut().for_each(|target| {
+            if target.target > node {
+                target.target -= 1;
+            }
+        });
         self.offsets.remove(node);
-        let offset_len = self.offsets.len();
-        self.offsets[node..offset_len]
-            .par_iter_mut()
-            .for_each(|x| *x -= n_out_edges);
+        self.update_offsets(node, |x| *x -= n_out_edges);
 
         true
     }
@@ -151,10 +266,7 @@ impl<EV> Csr<EV> {
                 i += 1;
             }?;
 
-            let offset_len = self.offsets.len();
-            self.offsets[edge.0 + 1..offset_len]
-                .par_iter_mut()
-                .for_each(|x| *x -= 1);
+            self.update_offsets(edge.0 + 1, |x| *x -= 1);
             Some(self.targets.remove(target_index).value)
         } else {
             None
@@ -171,13 +283,78 @@ impl<EV> Default for Csr<EV> {
     }
 }
 
+/// Number of start nodes kept in the Dijkstra cache by default.
+pub const DEFAULT_DIJKSTRA_CACHE_CAPACITY: usize = 4096;
+
+/// Upper bound for the Dijkstra cache capacity.
+///
+/// The cache preallocates its full capacity, so larger values are clamped to this.
+pub const MAX_DIJKSTRA_CACHE_CAPACITY: usize = 1 << 16;
+
+type DijkstraCache<EV> = SizedCache<(usize, Direction), DijkstraCacheEntry<EV>>;
+
+/// [DijkstraCache] which only allocates its capacity on first use.
+///
+/// Graphs are often loaded just to be queried a few times, so neither
+/// constructing nor deserializing a graph allocates the cache.
+#[derive(Debug)]
+struct LazyDijkstraCache<EV> {
+    capacity: usize,
+    cache: Option<DijkstraCache<EV>>,
+}
+
+impl<EV> LazyDijkstraCache<EV> {
+    /// The capacity is clamped to `1..=MAX_DIJKSTRA_CACHE_CAPACITY`.
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.clamp(1, MAX_DIJKSTRA_CACHE_CAPACITY),
+            cache: None,
+        }
+    }
+
+    fn get_or_init(&mut self) -> &mut DijkstraCache<EV> {
+        let capacity = self.capacity;
+        self.cache
+            .get_or_insert_with(|| SizedCache::with_size(capacity))
+    }
+
+    fn cache_clear(&mut self) {
+        if let Some(cache) = self.cache.as_mut() {
+            cache.cache_clear();
+        }
+    }
+
+    /// Drops the cache with its preallocated capacity until it is used again.
+    fn release(&mut self) {
+        self.cache = None;
+    }
+}
+
+impl<EV> Default for LazyDijkstraCache<EV> {
+    fn default() -> Self {
+        Self::new(DEFAULT_DIJKSTRA_CACHE_CAPACITY)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct DirectedCsrGraph<EV, NV> {
     pub node_values: Vec<NV>,
     pub csr_out: Csr<EV>,
     pub csr_inc: Csr<EV>,
+    /// Feature each edge was imported from as `(source, target, feature)`, sorted by edge. See
+    /// [DirectedCsrGraph::edge_provenance].
+    #[serde(default)]
+    edge_provenance: Option<Vec<(usize, usize, i64)>>,
+    /// Class of each edge as `(source, target, class)`, sorted by edge. See
+    /// [DirectedCsrGraph::edge_class].
+    #[serde(default)]
+    edge_classes: Option<Vec<(usize, usize, EdgeClass)>>,
+    /// Least recently used Dijkstra results keyed by start node and direction.
+    #[serde(skip)]
+    dijkstra_cache: Mutex<LazyDijkstraCache<EV>>,
+    /// Frontier used by the searches behind [Dijkstra], see [DirectedCsrGraph::set_frontier].
     #[serde(skip)]
-    dijkstra_cache: Mutex<FxHashMap<(usize, Direction), DijkstraResult<EV>>>,
+    frontier: Frontier,
 }
 
 impl<EV, NV> DirectedCsrGraph<EV, NV>
@@ -188,21 +365,38 @@ where
         node_values: Vec<NV>,
         csr_out: Csr<EV>,
         csr_inc: Csr<EV>,
+    ) -> DirectedCsrGraph<EV, NV> {
+        Self::with_cache_capacity(
+            node_values,
+            csr_out,
+            csr_inc,
+            DEFAULT_DIJKSTRA_CACHE_CAPACITY,
+        )
+    }
+
+    /// Like [`DirectedCsrGraph::new`] but keeps at most `dijkstra_cache_capacity`
+    /// start nodes in the Dijkstra cache, evicting the least recently used one.
+    ///
+    /// The capacity is clamped to `1..=MAX_DIJKSTRA_CACHE_CAPACITY`.
+    pub fn with_cache_capacity(
+        node_values: Vec<NV>,
+        csr_out: Csr<EV>,
+        csr_inc: Csr<EV>,
+        dijkstra_cache_capacity: usize,
     ) -> DirectedCsrGraph<EV, NV> {
         assert_eq!(
             csr_out.node_count(),
             csr_inc.node_count(),
             "csr_out and csr_in have different node counts"
         );
-        let node_count = csr_out.node_count();
         let g = Self {
             node_values,
             csr_out,
             csr_inc,
-            dijkstra_cache: Mutex::new(FxHashMap::with_capacity_and_hasher(
-                node_count,
-                FxBuildHasher,
-            )),
+            edge_provenance: None,
+            edge_classes: None,
+            dijkstra_cache: Mutex::new(LazyDijkstraCache::new(dijkstra_cache_capacity)),
+            frontier: Frontier::default(),
         };
 
         info!(
@@ -214,6 +408,83 @@ where
         g
     }
 
+    /// Creates an empty graph with space for `node_capacity` nodes and `edge_capacity` edges.
+    pub fn with_capacity(node_capacity: usize, edge_capacity: usize) -> DirectedCsrGraph<EV, NV> {
+        Self::new(
+            Vec::with_capacity(node_capacity),
+            Csr::with_capacity(node_capacity, edge_capacity),
+            Csr::with_capacity(node_capacity, edge_capacity),
+        )
+    }
+
+    /// Reserves space for at least `additional_nodes` more nodes and `additional_edges` more
+    /// edges in the node values and both CSRs.
+    ///
+    /// Avoids repeated reallocation when adding many nodes or edges one by one.
+    pub fn reserve(&mut self, additional_nodes: usize, additional_edges: usize) {
+        self.node_values.reserve(additional_nodes);
+        self.csr_out.reserve(additional_nodes, additional_edges);
+        self.csr_inc.reserve(additional_nodes, additional_edges);
+    }
+
+    /// Frees the capacity beyond the current nodes and edges and drops the Dijkstra cache.
+    ///
+    /// Graphs built by adding nodes and edges one by one or by [DirectedCsrGraph::filter] can
+    /// hold much more memory than they need, e.g. before they are serialized.
+    pub fn shrink_to_fit(&mut self) {
+        self.node_values.shrink_to_fit();
+        self.csr_out.shrink_to_fit();
+        self.csr_inc.shrink_to_fit();
+        if let Some(provenance) = self.edge_provenance.as_mut() {
+            provenance.shrink_to_fit();
+        }
+        if let Some(classes) = self.edge_classes.as_mut() {
+            classes.shrink_to_fit();
+        }
+        self.dijkstra_cache.get_mut().release();
+    }
+
+    /// Frontier used by [Dijkstra::dijkstra] and [Dijkstra::dijkstra_full].
+    pub fn frontier(&self) -> Frontier {
+        self.frontier
+    }
+
+    /// Sets the frontier used by [Dijkstra::dijkstra] and [Dijkstra::dijkstra_full].
+    ///
+    /// Both frontiers settle nodes at the same costs, so the Dijkstra cache is kept.
+    pub fn set_frontier(&mut self, frontier: Frontier) {
+        self.frontier = frontier;
+    }
+
+    /// Removes all nodes and edges and empties the Dijkstra cache while keeping
+    /// the allocated memory.
+    pub fn clear(&mut self) {
+        self.node_values.clear();
+        self.csr_out.clear();
+        self.csr_inc.clear();
+        self.edge_provenance = None;
+        self.edge_classes = None;
+        self.dijkstra_cache.lock().cache_clear();
+    }
+
+    /// Checks that both CSRs are well formed and have one node per node value.
+    ///
+    /// Deserialized graphs are not checked, so a truncated or malformed file
+    /// only fails later when traversing the graph.
+    pub fn validate(&self) -> Result<(), GraphError> {
+        for csr in [&self.csr_out, &self.csr_inc] {
+            if csr.offsets.len() != self.node_values.len() + 1 {
+                return Err(GraphError::NodeCountMismatch {
+                    expected: self.node_values.len(),
+                    found: csr.offsets.len().saturating_sub(1),
+                });
+            }
+            csr.validate()?;
+        }
+
+        Ok(())
+    }
+
     pub fn filter<F>(self, predicate: F) -> DirectedCsrGraph<EV, NV>
     where
         F: Fn(&(usize, &NV)) -> bool + Clone,
@@ -231,6 +502,9 @@ where
 
                 index += 1;
             });
+        // Kept nodes keep their order, so the provenance and classes stay sorted.
+        new_graph.edge_provenance = map_edge_attributes(&self.edge_provenance, &node_map);
+        new_graph.edge_classes = map_edge_attributes(&self.edge_classes, &node_map);
 
         node_map.iter().for_each(|node| {
             let neighbors = self.out_neighbors(*node.0).filter(|target| {
@@ -253,6 +527,253 @@ where
 
         new_graph
     }
+
+    /// Compares node values and adjacency regardless of the order of the edges within a row.
+    ///
+    /// Rows are ordered by insertion, so graphs built by adding the same edges in a different
+    /// order are structurally equal but not `==`.
+    pub fn structurally_eq(&self, other: &Self) -> bool
+    where
+        EV: PartialEq,
+        NV: PartialEq,
+    {
+        self.node_values == other.node_values
+            && self.csr_out.rows_eq_unordered(&other.csr_out)
+            && self.csr_inc.rows_eq_unordered(&other.csr_inc)
+    }
+
+    /// Maps each degree to the number of nodes with that degree.
+    ///
+    /// Like [Graph::degree] incoming and outgoing edges are counted, so a node in the middle
+    /// of a two-way road has degree 4.
+    pub fn degree_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for node in 0..self.node_values.len() {
+            let degree = self.csr_out.degree(node) + self.csr_inc.degree(node);
+            *histogram.entry(degree).or_default() += 1;
+        }
+
+        histogram
+    }
+
+    /// Returns the id of the feature the edge from `a` to `b` was imported from.
+    ///
+    /// Only recorded if the importer was asked for it, otherwise `None` for every edge.
+    pub fn edge_provenance(&self, a: usize, b: usize) -> Option<i64> {
+        edge_attribute(&self.edge_provenance, a, b)
+    }
+
+    /// Records the feature each edge was imported from, see [DirectedCsrGraph::edge_provenance].
+    ///
+    /// The first feature given for an edge is kept. Entries for edges which are not in the
+    /// graph are dropped.
+    pub fn set_edge_provenance(&mut self, provenance: Vec<(usize, usize, i64)>) {
+        self.edge_provenance = Some(sorted_edge_attributes(&self.csr_out, provenance));
+    }
+
+    /// Returns the class of the edge from `a` to `b`.
+    ///
+    /// `None` if the graph was built without classes or there is no such edge.
+    pub fn edge_class(&self, a: usize, b: usize) -> Option<EdgeClass> {
+        edge_attribute(&self.edge_classes, a, b)
+    }
+
+    /// Records the class of each edge, see [DirectedCsrGraph::edge_class].
+    ///
+    /// The first class given for an edge is kept. Entries for edges which are not in the graph
+    /// are dropped.
+    pub fn set_edge_classes(&mut self, classes: Vec<(usize, usize, EdgeClass)>) {
+        self.edge_classes = Some(sorted_edge_attributes(&self.csr_out, classes));
+    }
+
+    /// Gives the edge from `a` to `b` the provenance and class of the edge `from`, e.g. for the
+    /// pieces of a split edge.
+    ///
+    /// `from` must still be in the graph, as the attributes of removed edges are dropped.
+    /// Nothing is recorded if there is no edge from `a` to `b`.
+    pub fn copy_edge_attributes(&mut self, (a, b): (usize, usize), from: (usize, usize)) {
+        if a >= self.csr_out.node_count()
+            || !self.csr_out.targets(a).iter().any(|t| t.target() == b)
+        {
+            return;
+        }
+
+        let provenance = self.edge_provenance(from.0, from.1);
+        let class = self.edge_class(from.0, from.1);
+        set_edge_attribute(&mut self.edge_provenance, a, b, provenance);
+        set_edge_attribute(&mut self.edge_classes, a, b, class);
+    }
+
+    /// Returns a copy of the graph with only the edges whose class is accepted by `keep`, e.g.
+    /// to route a query over the roads of a multimodal graph.
+    ///
+    /// Edges without a class are dropped, unless the graph has no classes at all. Nodes keep
+    /// their ids, so a node only connected by removed edges stays in the graph without any
+    /// edges.
+    pub fn filter_edge_classes<F>(&self, keep: F) -> DirectedCsrGraph<EV, NV>
+    where
+        F: Fn(EdgeClass) -> bool,
+        NV: Clone,
+    {
+        let classified = self.edge_classes.is_some();
+        let keep_edge =
+            |a: usize, b: usize| !classified || self.edge_class(a, b).is_some_and(&keep);
+        let filter_csr = |csr: &Csr<EV>, keep_edge: &dyn Fn(usize, usize) -> bool| {
+            let mut offsets = vec![0];
+            let mut targets = Vec::new();
+            for node in 0..csr.node_count() {
+                targets.extend(
+                    csr.targets(node)
+                        .iter()
+                        .filter(|target| keep_edge(node, target.target()))
+                        .cloned(),
+                );
+                offsets.push(targets.len());
+            }
+            Csr::new(offsets, targets)
+        };
+
+        let mut graph = Self::new(
+            self.node_values.clone(),
+            filter_csr(&self.csr_out, &keep_edge),
+            filter_csr(&self.csr_inc, &|b, a| keep_edge(a, b)),
+        );
+        graph.edge_provenance = self.edge_provenance.as_ref().map(|provenance| {
+            provenance
+                .iter()
+                .filter(|&&(a, b, _)| keep_edge(a, b))
+                .copied()
+                .collect()
+        });
+        graph.edge_classes = self.edge_classes.as_ref().map(|classes| {
+            classes
+                .iter()
+                .filter(|&&(_, _, class)| keep(class))
+                .copied()
+                .collect()
+        });
+
+        graph
+    }
+
+    /// Returns the minimum, maximum, mean and standard deviation of the edge weights, or `None`
+    /// if the graph has no edges.
+    ///
+    /// Each directed edge counts once. The mean and deviation are computed in one pass with
+    /// Welford's algorithm.
+    pub fn edge_weight_stats(&self) -> Option<(EV, EV, EV, EV)>
+    where
+        EV: Float,
+    {
+        let mut weights = self.csr_out.targets.iter().map(|target| *target.value());
+        let first = weights.next()?;
+
+        let (mut min, mut max, mut mean) = (first, first, first);
+        let (mut squares, mut count) = (EV::zero(), EV::one());
+        for weight in weights {
+            count = count + EV::one();
+            min = min.min(weight);
+            max = max.max(weight);
+            let delta = weight - mean;
+            mean = mean + delta / count;
+            squares = squares + delta * (weight - mean);
+        }
+
+        Some((min, max, mean, (squares / count).sqrt()))
+    }
+
+    /// Returns the edges as `(source, target, weight)` whose weight exceeds the mean by more
+    /// than `sigma` standard deviations, see [DirectedCsrGraph::edge_weight_stats].
+    ///
+    /// Meant to spot broken imports, like an edge spanning the globe because of a typo in a
+    /// coordinate.
+    pub fn find_outlier_edges(&self, sigma: EV) -> Vec<(usize, usize, EV)>
+    where
+        EV: Float,
+    {
+        let Some((_, _, mean, stddev)) = self.edge_weight_stats() else {
+            return vec![];
+        };
+        let threshold = mean + sigma * stddev;
+
+        (0..self.csr_out.node_count())
+            .flat_map(|node| {
+                self.csr_out
+                    .targets(node)
+                    .iter()
+                    .filter(|target| *target.value() > threshold)
+                    .map(move |target| (node, target.target(), *target.value()))
+            })
+            .collect()
+    }
+
+    /// Consumes the graph and returns its edges as `(source, target, weight)`, discarding
+    /// the node values.
+    ///
+    /// Isolated nodes after the largest node id with an edge are lost, as [EdgeList] has no
+    /// notion of node count.
+    pub fn into_edge_list(self) -> EdgeList<EV>
+    where
+        EV: Copy,
+    {
+        let edges = (0..self.csr_out.node_count())
+            .flat_map(|node| {
+                self.csr_out
+                    .targets(node)
+                    .iter()
+                    .map(move |target| (node, target.target(), *target.value()))
+            })
+            .collect();
+
+        EdgeList::new(edges)
+    }
+
+    /// Replaces every edge weight by `f(from, to, &weight)`, in parallel over the nodes if the
+    /// `rayon` feature is enabled.
+    ///
+    /// Both CSRs are updated, so `f` has to return the same weight whenever it is called for
+    /// the same edge. The Dijkstra cache is cleared, as all cached costs are outdated.
+    pub fn recompute_edge_weights<F>(&mut self, f: F)
+    where
+        F: Fn(usize, usize, &EV) -> EV + Sync,
+        EV: Send + Sync,
+    {
+        self.csr_out
+            .map_values(|from, target| f(from, target.target(), target.value()));
+        self.csr_inc
+            .map_values(|to, target| f(target.target(), to, target.value()));
+        self.dijkstra_cache.get_mut().cache_clear();
+    }
+
+    /// Sets the weight of the edge from `a` to `b` and returns the old weight, or `None` if
+    /// there is no such edge.
+    ///
+    /// Both CSRs are updated in place, so unlike removing and adding the edge the order of the
+    /// edges is kept. The Dijkstra cache is cleared.
+    pub fn set_edge_weight(&mut self, a: usize, b: usize, weight: EV) -> Option<EV> {
+        let target = self.csr_out.target_mut(a, b)?;
+        let old_weight = target.value().clone();
+        target.set_value(weight.clone());
+        self.csr_inc
+            .target_mut(b, a)
+            .expect("csr_out and csr_inc are inconsistent")
+            .set_value(weight);
+        self.dijkstra_cache.get_mut().cache_clear();
+
+        Some(old_weight)
+    }
+
+    /// Returns an iterator over all nodes with mutable references to their values, ordered by
+    /// id.
+    ///
+    /// Edge weights can not change through node values, so the Dijkstra cache is kept. Indices
+    /// built from the values, like the r-tree of an
+    /// [RTreeGraph](crate::graph::rstar::RTreeGraph), have to be rebuilt.
+    pub fn node_values_iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut NV)> {
+        self.node_values.iter_mut().enumerate()
+    }
+
+    #[cfg(feature = "rayon")]
     pub fn par_out_neighbors(&self, node_id: usize) -> rayon::slice::Iter<'_, Target<EV>>
     where
         EV: Send + Sync,
@@ -376,25 +897,40 @@ impl<EV: Clone + Default, NV> Graph for DirectedCsrGraph<EV, NV> {
         let ret_in = self.csr_inc.add_edge(b, a, weight);
         assert_eq!(ret_out, ret_in, "csr_out and csr_in are inconsitent");
 
+        if ret_out {
+            self.dijkstra_cache.get_mut().cache_clear();
+        }
         ret_out
     }
 
     fn remove_node(&mut self, node: usize) -> Option<NV> {
         self.csr_inc.remove_node(node);
         self.csr_out.remove_node(node);
+        self.dijkstra_cache.get_mut().cache_clear();
+        remove_node_attributes(&mut self.edge_provenance, node);
+        remove_node_attributes(&mut self.edge_classes, node);
 
         if self.node_values.len() <= node {
             return None;
         }
 
-        dbg!(self.node_values.len());
-
         Some(self.node_values.remove(node))
     }
 
     fn remove_edge(&mut self, edge: (usize, usize)) -> Option<EV> {
         self.csr_inc.remove_edge((edge.1, edge.0));
-        self.csr_out.remove_edge(edge)
+        let value = self.csr_out.remove_edge(edge);
+
+        if value.is_some() {
+            self.dijkstra_cache.get_mut().cache_clear();
+            if let Some(provenance) = self.edge_provenance.as_mut() {
+                provenance.retain(|&(a, b, _)| (a, b) != edge);
+            }
+            if let Some(classes) = self.edge_classes.as_mut() {
+                classes.retain(|&(a, b, _)| (a, b) != edge);
+            }
+        }
+        value
     }
 }
 
@@ -426,92 +962,455 @@ impl<EV: Clone + Default, NV> DirectedGraph for DirectedCsrGraph<EV, NV> {
     }
 }
 
-impl<EV, NV> Dijkstra for DirectedCsrGraph<EV, NV>
+/// Cached shortest paths from a single start node.
+#[derive(Debug)]
+struct DijkstraCacheEntry<EV> {
+    result: DijkstraResult<EV>,
+    /// `true` once every node reachable from the start node has been settled.
+    complete: bool,
+}
+
+/// Dijkstra cache as written by [DirectedCsrGraph::save_cache].
+#[cfg(feature = "cache-snapshot")]
+#[derive(Serialize, Deserialize)]
+struct CacheSnapshot<EV> {
+    /// Hash of both CSRs, see [DirectedCsrGraph::fingerprint].
+    fingerprint: u64,
+    /// Least recently used first.
+    entries: Vec<CacheSnapshotEntry<EV>>,
+}
+
+#[cfg(feature = "cache-snapshot")]
+#[derive(Serialize, Deserialize)]
+struct CacheSnapshotEntry<EV> {
+    start: usize,
+    direction: Direction,
+    nodes: Vec<ResultNode<EV>>,
+    complete: bool,
+}
+
+#[cfg(feature = "cache-snapshot")]
+fn cache_io(err: impl std::fmt::Display) -> GraphError {
+    GraphError::CacheIo(err.to_string())
+}
+
+impl<EV> Default for DijkstraCacheEntry<EV> {
+    fn default() -> Self {
+        Self {
+            result: DijkstraResult(FxHashSet::default()),
+            complete: false,
+        }
+    }
+}
+
+impl<EV, NV> DirectedCsrGraph<EV, NV>
 where
     EV: FloatCore + Default + Debug + Clone,
 {
-    #[instrument(level = "trace", skip(self))]
-    fn dijkstra(
-        &self,
-        start_node: usize,
-        target_set: FxHashSet<usize>,
-        direction: Direction,
-    ) -> DijkstraResult<EV> {
-        let mut cache = self.dijkstra_cache.lock();
+    /// Runs an outgoing [Dijkstra::dijkstra_full] from every node in `sources` and keeps the
+    /// results in the Dijkstra cache.
+    ///
+    /// Call this before timing or serving queries to pay the cold cache cost up front. It
+    /// trades memory for latency, as every warmed source holds a full result in memory. If
+    /// there are more `sources` than the cache capacity, only the last ones stay cached.
+    pub fn warm_cache(&self, sources: &[usize]) {
+        for &source in sources {
+            self.cached_dijkstra(source, None, Direction::Outgoing);
+        }
+    }
 
-        let entry = cache.entry((start_node, direction));
+    /// Empties the Dijkstra cache, e.g. to time searches without the results of earlier ones.
+    pub fn clear_cache(&mut self) {
+        self.dijkstra_cache.get_mut().cache_clear();
+    }
 
-        let result = entry.or_insert(DijkstraResult(FxHashSet::default()));
+    /// Writes the Dijkstra cache to `path`, so a later run on the same graph can skip warming
+    /// it with [DirectedCsrGraph::load_cache].
+    #[cfg(feature = "cache-snapshot")]
+    pub fn save_cache(&self, path: impl AsRef<std::path::Path>) -> Result<(), GraphError>
+    where
+        EV: Serialize,
+    {
+        use std::io::Write;
+
+        let mut entries: Vec<CacheSnapshotEntry<EV>> = match &self.dijkstra_cache.lock().cache {
+            Some(cache) => cache
+                .key_order()
+                .zip(cache.value_order())
+                .map(|(&(start, direction), entry)| CacheSnapshotEntry {
+                    start,
+                    direction,
+                    nodes: entry.result.0.iter().cloned().collect(),
+                    complete: entry.complete,
+                })
+                .collect(),
+            None => vec![],
+        };
+        // The cache iterates the most recently used entry first.
+        entries.reverse();
 
-        // Get nodes which are not in the cached result
-        let target_set: FxHashSet<ResultNode<EV>> =
-            FxHashSet::from_iter(target_set.iter().map(|e| (*e).into()));
-        let mut target_set = FxHashSet::from_iter(target_set.difference(&result.0).cloned());
+        let snapshot = CacheSnapshot {
+            fingerprint: self.fingerprint()?,
+            entries,
+        };
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path).map_err(cache_io)?);
+        bincode::serde::encode_into_std_write(&snapshot, &mut writer, bincode::config::standard())
+            .map_err(cache_io)?;
+        writer.flush().map_err(cache_io)
+    }
 
-        let mut frontier = PriorityQueue::with_hasher(FxBuildHasher);
-        let mut visited = FxHashSet::default();
-        frontier.push(
-            ResultNode::new(Target::new(start_node, Self::EV::zero()), None),
-            Reverse(OrderedFloat(EV::zero())),
-        );
+    /// Replaces the Dijkstra cache with the one saved to `path` by
+    /// [DirectedCsrGraph::save_cache].
+    ///
+    /// Fails with [GraphError::CacheMismatch] if the cache was saved for a graph with other
+    /// edges or edge weights. Node values are not compared.
+    #[cfg(feature = "cache-snapshot")]
+    pub fn load_cache(&mut self, path: impl AsRef<std::path::Path>) -> Result<(), GraphError>
+    where
+        EV: Serialize + serde::de::DeserializeOwned,
+    {
+        let mut reader = std::io::BufReader::new(std::fs::File::open(path).map_err(cache_io)?);
+        let snapshot: CacheSnapshot<EV> =
+            bincode::serde::decode_from_std_read(&mut reader, bincode::config::standard())
+                .map_err(cache_io)?;
+        if snapshot.fingerprint != self.fingerprint()? {
+            return Err(GraphError::CacheMismatch);
+        }
 
-        while !target_set.is_empty() && !frontier.is_empty() {
-            let node = frontier.pop().expect("This is a bug").0;
-            if visited.contains(&node.node_id()) {
-                continue;
+        let cache = self.dijkstra_cache.get_mut().get_or_init();
+        cache.cache_clear();
+        for entry in snapshot.entries {
+            cache.cache_set(
+                (entry.start, entry.direction),
+                DijkstraCacheEntry {
+                    result: DijkstraResult(FxHashSet::from_iter(entry.nodes)),
+                    complete: entry.complete,
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Hash of both CSRs, which identifies the graph a saved Dijkstra cache is valid for.
+    #[cfg(feature = "cache-snapshot")]
+    fn fingerprint(&self) -> Result<u64, GraphError>
+    where
+        EV: Serialize,
+    {
+        use std::hash::Hasher;
+
+        let bytes = bincode::serde::encode_to_vec(
+            (&self.csr_out, &self.csr_inc),
+            bincode::config::standard(),
+        )
+        .map_err(cache_io)?;
+        let mut hasher = rustc_hash::FxHasher::default();
+        hasher.write(&bytes);
+        Ok(hasher.finish())
+    }
+
+    /// Runs Dijkstra from `start_node` and stores the settled nodes in the cache.
+    ///
+    /// When `target_set` is `None` every reachable node is settled.
+    /// Returns the cached result together with the number of nodes settled by this call.
+    ///
+    /// The cache is only locked to look up and store the result, so searches from several
+    /// threads run concurrently. Threads searching from the same start node at the same time
+    /// each run their own search.
+    #[instrument(level = "trace", skip(self))]
+    fn cached_dijkstra(
+        &self,
+        start_node: usize,
+        target_set: Option<FxHashSet<usize>>,
+        direction: Direction,
+    ) -> (DijkstraResult<EV>, usize) {
+        let mut target_set: Option<FxHashSet<ResultNode<EV>>> = {
+            let mut cache = self.dijkstra_cache.lock();
+            let entry = cache
+                .get_or_init()
+                .cache_get_or_set_with((start_node, direction), Default::default);
+
+            if entry.complete {
+                return (entry.result.clone(), 0);
             }
 
-            let neighbours: Box<dyn Iterator<Item = &Target<EV>>> = match direction {
-                Direction::Outgoing => Box::new(self.out_neighbors(node.node_id())),
-                Direction::Incoming => Box::new(self.in_neighbors(node.node_id())),
-                Direction::Undirected => Box::new(self.neighbors(node.node_id())),
-            };
-
-            neighbours.for_each(|n| {
-                let path_cost = *node.cost() + *n.value();
-                let new_node =
-                    ResultNode::new(Target::new(n.target(), path_cost), Some(node.node_id()));
-                let path_cost = Reverse(OrderedFloat(path_cost));
-                if let Some(priority) = frontier.get_priority(&new_node) {
-                    if priority < &path_cost {
-                        frontier.change_priority(&new_node, path_cost);
-                    }
-                } else {
-                    frontier.push(new_node, path_cost);
-                }
-                // if !frontier.change_priority_by(&new_node, |p| {
-                //     if p.0 > path_cost {
-                //         p.0 = path_cost
-                //     }
-                // }) {
-                //     frontier.push(new_node, Reverse(path_cost));
-                // }
-            });
+            // Get nodes which are not in the cached result
+            target_set.map(|target_set| {
+                target_set
+                    .into_iter()
+                    .map(ResultNode::from)
+                    .filter(|node| !entry.result.0.contains(node))
+                    .collect()
+            })
+        };
 
-            visited.insert(node.node_id());
+        let (settled, exhausted) = match self.frontier {
+            Frontier::PriorityQueue => self.settle_queue(start_node, &mut target_set, direction),
+            Frontier::BinaryHeap => self.settle_heap(start_node, &mut target_set, direction),
+        };
+        let settled_count = settled.len();
 
-            target_set.take(&node).inspect(|node| {
-                trace!("found path to node {:?}", node);
-            });
-            result.0.insert(node);
+        let mut cache = self.dijkstra_cache.lock();
+        // The entry can have been evicted or completed by another thread in the meantime.
+        let entry = cache
+            .get_or_init()
+            .cache_get_or_set_with((start_node, direction), Default::default);
+        entry.result.0.extend(settled);
+
+        // An exhausted frontier means every reachable node has been settled.
+        if exhausted {
+            entry.complete = true;
         }
 
-        if !target_set.is_empty() {
+        if let Some(target_set) = target_set
+            && !target_set.is_empty()
+        {
             debug!("could not find a path to these nodes: {:?}", target_set);
         }
 
-        result.clone()
+        (entry.result.clone(), settled_count)
     }
-}
 
-impl<EV, NV> From<EdgeList<EV>> for DirectedCsrGraph<EV, NV>
+    /// Neighbours of `node` in `direction`.
+    fn neighbors_in(
+        &self,
+        node: usize,
+        direction: Direction,
+    ) -> Box<dyn Iterator<Item = &Target<EV>> + '_> {
+        match direction {
+            Direction::Outgoing => Box::new(self.out_neighbors(node)),
+            Direction::Incoming => Box::new(self.in_neighbors(node)),
+            Direction::Undirected => Box::new(self.neighbors(node)),
+        }
+    }
+
+    /// Settles nodes from `start_node` with a [Frontier::PriorityQueue] until `target_set` is
+    /// empty or the frontier is exhausted. Settled targets are removed from `target_set`.
+    ///
+    /// Returns the settled nodes and whether the frontier was exhausted.
+    fn settle_queue(
+        &self,
+        start_node: usize,
+        target_set: &mut Option<FxHashSet<ResultNode<EV>>>,
+        direction: Direction,
+    ) -> (Vec<ResultNode<EV>>, bool) {
+        let mut frontier = PriorityQueue::with_hasher(FxBuildHasher);
+        let mut best: FxHashMap<usize, (EV, Option<usize>)> = FxHashMap::default();
+        let mut visited = FxHashSet::default();
+        let mut settled = Vec::new();
+        best.insert(start_node, (EV::zero(), None));
+        frontier.push(start_node, Reverse(OrderedFloat(EV::zero())));
+
+        while target_set
+            .as_ref()
+            .is_none_or(|target_set| !target_set.is_empty())
+            && let Some((node_id, _)) = frontier.pop()
+        {
+            visited.insert(node_id);
+            let (cost, predecessor) = best[&node_id];
+            let node = ResultNode::new(Target::new(node_id, cost), predecessor);
+
+            self.neighbors_in(node_id, direction).for_each(|n| {
+                if visited.contains(&n.target()) {
+                    return;
+                }
+                let path_cost = cost + *n.value();
+                if best.get(&n.target()).is_none_or(|(c, _)| path_cost < *c) {
+                    best.insert(n.target(), (path_cost, Some(node_id)));
+                    frontier.push_increase(n.target(), Reverse(OrderedFloat(path_cost)));
+                }
+            });
+
+            if let Some(target_set) = target_set.as_mut() {
+                target_set.take(&node).inspect(|node| {
+                    trace!("found path to node {:?}", node);
+                });
+            }
+            settled.push(node);
+        }
+
+        (settled, frontier.is_empty())
+    }
+
+    /// Like [DirectedCsrGraph::settle_queue] with a [Frontier::BinaryHeap].
+    ///
+    /// A node is pushed again for every cheaper path found and popped entries of settled nodes
+    /// are skipped.
+    fn settle_heap(
+        &self,
+        start_node: usize,
+        target_set: &mut Option<FxHashSet<ResultNode<EV>>>,
+        direction: Direction,
+    ) -> (Vec<ResultNode<EV>>, bool) {
+        let mut frontier = BinaryHeap::new();
+        let mut visited = FxHashSet::default();
+        let mut settled = Vec::new();
+        frontier.push(Reverse((OrderedFloat(EV::zero()), start_node, None)));
+
+        while target_set
+            .as_ref()
+            .is_none_or(|target_set| !target_set.is_empty())
+            && let Some(Reverse((OrderedFloat(cost), node_id, predecessor))) = frontier.pop()
+        {
+            if !visited.insert(node_id) {
+                continue;
+            }
+
+            for n in self
+                .neighbors_in(node_id, direction)
+                .filter(|n| !visited.contains(&n.target()))
+            {
+                frontier.push(Reverse((
+                    OrderedFloat(cost + *n.value()),
+                    n.target(),
+                    Some(node_id),
+                )));
+            }
+
+            let node = ResultNode::new(Target::new(node_id, cost), predecessor);
+            if let Some(target_set) = target_set.as_mut() {
+                target_set.take(&node).inspect(|node| {
+                    trace!("found path to node {:?}", node);
+                });
+            }
+            settled.push(node);
+        }
+
+        // Stale entries of settled nodes can remain, they do not count as unexplored.
+        let exhausted = frontier
+            .iter()
+            .all(|Reverse((_, node_id, _))| visited.contains(node_id));
+
+        (settled, exhausted)
+    }
+}
+
+impl<EV, NV> DirectedCsrGraph<EV, NV>
 where
-    EV: Copy + Default + Send + Sync,
-    NV: Default + Clone,
+    EV: FloatCore + Default + Debug + Clone,
 {
-    fn from(edge_list: EdgeList<EV>) -> Self {
-        let degrees_out = edge_list.degrees(Direction::Outgoing);
-        let degrees_in = edge_list.degrees(Direction::Incoming);
+    /// Edge based Dijkstra from `start_node` to `target` with turn costs.
+    ///
+    /// Whenever a path continues from `prev_edge` over `node` into `next_edge`,
+    /// `turn_cost(prev_edge, node, next_edge)` is added to its cost. An infinite
+    /// turn cost forbids the turn.
+    /// Returns `None` if `target` can not be reached.
+    #[instrument(level = "trace", skip(self, turn_cost))]
+    pub fn dijkstra_with_turns<F>(
+        &self,
+        start_node: usize,
+        target: usize,
+        turn_cost: F,
+    ) -> Option<Path<EV>>
+    where
+        F: Fn((usize, usize), usize, (usize, usize)) -> EV,
+    {
+        // A node together with the node it was entered from.
+        type State = (Option<usize>, usize);
+
+        let mut frontier = PriorityQueue::with_hasher(FxBuildHasher);
+        let mut best: FxHashMap<State, (EV, Option<State>)> = FxHashMap::default();
+        let mut settled = FxHashSet::default();
+
+        let start = (None, start_node);
+        best.insert(start, (EV::zero(), None));
+        frontier.push(start, Reverse(OrderedFloat(EV::zero())));
+
+        while let Some((state, Reverse(OrderedFloat(cost)))) = frontier.pop() {
+            settled.insert(state);
+            let (prev_node, node) = state;
+
+            if node == target {
+                let mut path = Vec::new();
+                let mut current = Some(state);
+                while let Some(state) = current {
+                    let (cost, prev_state) = best[&state];
+                    path.push(Target::new(state.1, cost));
+                    current = prev_state;
+                }
+                path.reverse();
+
+                return Some(Path::new(path));
+            }
+
+            for next in self.out_neighbors(node) {
+                let next_state = (Some(node), next.target());
+                if settled.contains(&next_state) {
+                    continue;
+                }
+
+                let turn = prev_node
+                    .map(|prev_node| turn_cost((prev_node, node), node, (node, next.target())))
+                    .unwrap_or(EV::zero());
+                let next_cost = cost + turn + *next.value();
+                if next_cost.is_infinite() {
+                    continue;
+                }
+
+                if best
+                    .get(&next_state)
+                    .is_none_or(|(best_cost, _)| next_cost < *best_cost)
+                {
+                    best.insert(next_state, (next_cost, Some(state)));
+                    frontier.push_increase(next_state, Reverse(OrderedFloat(next_cost)));
+                }
+            }
+        }
+
+        debug!("could not find a path to node {target}");
+
+        None
+    }
+}
+
+impl<EV, NV> Dijkstra for DirectedCsrGraph<EV, NV>
+where
+    EV: FloatCore + Weight + Default + Debug + Clone,
+{
+    fn dijkstra(
+        &self,
+        start_node: usize,
+        target_set: FxHashSet<usize>,
+        direction: Direction,
+    ) -> DijkstraResult<EV> {
+        self.cached_dijkstra(start_node, Some(target_set), direction)
+            .0
+    }
+
+    /// Settles every node reachable from `start_node` once and serves later
+    /// queries from the same start node out of the cache.
+    fn dijkstra_full(&self, start_node: usize, direction: Direction) -> DijkstraResult<EV> {
+        self.cached_dijkstra(start_node, None, direction).0
+    }
+}
+
+impl<EV, NV> DirectedCsrGraph<EV, NV>
+where
+    EV: Copy + Default,
+{
+    /// Builds the graph in a single pass from `edges` and the values of all nodes.
+    ///
+    /// Node ids in `edges` index into `node_values`. This avoids building an
+    /// intermediate graph when the node values are already known.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an edge references a node without a value.
+    pub fn from_edges_and_values<I>(edges: I, node_values: Vec<NV>) -> DirectedCsrGraph<EV, NV>
+    where
+        I: IntoIterator<Item = (usize, usize, EV)>,
+        I::IntoIter: Clone,
+    {
+        let edges = edges.into_iter();
+
+        let mut degrees_out = vec![0; node_values.len()];
+        let mut degrees_in = vec![0; node_values.len()];
+
+        edges.clone().for_each(|(s, t, _)| {
+            degrees_out[s] += 1;
+            degrees_in[t] += 1;
+        });
 
         let mut offsets_out = prefix_sum(degrees_out);
         let mut offsets_in = prefix_sum(degrees_in);
@@ -531,7 +1430,7 @@ where
         targets_out.resize_with(*edge_count_out, || Target::new(0, Default::default()));
         targets_in.resize_with(*edge_count_in, || Target::new(0, Default::default()));
 
-        edge_list.edges().for_each(|(s, t, v)| {
+        edges.for_each(|(s, t, v)| {
             let offset_out = offsets_out[s];
             let offset_in = offsets_in[t];
 
@@ -551,15 +1450,411 @@ where
 
         let csr_out = Csr::new(offsets_out, targets_out);
         let csr_inc = Csr::new(offsets_in, targets_in);
-        let mut node_values = Vec::new();
-        node_values.resize(csr_out.node_count(), NV::default());
 
         DirectedCsrGraph::new(node_values, csr_out, csr_inc)
     }
+
+    /// Builds a graph whose node values carry a coordinate, like `CoordNode`.
+    ///
+    /// Node ids in `edges` index into `nodes`.
+    ///
+    /// ```
+    /// use geo::Coord;
+    /// use graph_rs::{Graph, graph::csr::DirectedCsrGraph};
+    ///
+    /// let graph = DirectedCsrGraph::from_coords_and_edges(
+    ///     vec![
+    ///         Coord { x: 0., y: 0. },
+    ///         Coord { x: 1., y: 0. },
+    ///         Coord { x: 0., y: 1. },
+    ///     ],
+    ///     vec![(0, 1, 1.), (1, 2, 1.5), (2, 0, 1.)],
+    /// );
+    ///
+    /// assert_eq!(graph.node_count(), 3);
+    /// assert_eq!(graph.edge_count(), 3);
+    /// assert_eq!(graph.node_value(2), Some(&Coord { x: 0., y: 1. }));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if an edge references a node which is not in `nodes`.
+    pub fn from_coords_and_edges(
+        nodes: Vec<NV>,
+        edges: Vec<(usize, usize, EV)>,
+    ) -> DirectedCsrGraph<EV, NV>
+    where
+        NV: Coordinate,
+    {
+        Self::from_edges_and_values(edges, nodes)
+    }
+}
+
+impl<EV> DirectedCsrGraph<EV, ()> {
+    /// Attaches `values` to the nodes, e.g. coordinates read separately from the edges.
+    ///
+    /// The CSRs are reused, as are the edge provenance and classes and the Dijkstra cache,
+    /// which do not depend on the node values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is not exactly one value per node.
+    pub fn with_node_values<NV>(self, values: Vec<NV>) -> DirectedCsrGraph<EV, NV> {
+        assert_eq!(
+            values.len(),
+            self.csr_out.node_count(),
+            "number of node values and nodes differ"
+        );
+
+        DirectedCsrGraph {
+            node_values: values,
+            csr_out: self.csr_out,
+            csr_inc: self.csr_inc,
+            edge_provenance: self.edge_provenance,
+            edge_classes: self.edge_classes,
+            dijkstra_cache: self.dijkstra_cache,
+            frontier: self.frontier,
+        }
+    }
+}
+
+/// Sorts per edge `attributes` by edge, keeping the first one given for each edge of `csr`.
+fn sorted_edge_attributes<EV, T>(
+    csr: &Csr<EV>,
+    mut attributes: Vec<(usize, usize, T)>,
+) -> Vec<(usize, usize, T)> {
+    attributes.retain(|&(a, b, _)| {
+        a < csr.node_count() && csr.targets(a).iter().any(|t| t.target() == b)
+    });
+    attributes.sort_by_key(|&(a, b, _)| (a, b));
+    attributes.dedup_by_key(|&mut (a, b, _)| (a, b));
+    attributes
+}
+
+/// Looks up the attribute of the edge from `a` to `b` in attributes sorted by edge.
+fn edge_attribute<T: Copy>(
+    attributes: &Option<Vec<(usize, usize, T)>>,
+    a: usize,
+    b: usize,
+) -> Option<T> {
+    let attributes = attributes.as_ref()?;
+    let index = attributes
+        .binary_search_by_key(&(a, b), |&(a, b, _)| (a, b))
+        .ok()?;
+
+    Some(attributes[index].2)
+}
+
+/// Sets the attribute of the edge from `a` to `b` in attributes sorted by edge, if the graph
+/// records them and there is a `value`.
+fn set_edge_attribute<T: Copy>(
+    attributes: &mut Option<Vec<(usize, usize, T)>>,
+    a: usize,
+    b: usize,
+    value: Option<T>,
+) {
+    let (Some(attributes), Some(value)) = (attributes.as_mut(), value) else {
+        return;
+    };
+    match attributes.binary_search_by_key(&(a, b), |&(a, b, _)| (a, b)) {
+        Ok(index) => attributes[index].2 = value,
+        Err(index) => attributes.insert(index, (a, b, value)),
+    }
+}
+
+/// Drops the attributes of the edges of `node` and shifts the ids after it.
+fn remove_node_attributes<T: Copy>(attributes: &mut Option<Vec<(usize, usize, T)>>, node: usize) {
+    if let Some(attributes) = attributes.as_mut() {
+        attributes.retain(|&(a, b, _)| a != node && b != node);
+        let shift = |id: usize| if id > node { id - 1 } else { id };
+        for edge in attributes.iter_mut() {
+            *edge = (shift(edge.0), shift(edge.1), edge.2);
+        }
+    }
+}
+
+/// Renames the nodes of each attribute by `node_map`, dropping edges with a removed node.
+fn map_edge_attributes<T: Copy>(
+    attributes: &Option<Vec<(usize, usize, T)>>,
+    node_map: &HashMap<usize, usize>,
+) -> Option<Vec<(usize, usize, T)>> {
+    attributes.as_ref().map(|attributes| {
+        attributes
+            .iter()
+            .filter_map(|&(a, b, value)| Some((*node_map.get(&a)?, *node_map.get(&b)?, value)))
+            .collect()
+    })
+}
+
+impl<EV, NV> From<EdgeList<EV>> for DirectedCsrGraph<EV, NV>
+where
+    EV: Copy + Default + Send + Sync,
+    NV: Default + Clone,
+{
+    fn from(edge_list: EdgeList<EV>) -> Self {
+        let node_values = vec![NV::default(); edge_list.max_node_id() + 1];
+
+        DirectedCsrGraph::from_edges_and_values(edge_list.edges(), node_values)
+    }
+}
+
+/// Undirected graph backed by a single [Csr].
+///
+/// Every edge is stored in the rows of both of its endpoints, which halves the
+/// memory of [DirectedCsrGraph] for symmetric networks.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct UndirectedCsrGraph<EV, NV> {
+    pub node_values: Vec<NV>,
+    pub csr: Csr<EV>,
+    /// Self-loops are stored once, every other edge in the rows of both endpoints.
+    loops: usize,
+}
+
+impl<EV, NV> UndirectedCsrGraph<EV, NV> {
+    pub fn new(node_values: Vec<NV>, csr: Csr<EV>) -> UndirectedCsrGraph<EV, NV> {
+        let loops = (0..csr.node_count())
+            .map(|node| Self::loops_at(&csr, node))
+            .sum();
+        let g = Self {
+            node_values,
+            csr,
+            loops,
+        };
+
+        info!(
+            "Created undirected graph (node_count: {:?}, edge_count = {:?})",
+            g.csr.node_count(),
+            (g.csr.edge_count() + g.loops) / 2
+        );
+
+        g
+    }
+
+    fn loops_at(csr: &Csr<EV>, node: usize) -> usize {
+        csr.targets(node)
+            .iter()
+            .filter(|target| target.target() == node)
+            .count()
+    }
+}
+
+impl<EV, NV> Default for UndirectedCsrGraph<EV, NV> {
+    fn default() -> Self {
+        UndirectedCsrGraph::new(vec![], Csr::default())
+    }
+}
+
+impl<EV: Clone + Default, NV> Graph for UndirectedCsrGraph<EV, NV> {
+    type EV = EV;
+    type NV = NV;
+    fn node_count(&self) -> usize {
+        self.csr.node_count()
+    }
+
+    fn edge_count(&self) -> usize {
+        (self.csr.edge_count() + self.loops) / 2
+    }
+
+    // TODO: Use Result<usize> as return value.
+    fn neighbors<'a>(&'a self, node: usize) -> impl Iterator<Item = &'a Target<EV>>
+    where
+        EV: 'a,
+    {
+        self.csr.targets(node).iter()
+    }
+
+    /// Returns every edge once as `(a, b)` with `a <= b`.
+    fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.node_count()).flat_map(move |node_id| {
+            self.neighbors(node_id)
+                .filter(move |neighbor| node_id <= neighbor.target())
+                .map(move |neighbor| (node_id, neighbor.target()))
+        })
+    }
+
+    // TODO: Use Result<usize> as return value.
+    fn degree(&self, node: usize) -> usize {
+        self.csr.degree(node)
+    }
+
+    fn node_value(&self, node: usize) -> Option<&NV> {
+        self.node_values.get(node)
+    }
+
+    fn nodes_iter<'a>(&'a self) -> impl Iterator<Item = (usize, &'a NV)>
+    where
+        NV: 'a,
+    {
+        self.node_values.iter().enumerate()
+    }
+
+    fn node_value_mut(&mut self, node: usize) -> Option<&mut NV> {
+        self.node_values.get_mut(node)
+    }
+
+    fn set_node_value(&mut self, node: usize, value: NV) -> Result<(), crate::GraphError> {
+        let node_value = self
+            .node_values
+            .get_mut(node)
+            .ok_or(GraphError::EmptyNode(node))?;
+
+        *node_value = value;
+
+        Ok(())
+    }
+
+    fn add_node(&mut self, weight: NV) -> usize {
+        let id = self.csr.add_node();
+
+        self.node_values.push(weight);
+        assert_eq!(id, self.node_values.len() - 1);
+        id
+    }
+
+    fn add_edge(&mut self, a: usize, b: usize, weight: EV) -> bool {
+        let ret = self.csr.add_edge(a, b, weight.clone());
+        if ret && a == b {
+            self.loops += 1;
+        } else if ret {
+            let ret_rev = self.csr.add_edge(b, a, weight);
+            assert!(ret_rev, "csr is not symmetric");
+        }
+
+        ret
+    }
+
+    fn remove_node(&mut self, node: usize) -> Option<NV> {
+        if node < self.csr.node_count() {
+            self.loops -= Self::loops_at(&self.csr, node);
+        }
+        self.csr.remove_node(node);
+
+        if self.node_values.len() <= node {
+            return None;
+        }
+
+        Some(self.node_values.remove(node))
+    }
+
+    fn remove_edge(&mut self, edge: (usize, usize)) -> Option<EV> {
+        if edge.0 != edge.1 {
+            self.csr.remove_edge((edge.1, edge.0));
+        }
+        let removed = self.csr.remove_edge(edge);
+        if removed.is_some() && edge.0 == edge.1 {
+            self.loops -= 1;
+        }
+        removed
+    }
+}
+
+/// Incoming and outgoing neighbors of an undirected graph are the same.
+impl<EV: Clone + Default, NV> DirectedGraph for UndirectedCsrGraph<EV, NV> {
+    fn out_neighbors<'a>(&'a self, node: usize) -> impl Iterator<Item = &'a Target<EV>>
+    where
+        EV: 'a,
+    {
+        self.neighbors(node)
+    }
+
+    fn in_neighbors<'a>(&'a self, node: usize) -> impl Iterator<Item = &'a Target<EV>>
+    where
+        EV: 'a,
+    {
+        self.neighbors(node)
+    }
+
+    fn out_degree(&self, node: usize) -> usize {
+        self.degree(node)
+    }
+
+    fn in_degree(&self, node: usize) -> usize {
+        self.degree(node)
+    }
+}
+
+impl<EV, NV> From<EdgeList<EV>> for UndirectedCsrGraph<EV, NV>
+where
+    EV: Copy + Default + Send + Sync,
+    NV: Default + Clone,
+{
+    /// Interprets every edge as undirected.
+    ///
+    /// Edges which are listed in both directions are only inserted once.
+    fn from(edge_list: EdgeList<EV>) -> Self {
+        let mut seen = FxHashSet::default();
+        let edges: Vec<_> = edge_list
+            .edges()
+            .filter(|(s, t, _)| seen.insert((*s.min(t), *s.max(t))))
+            .collect();
+
+        let mut degrees = vec![0; edge_list.max_node_id() + 1];
+        edges.iter().for_each(|(s, t, _)| {
+            degrees[*s] += 1;
+            if s != t {
+                degrees[*t] += 1;
+            }
+        });
+
+        let mut offsets = prefix_sum(degrees);
+        let edge_count = *offsets.last().unwrap();
+
+        let mut targets = Vec::<Target<EV>>::with_capacity(edge_count);
+        targets.resize_with(edge_count, || Target::new(0, Default::default()));
+
+        edges.into_iter().for_each(|(s, t, v)| {
+            targets[offsets[s]] = Target::new(t, v);
+            offsets[s] += 1;
+
+            if s != t {
+                targets[offsets[t]] = Target::new(s, v);
+                offsets[t] += 1;
+            }
+        });
+
+        offsets.rotate_right(1);
+        offsets[0] = 0;
+
+        let csr = Csr::new(offsets, targets);
+        let mut node_values = Vec::new();
+        node_values.resize(csr.node_count(), NV::default());
+
+        UndirectedCsrGraph::new(node_values, csr)
+    }
+}
+
+impl<EV, NV> From<DirectedCsrGraph<EV, NV>> for UndirectedCsrGraph<EV, NV>
+where
+    EV: Clone + Default + PartialEq + Debug,
+{
+    /// Converts a symmetric [DirectedCsrGraph].
+    ///
+    /// # Panics
+    ///
+    /// Panics if an edge `(a, b)` has no reverse edge `(b, a)` with the same weight.
+    fn from(graph: DirectedCsrGraph<EV, NV>) -> Self {
+        for node in 0..graph.node_count() {
+            let mut out_targets: Vec<_> = graph.out_neighbors(node).collect();
+            let mut in_targets: Vec<_> = graph.in_neighbors(node).collect();
+            out_targets.sort_by_key(|target| target.target());
+            in_targets.sort_by_key(|target| target.target());
+
+            assert!(
+                out_targets.len() == in_targets.len()
+                    && out_targets
+                        .iter()
+                        .zip(in_targets.iter())
+                        .all(|(o, i)| { o.target() == i.target() && o.value() == i.value() }),
+                "graph is not symmetric at node {node}: out: {out_targets:?}, in: {in_targets:?}"
+            );
+        }
+
+        UndirectedCsrGraph::new(graph.node_values, graph.csr_out)
+    }
 }
 
 fn prefix_sum(degrees: Vec<usize>) -> Vec<usize> {
-    let mut last = *degrees.last().unwrap();
+    let mut last = degrees.last().copied().unwrap_or(0);
     let mut sums: Vec<usize> = degrees
         .into_iter()
         .scan(0, |total, degree| {
@@ -569,7 +1864,7 @@ fn prefix_sum(degrees: Vec<usize>) -> Vec<usize> {
         })
         .collect();
 
-    last += *sums.last().unwrap();
+    last += sums.last().copied().unwrap_or(0);
     sums.push(last);
 
     sums
@@ -738,6 +2033,284 @@ mod tests {
         assert_eq!(graph, expected);
     }
 
+    #[test]
+    fn structurally_eq() {
+        let mut lhs: DirectedCsrGraph<i32, ()> = DirectedCsrGraph::default();
+        let mut rhs: DirectedCsrGraph<i32, ()> = DirectedCsrGraph::default();
+        for _ in 0..3 {
+            lhs.add_node(());
+            rhs.add_node(());
+        }
+
+        let edges = [(0, 1, 1), (0, 2, 2), (2, 1, 3)];
+        for (a, b, weight) in edges {
+            lhs.add_edge(a, b, weight);
+        }
+        for (a, b, weight) in edges.into_iter().rev() {
+            rhs.add_edge(a, b, weight);
+        }
+
+        assert_ne!(lhs, rhs);
+        assert!(lhs.structurally_eq(&rhs));
+
+        rhs.remove_edge((0, 2));
+        rhs.add_edge(0, 2, 4);
+        assert!(!lhs.structurally_eq(&rhs));
+    }
+
+    #[test]
+    fn recompute_edge_weights() {
+        let mut graph: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(vec![
+            (0, 1, 0.25),
+            (1, 2, 0.25),
+            (2, 3, 0.25),
+            (0, 3, 10.),
+            (3, 4, 1.),
+        ]));
+        assert_eq!(
+            graph.dijkstra_full(0, Direction::Outgoing).cost(3),
+            Some(0.75)
+        );
+
+        graph.recompute_edge_weights(|_, _, _| 1.);
+
+        // Every edge costs 1, so the costs are the hop counts.
+        let outgoing = graph.dijkstra_full(0, Direction::Outgoing);
+        let hops: Vec<_> = (0..5).map(|node| outgoing.cost(node)).collect();
+        assert_eq!(hops, vec![Some(0.), Some(1.), Some(2.), Some(1.), Some(2.)]);
+        let incoming = graph.dijkstra_full(4, Direction::Incoming);
+        assert_eq!(incoming.cost(0), Some(2.));
+
+        graph.recompute_edge_weights(|from, to, weight| weight * (10 * from + to) as f64);
+        let out_weight = graph.out_neighbors(2).find(|t| t.target() == 3).unwrap();
+        let in_weight = graph.in_neighbors(3).find(|t| t.target() == 2).unwrap();
+        assert_eq!(*out_weight.value(), 23.);
+        assert_eq!(*in_weight.value(), 23.);
+    }
+
+    #[test]
+    fn set_edge_weight() {
+        let mut graph: DirectedCsrGraph<f64, ()> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.), (1, 2, 1.), (0, 2, 5.)]));
+        assert_eq!(
+            graph.dijkstra_full(0, Direction::Outgoing).cost(2),
+            Some(2.)
+        );
+
+        assert_eq!(graph.set_edge_weight(0, 2, 0.5), Some(5.));
+        assert_eq!(
+            graph.dijkstra_full(0, Direction::Outgoing).cost(2),
+            Some(0.5)
+        );
+        assert_eq!(
+            graph.dijkstra_full(2, Direction::Incoming).cost(0),
+            Some(0.5)
+        );
+        let in_weight = graph.in_neighbors(2).find(|t| t.target() == 0).unwrap();
+        assert_eq!(*in_weight.value(), 0.5);
+
+        assert_eq!(graph.set_edge_weight(2, 0, 1.), None);
+        assert_eq!(graph.set_edge_weight(7, 0, 1.), None);
+        assert_eq!(graph.edge_count(), 3);
+    }
+
+    #[cfg(feature = "rstar")]
+    #[test]
+    fn with_node_values() {
+        use geo::Coord;
+
+        use crate::{CoordGraph, graph::rstar::RTreeGraph};
+
+        let graph: DirectedCsrGraph<f64, ()> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.), (1, 2, 1.), (2, 0, 3.)]));
+        let coords = (0..3).map(|x| Coord { x: x as f64, y: 0. }).collect();
+        let graph = RTreeGraph::new_from_graph(graph.with_node_values(coords));
+
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 3);
+        assert_eq!(graph.node_coord(2), Some(Coord { x: 2., y: 0. }));
+        assert_eq!(graph.nearest_node(&Coord { x: 0.9, y: 0.1 }), Some(1));
+        assert_eq!(
+            graph.dijkstra_full(2, Direction::Outgoing).cost(1),
+            Some(4.)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "number of node values and nodes differ")]
+    fn with_node_values_count() {
+        let graph: DirectedCsrGraph<f64, ()> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.)]));
+        graph.with_node_values(vec![0; 3]);
+    }
+
+    #[cfg(feature = "rstar")]
+    #[test]
+    fn node_values_iter_mut() {
+        use geo::Coord;
+
+        use crate::{CoordGraph, graph::rstar::RTreeGraph};
+
+        let edges = vec![(0, 1, 1.), (1, 2, 1.), (2, 3, 1.)];
+        let coords = (0..4).map(|x| Coord { x: x as f64, y: 0. }).collect();
+        let mut graph: DirectedCsrGraph<f64, Coord<f64>> =
+            DirectedCsrGraph::from_edges_and_values(edges, coords);
+        assert_eq!(
+            graph.dijkstra_full(0, Direction::Outgoing).cost(3),
+            Some(3.)
+        );
+
+        for (node, coord) in graph.node_values_iter_mut() {
+            assert_eq!(coord.x, node as f64);
+            coord.y = 10.;
+        }
+
+        assert!(graph.nodes_iter().all(|(node, coord)| *coord
+            == Coord {
+                x: node as f64,
+                y: 10.
+            }));
+        assert_eq!(
+            graph.dijkstra_full(0, Direction::Outgoing).cost(3),
+            Some(3.)
+        );
+
+        let graph = RTreeGraph::new_from_graph(graph);
+        assert_eq!(graph.nearest_node(&Coord { x: 2.1, y: 10. }), Some(2));
+        assert_eq!(graph.node_coord(3), Some(Coord { x: 3., y: 10. }));
+    }
+
+    #[test]
+    fn into_edge_list() {
+        let graph = setup();
+        let edge_count = graph.edge_count();
+
+        let edge_list = graph.into_edge_list();
+        assert_eq!(edge_list.edges().count(), edge_count);
+        assert_eq!(edge_list.max_node_id(), 30);
+
+        let graph: DirectedCsrGraph<i32, ()> = DirectedCsrGraph::from(edge_list);
+        assert!(graph.structurally_eq(&setup()));
+    }
+
+    #[test]
+    fn degree_histogram() {
+        let graph = setup();
+
+        // Nodes 6 to 29 except 20 have no edges.
+        assert_eq!(
+            graph.degree_histogram().into_iter().collect::<Vec<_>>(),
+            vec![(0, 23), (1, 2), (2, 3), (3, 2), (4, 1)]
+        );
+        assert_eq!(
+            DirectedCsrGraph::<i32, ()>::default().degree_histogram(),
+            Default::default()
+        );
+    }
+
+    #[test]
+    fn edge_weight_stats() {
+        assert_eq!(
+            DirectedCsrGraph::<f64, ()>::default().edge_weight_stats(),
+            None
+        );
+        assert!(
+            DirectedCsrGraph::<f64, ()>::default()
+                .find_outlier_edges(3.)
+                .is_empty()
+        );
+
+        // A path of 10 edges of length 1 and one edge across the globe.
+        let mut edges = vec![(0, 10, 2e7), (10, 0, 2e7)];
+        for node in 0..10 {
+            edges.extend([(node, node + 1, 1.), (node + 1, node, 1.)]);
+        }
+        let graph: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(edges));
+
+        let (min, max, mean, stddev) = graph.edge_weight_stats().unwrap();
+        assert_eq!((min, max), (1., 2e7));
+        let expected_mean = (20. + 4e7) / 22.;
+        let variance = |weight: f64, count: f64| count * (weight - expected_mean).powi(2) / 22.;
+        let expected_stddev = (variance(1., 20.) + variance(2e7, 2.)).sqrt();
+        assert!((mean / expected_mean - 1.).abs() < 1e-9, "{mean}");
+        assert!((stddev / expected_stddev - 1.).abs() < 1e-9, "{stddev}");
+
+        let mut outliers = graph.find_outlier_edges(3.);
+        outliers.sort_by_key(|edge| (edge.0, edge.1));
+        assert_eq!(outliers, vec![(0, 10, 2e7), (10, 0, 2e7)]);
+        assert!(graph.find_outlier_edges(4.).is_empty());
+    }
+
+    #[test]
+    fn edge_provenance() {
+        let mut graph: DirectedCsrGraph<f64, usize> = DirectedCsrGraph::from_edges_and_values(
+            vec![(0, 1, 1.), (1, 2, 1.), (2, 3, 1.), (3, 0, 1.)],
+            vec![0, 1, 2, 3],
+        );
+        assert_eq!(graph.edge_provenance(0, 1), None);
+
+        // The first feature of an edge is kept and (1, 3) is no edge.
+        graph.set_edge_provenance(vec![
+            (2, 3, 12),
+            (0, 1, 10),
+            (1, 2, 11),
+            (0, 1, 13),
+            (1, 3, 14),
+        ]);
+        assert_eq!(graph.edge_provenance(0, 1), Some(10));
+        assert_eq!(graph.edge_provenance(1, 2), Some(11));
+        assert_eq!(graph.edge_provenance(3, 0), None);
+        assert_eq!(graph.edge_provenance(1, 3), None);
+
+        graph.remove_edge((2, 3));
+        assert_eq!(graph.edge_provenance(2, 3), None);
+
+        // Removing a node shifts the ids after it.
+        graph.remove_node(0);
+        assert_eq!(graph.edge_provenance(0, 1), Some(11));
+
+        let graph = graph.filter(|node| *node.1 != 1);
+        assert_eq!(graph.edge_provenance(0, 1), None);
+        assert_eq!(graph.node_count(), 2);
+    }
+
+    #[test]
+    fn edge_classes() {
+        let mut graph: DirectedCsrGraph<f64, usize> = DirectedCsrGraph::from_edges_and_values(
+            vec![(0, 1, 1.), (1, 2, 1.), (0, 2, 1.), (2, 0, 1.)],
+            vec![0, 1, 2],
+        );
+        assert_eq!(graph.edge_class(0, 1), None);
+        assert_eq!(graph.filter_edge_classes(|_| false).edge_count(), 4);
+
+        // (2, 0) is left without a class.
+        graph.set_edge_classes(vec![
+            (0, 1, EdgeClass::Road),
+            (1, 2, EdgeClass::Road),
+            (0, 2, EdgeClass::Footpath),
+        ]);
+        assert_eq!(graph.edge_class(0, 2), Some(EdgeClass::Footpath));
+        assert_eq!(graph.edge_class(2, 0), None);
+
+        let roads = graph.filter_edge_classes(|class| class == EdgeClass::Road);
+        assert_eq!(roads.node_count(), 3);
+        assert_eq!(roads.edge_count(), 2);
+        assert!(roads.out_neighbors(0).all(|target| target.target() == 1));
+        assert!(roads.in_neighbors(2).all(|target| target.target() == 1));
+        assert_eq!(roads.out_neighbors(2).count(), 0);
+        assert_eq!(roads.edge_class(0, 2), None);
+        assert_eq!(roads.edge_class(1, 2), Some(EdgeClass::Road));
+
+        graph.add_edge(2, 1, 1.);
+        graph.copy_edge_attributes((2, 1), (0, 2));
+        assert_eq!(graph.edge_class(2, 1), Some(EdgeClass::Footpath));
+        graph.copy_edge_attributes((1, 0), (0, 2));
+        assert_eq!(graph.edge_class(1, 0), None);
+
+        graph.remove_node(1);
+        assert_eq!(graph.edge_class(0, 1), Some(EdgeClass::Footpath));
+    }
+
     #[test]
     fn remove_node() {
         let mut graph = setup();
@@ -777,4 +2350,423 @@ mod tests {
 
         assert_eq!(graph, expected);
     }
+
+    #[test]
+    fn dijkstra_full_cached() {
+        let graph: DirectedCsrGraph<f64, ()> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.), (1, 2, 2.), (0, 2, 4.)]));
+
+        let (result, settled) = graph.cached_dijkstra(0, None, Direction::Outgoing);
+        assert_eq!(settled, 3);
+        assert_eq!(*result.get(2).unwrap().cost(), 3.);
+
+        let (cached, settled) = graph.cached_dijkstra(0, None, Direction::Outgoing);
+        assert_eq!(settled, 0);
+        assert_eq!(cached, result);
+
+        let (_, settled) =
+            graph.cached_dijkstra(0, Some(FxHashSet::from_iter([2])), Direction::Outgoing);
+        assert_eq!(settled, 0);
+    }
+
+    #[test]
+    fn dijkstra_frontiers() {
+        let edges = vec![(0, 1, 1.), (1, 2, 2.), (0, 2, 4.), (2, 3, 1.), (1, 3, 5.)];
+        let queue: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(edges.clone()));
+        let mut heap: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(edges));
+        heap.set_frontier(Frontier::BinaryHeap);
+
+        for direction in [Direction::Outgoing, Direction::Incoming] {
+            for start_node in 0..4 {
+                let queue = queue.dijkstra_full(start_node, direction);
+                let heap = heap.dijkstra_full(start_node, direction);
+                for node in 0..4 {
+                    assert_eq!(queue.cost(node), heap.cost(node));
+                }
+            }
+        }
+        assert_eq!(heap.dijkstra_full(0, Direction::Outgoing).cost(3), Some(4.));
+
+        let (result, settled) =
+            heap.cached_dijkstra(3, Some(FxHashSet::from_iter([0])), Direction::Incoming);
+        assert_eq!(settled, 0);
+        assert_eq!(result.cost(0), Some(4.));
+    }
+
+    #[test]
+    fn warm_cache() {
+        let edges = vec![(0, 1, 1.), (1, 2, 2.), (2, 3, 1.), (3, 0, 4.)];
+        let cold: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(edges.clone()));
+        let warm: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(edges));
+        warm.warm_cache(&[0, 2]);
+
+        let (cold_result, settled) =
+            cold.cached_dijkstra(0, Some(FxHashSet::from_iter([3])), Direction::Outgoing);
+        assert_eq!(settled, 4);
+
+        let (warm_result, settled) =
+            warm.cached_dijkstra(0, Some(FxHashSet::from_iter([3])), Direction::Outgoing);
+        assert_eq!(settled, 0);
+        assert_eq!(warm_result.cost(3), cold_result.cost(3));
+
+        let (_, settled) = warm.cached_dijkstra(2, None, Direction::Outgoing);
+        assert_eq!(settled, 0);
+    }
+
+    #[cfg(feature = "cache-snapshot")]
+    #[test]
+    fn save_load_cache() {
+        let edges = vec![(0, 1, 1.), (1, 2, 2.), (2, 3, 1.), (3, 0, 4.)];
+        let warm: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(edges.clone()));
+        warm.warm_cache(&[0, 2]);
+
+        let path = std::env::temp_dir().join(format!("graph_rs_cache_{}", std::process::id()));
+        warm.save_cache(&path).unwrap();
+
+        let mut loaded: DirectedCsrGraph<f64, ()> =
+            DirectedCsrGraph::from(EdgeList::new(edges.clone()));
+        loaded.load_cache(&path).unwrap();
+        for source in [0, 2] {
+            let (result, settled) = loaded.cached_dijkstra(source, None, Direction::Outgoing);
+            assert_eq!(settled, 0);
+            assert_eq!(
+                result,
+                warm.cached_dijkstra(source, None, Direction::Outgoing).0
+            );
+        }
+
+        let mut other: DirectedCsrGraph<f64, ()> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.), (1, 2, 2.), (2, 3, 1.)]));
+        assert!(matches!(
+            other.load_cache(&path),
+            Err(GraphError::CacheMismatch)
+        ));
+        let (_, settled) = other.cached_dijkstra(0, None, Direction::Outgoing);
+        assert_eq!(settled, 4);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn dijkstra_cache_evicts_oldest() {
+        let graph: DirectedCsrGraph<f64, ()> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.), (1, 2, 2.), (2, 0, 4.)]));
+        let graph = DirectedCsrGraph::with_cache_capacity(
+            graph.node_values,
+            graph.csr_out,
+            graph.csr_inc,
+            2,
+        );
+
+        for start in 0..3 {
+            graph.dijkstra_full(start, Direction::Outgoing);
+        }
+
+        let mut cache = graph.dijkstra_cache.lock();
+        let cache = cache.get_or_init();
+        assert_eq!(cache.cache_size(), 2);
+        assert!(cache.cache_get(&(0, Direction::Outgoing)).is_none());
+        assert!(cache.cache_get(&(1, Direction::Outgoing)).is_some());
+        assert!(cache.cache_get(&(2, Direction::Outgoing)).is_some());
+    }
+
+    #[test]
+    fn dijkstra_cache_capacity_large_graph() {
+        let node_count = 100_000;
+        let graph: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(
+            (0..node_count - 1).map(|i| (i, i + 1, 1.)).collect(),
+        ));
+        assert_eq!(graph.node_count(), node_count);
+        assert_eq!(
+            graph.dijkstra_cache.lock().get_or_init().cache_capacity(),
+            Some(DEFAULT_DIJKSTRA_CACHE_CAPACITY)
+        );
+
+        let graph = DirectedCsrGraph::with_cache_capacity(
+            graph.node_values,
+            graph.csr_out,
+            graph.csr_inc,
+            usize::MAX,
+        );
+        assert_eq!(
+            graph.dijkstra_cache.lock().get_or_init().cache_capacity(),
+            Some(MAX_DIJKSTRA_CACHE_CAPACITY)
+        );
+    }
+
+    #[test]
+    fn dijkstra_cache_lazy_after_deserialize() {
+        let node_count = 10_000;
+        let graph: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(
+            (0..node_count - 1).map(|i| (i, i + 1, 1.)).collect(),
+        ));
+        let json = serde_json::to_string(&graph).unwrap();
+
+        let graph: DirectedCsrGraph<f64, ()> = serde_json::from_str(&json).unwrap();
+        assert_eq!(graph.node_count(), node_count);
+        assert!(graph.dijkstra_cache.lock().cache.is_none());
+
+        graph.dijkstra(0, FxHashSet::from_iter([1]), Direction::Outgoing);
+        let cache = graph.dijkstra_cache.lock();
+        assert_eq!(
+            cache
+                .cache
+                .as_ref()
+                .and_then(|cache| cache.cache_capacity()),
+            Some(DEFAULT_DIJKSTRA_CACHE_CAPACITY)
+        );
+        assert_eq!(
+            cache.cache.as_ref().map(|cache| cache.cache_size()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn undirected_from_edgelist() {
+        let graph: UndirectedCsrGraph<i32, ()> = UndirectedCsrGraph::from(EdgeList::new(vec![
+            (0, 1, 1),
+            (1, 0, 1),
+            (1, 2, 2),
+            (3, 1, 3),
+        ]));
+
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 3);
+
+        let mut neighbors: Vec<_> = graph.neighbors(1).map(|t| t.target()).collect();
+        neighbors.sort();
+        assert_eq!(neighbors, vec![0, 2, 3]);
+        assert_eq!(
+            graph.in_neighbors(3).collect::<Vec<_>>(),
+            graph.out_neighbors(3).collect::<Vec<_>>()
+        );
+
+        let mut edges: Vec<_> = graph.edges().collect();
+        edges.sort();
+        assert_eq!(edges, vec![(0, 1), (1, 2), (1, 3)]);
+    }
+
+    #[test]
+    fn undirected_add_remove_edge() {
+        let mut graph: UndirectedCsrGraph<i32, ()> =
+            UndirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1), (1, 2, 2)]));
+
+        assert!(graph.add_edge(2, 0, 3));
+        assert!(!graph.add_edge(0, 2, 3));
+        assert_eq!(graph.degree(0), 2);
+
+        assert_eq!(graph.remove_edge((1, 0)), Some(1));
+        assert_eq!(graph.degree(0), 1);
+        assert_eq!(graph.degree(1), 1);
+        assert_eq!(graph.edge_count(), 2);
+    }
+
+    #[test]
+    fn undirected_from_directed() {
+        let directed: DirectedCsrGraph<i32, ()> = DirectedCsrGraph::from(EdgeList::new(vec![
+            (0, 1, 1),
+            (1, 0, 1),
+            (1, 2, 2),
+            (2, 1, 2),
+        ]));
+
+        let graph = UndirectedCsrGraph::from(directed);
+
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(graph.degree(1), 2);
+    }
+
+    #[test]
+    fn undirected_self_loop() {
+        let mut graph: UndirectedCsrGraph<i32, ()> =
+            UndirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1), (1, 1, 2)]));
+
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(graph.degree(1), 2);
+
+        assert!(graph.add_edge(0, 0, 3));
+        assert_eq!(graph.edge_count(), 3);
+        assert_eq!(graph.remove_edge((1, 1)), Some(2));
+        assert_eq!(graph.edge_count(), 2);
+        assert_eq!(graph.remove_node(0), Some(()));
+        assert_eq!(graph.edge_count(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn undirected_from_asymmetric_directed() {
+        let directed: DirectedCsrGraph<i32, ()> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1), (1, 0, 1), (1, 2, 2)]));
+
+        let _ = UndirectedCsrGraph::from(directed);
+    }
+
+    #[test]
+    fn dijkstra_with_turns_forbidden_turn() {
+        //  0 -> 1 -> 2
+        //  |         ^
+        //  +--> 3 ---+
+        let graph: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(vec![
+            (0, 1, 1.),
+            (1, 2, 1.),
+            (0, 3, 2.),
+            (3, 2, 2.),
+        ]));
+
+        let path = graph.dijkstra_with_turns(0, 2, |_, _, _| 0.).unwrap();
+        assert_eq!(
+            path.path.iter().map(|t| t.target()).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(path.cost(), 2.);
+
+        let path = graph
+            .dijkstra_with_turns(0, 2, |prev_edge, _, next_edge| {
+                if prev_edge == (0, 1) && next_edge == (1, 2) {
+                    f64::INFINITY
+                } else {
+                    0.
+                }
+            })
+            .unwrap();
+        assert_eq!(
+            path.path.iter().map(|t| t.target()).collect::<Vec<_>>(),
+            vec![0, 3, 2]
+        );
+        assert_eq!(path.cost(), 4.);
+    }
+
+    #[test]
+    fn validate() {
+        let graph = setup();
+        assert!(graph.validate().is_ok());
+
+        let mut graph = setup();
+        graph.csr_out.offsets.swap(2, 3);
+        assert!(matches!(
+            graph.validate(),
+            Err(GraphError::InvalidOffsets(3))
+        ));
+
+        let mut graph = setup();
+        graph.csr_inc.offsets[0] = 1;
+        assert!(matches!(
+            graph.validate(),
+            Err(GraphError::InvalidOffsets(0))
+        ));
+
+        let mut graph = setup();
+        graph.csr_out.targets[0] = Target::new(31, 0);
+        assert!(matches!(
+            graph.validate(),
+            Err(GraphError::TargetOutOfRange {
+                node: 0,
+                target: 31
+            })
+        ));
+
+        let mut graph = setup();
+        graph.node_values.pop();
+        assert!(matches!(
+            graph.validate(),
+            Err(GraphError::NodeCountMismatch {
+                expected: 30,
+                found: 31
+            })
+        ));
+    }
+
+    #[test]
+    fn reserve() {
+        let mut graph: DirectedCsrGraph<f64, ()> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.), (1, 2, 1.)]));
+        let node_count = graph.node_count();
+        let edge_count = graph.edge_count();
+
+        graph.reserve(100, 200);
+
+        assert_eq!(graph.node_count(), node_count);
+        assert_eq!(graph.edge_count(), edge_count);
+        assert!(graph.node_values.capacity() >= node_count + 100);
+        for csr in [&graph.csr_out, &graph.csr_inc] {
+            assert!(csr.offsets.capacity() >= node_count + 101);
+            assert!(csr.targets.capacity() >= edge_count + 200);
+        }
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let mut graph: DirectedCsrGraph<f64, usize> = DirectedCsrGraph::with_capacity(100, 200);
+        for node in 0..3 {
+            graph.add_node(node);
+        }
+        graph.add_edge(0, 1, 1.);
+        graph.add_edge(1, 2, 1.);
+        graph.dijkstra_full(0, Direction::Outgoing);
+
+        graph.shrink_to_fit();
+
+        assert_eq!(graph.node_values.capacity(), graph.node_values.len());
+        for csr in [&graph.csr_out, &graph.csr_inc] {
+            assert_eq!(csr.offsets.capacity(), csr.offsets.len());
+            assert_eq!(csr.targets.capacity(), csr.targets.len());
+        }
+        assert!(graph.dijkstra_cache.lock().cache.is_none());
+        assert_eq!(
+            graph.dijkstra_full(0, Direction::Outgoing).cost(2),
+            Some(2.)
+        );
+    }
+
+    #[test]
+    fn clear() {
+        let mut graph: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::with_capacity(3, 2);
+        for _ in 0..3 {
+            graph.add_node(());
+        }
+        graph.add_edge(0, 1, 1.);
+        graph.add_edge(1, 2, 1.);
+        graph.dijkstra_full(0, Direction::Outgoing);
+
+        graph.clear();
+
+        assert_eq!(graph.node_count(), 0);
+        assert_eq!(graph.edge_count(), 0);
+        assert_eq!(graph.dijkstra_cache.lock().get_or_init().cache_size(), 0);
+        assert_eq!(graph, DirectedCsrGraph::default());
+
+        graph.add_node(());
+        graph.add_node(());
+        graph.add_edge(1, 0, 2.);
+
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.edge_count(), 1);
+        assert_eq!(graph.in_neighbors(0).next(), Some(&Target::new(1, 2.)));
+    }
+
+    #[test]
+    fn from_edges_and_values() {
+        let edges = vec![(0, 1, 1.), (1, 2, 2.), (2, 0, 3.)];
+        let graph = DirectedCsrGraph::from_edges_and_values(
+            edges.iter().copied(),
+            vec!["a", "b", "c", "d"],
+        );
+
+        assert_eq!(graph.node_count(), 4);
+        assert_eq!(graph.edge_count(), 3);
+        assert_eq!(graph.node_value(3), Some(&"d"));
+        assert_eq!(graph.out_neighbors(1).next(), Some(&Target::new(2, 2.)));
+        assert_eq!(graph.in_neighbors(0).next(), Some(&Target::new(2, 3.)));
+
+        // Without the isolated node 3 the CSR matches the one built from the edge list.
+        let graph =
+            DirectedCsrGraph::from_edges_and_values(edges.iter().copied(), vec!["a", "b", "c"]);
+        let expected: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(edges));
+        assert_eq!(graph.csr_out, expected.csr_out);
+        assert_eq!(graph.csr_inc, expected.csr_inc);
+
+        let empty: DirectedCsrGraph<f64, ()> =
+            DirectedCsrGraph::from_edges_and_values(Vec::new(), Vec::new());
+        assert_eq!(empty, DirectedCsrGraph::default());
+    }
 }
@@ -0,0 +1,155 @@
+use std::fmt::Display;
+
+use geo::{Coord, CoordFloat};
+use ordered_float::{FloatCore, OrderedFloat};
+use rustc_hash::FxHashSet;
+
+use crate::{CoordGraph, DirectedGraph};
+
+type Key<C> = (OrderedFloat<C>, OrderedFloat<C>);
+type EdgeKey<C> = (Key<C>, Key<C>);
+
+/// Nodes and edges which are only in one of two graphs, see [diff].
+///
+/// Nodes are identified by their coordinate and edges by the coordinates of their
+/// endpoints, as node ids are not stable between imports. Edge values are not compared.
+#[derive(Debug, PartialEq)]
+pub struct GraphDiff<C>
+where
+    C: CoordFloat,
+{
+    pub nodes_added: Vec<Coord<C>>,
+    pub nodes_removed: Vec<Coord<C>>,
+    pub edges_added: Vec<(Coord<C>, Coord<C>)>,
+    pub edges_removed: Vec<(Coord<C>, Coord<C>)>,
+}
+
+impl<C> GraphDiff<C>
+where
+    C: CoordFloat,
+{
+    /// Returns `true` if both graphs have the same nodes and edges.
+    pub fn is_empty(&self) -> bool {
+        self.nodes_added.is_empty()
+            && self.nodes_removed.is_empty()
+            && self.edges_added.is_empty()
+            && self.edges_removed.is_empty()
+    }
+}
+
+impl<C> Display for GraphDiff<C>
+where
+    C: CoordFloat,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "nodes: +{} -{}, edges: +{} -{}",
+            self.nodes_added.len(),
+            self.nodes_removed.len(),
+            self.edges_added.len(),
+            self.edges_removed.len()
+        )
+    }
+}
+
+/// Compares graph `a` to graph `b`.
+///
+/// Added nodes and edges are in `b` but not in `a`, removed ones the other way round.
+/// All lists are sorted by coordinate.
+pub fn diff<GA, GB, C>(a: &GA, b: &GB) -> GraphDiff<C>
+where
+    GA: CoordGraph<C = C> + DirectedGraph,
+    GB: CoordGraph<C = C> + DirectedGraph,
+    C: CoordFloat + FloatCore,
+{
+    let (nodes_a, edges_a) = coord_sets(a);
+    let (nodes_b, edges_b) = coord_sets(b);
+
+    let edge = |e: EdgeKey<C>| (to_coord(e.0), to_coord(e.1));
+
+    GraphDiff {
+        nodes_added: sorted(nodes_b.difference(&nodes_a)).map(to_coord).collect(),
+        nodes_removed: sorted(nodes_a.difference(&nodes_b)).map(to_coord).collect(),
+        edges_added: sorted(edges_b.difference(&edges_a)).map(edge).collect(),
+        edges_removed: sorted(edges_a.difference(&edges_b)).map(edge).collect(),
+    }
+}
+
+fn coord_sets<G, C>(graph: &G) -> (FxHashSet<Key<C>>, FxHashSet<EdgeKey<C>>)
+where
+    G: CoordGraph<C = C> + DirectedGraph,
+    C: CoordFloat + FloatCore,
+{
+    let key = |node| {
+        graph
+            .node_coord(node)
+            .map(|coord| (OrderedFloat(coord.x), OrderedFloat(coord.y)))
+    };
+
+    let nodes = graph
+        .nodes_iter()
+        .filter_map(|(node, _)| key(node))
+        .collect();
+    let edges = graph
+        .nodes_iter()
+        .flat_map(|(node, _)| graph.out_neighbors(node).map(move |t| (node, t.target())))
+        .filter_map(|(s, t)| Some((key(s)?, key(t)?)))
+        .collect();
+
+    (nodes, edges)
+}
+
+fn to_coord<C: CoordFloat>(key: Key<C>) -> Coord<C> {
+    Coord {
+        x: key.0.0,
+        y: key.1.0,
+    }
+}
+
+fn sorted<'a, T: Ord + Copy + 'a>(iter: impl Iterator<Item = &'a T>) -> impl Iterator<Item = T> {
+    let mut items: Vec<T> = iter.copied().collect();
+    items.sort();
+    items.into_iter()
+}
+
+#[cfg(all(test, feature = "rstar"))]
+mod test {
+    use geo::Coord;
+
+    use crate::graph::{csr::DirectedCsrGraph, rstar::RTreeGraph};
+
+    use super::diff;
+
+    #[test]
+    fn one_edge_differs() {
+        let coords = vec![
+            Coord { x: 0., y: 0. },
+            Coord { x: 1., y: 0. },
+            Coord { x: 1., y: 1. },
+        ];
+        let a: DirectedCsrGraph<f64, Coord<f64>> =
+            DirectedCsrGraph::from_edges_and_values(vec![(0, 1, 1.), (1, 2, 1.)], coords.clone());
+        // Same graph with the nodes in a different order and one more edge.
+        let b: DirectedCsrGraph<f64, Coord<f64>> = DirectedCsrGraph::from_edges_and_values(
+            vec![(2, 1, 1.), (1, 0, 1.), (0, 2, 1.)],
+            coords.into_iter().rev().collect(),
+        );
+        let (a, b) = (RTreeGraph::new_from_graph(a), RTreeGraph::new_from_graph(b));
+
+        let d = diff(&a, &b);
+
+        assert!(d.nodes_added.is_empty());
+        assert!(d.nodes_removed.is_empty());
+        assert_eq!(
+            d.edges_added,
+            vec![(Coord { x: 1., y: 1. }, Coord { x: 0., y: 0. })]
+        );
+        assert!(d.edges_removed.is_empty());
+        assert_eq!(d.to_string(), "nodes: +0 -0, edges: +1 -0");
+
+        let d = diff(&b, &a);
+        assert_eq!(d.edges_removed.len(), 1);
+        assert!(diff(&a, &a).is_empty());
+    }
+}
@@ -3,15 +3,21 @@ use std::{
     hash::Hash,
 };
 
-use geo::LineString;
-use num_traits::Num;
+use geo::{Coord, CoordFloat, HaversineIntermediate, LineString, Point};
+use num_traits::{FromPrimitive, Num};
 use serde::{Deserialize, Serialize};
 
 use crate::{CoordGraph, EdgeTrait, NodeTrait};
 
 pub mod csr;
+pub mod diff;
 pub mod node;
+#[cfg(feature = "rstar")]
 pub mod rstar;
+#[cfg(feature = "time-dependent")]
+pub mod time_dependent;
+
+pub use diff::{GraphDiff, diff};
 
 #[derive(Clone, Copy, Debug)]
 pub struct Node<NV> {
@@ -136,6 +142,37 @@ impl<EV> Path<EV> {
 
         Some(LineString::new(coords))
     }
+
+    /// Like [Path::line_string], but splits the line between two nodes into
+    /// `segments_per_edge` segments along the great circle, with node coordinates as longitude
+    /// and latitude.
+    ///
+    /// With one segment per edge this is the same as [Path::line_string].
+    pub fn line_string_interpolated<G: CoordGraph>(
+        &self,
+        graph: &G,
+        segments_per_edge: usize,
+    ) -> Option<LineString<G::C>>
+    where
+        G::C: CoordFloat + FromPrimitive,
+    {
+        let mut coords: Vec<Coord<G::C>> = Vec::new();
+        let segments = G::C::from_usize(segments_per_edge)?;
+
+        for node in self.path.iter() {
+            let coord = graph.node_coord(node.target())?;
+            if let Some(&last) = coords.last() {
+                let (a, b) = (Point::from(last), Point::from(coord));
+                for i in 1..segments_per_edge {
+                    let fraction = G::C::from_usize(i)? / segments;
+                    coords.push(a.haversine_intermediate(&b, fraction).into());
+                }
+            }
+            coords.push(coord);
+        }
+
+        Some(LineString::new(coords))
+    }
 }
 
 impl<EV: Num + Copy> Path<EV> {
@@ -162,6 +199,10 @@ impl<EV> Target<EV> {
     pub fn value(&self) -> &EV {
         &self.value
     }
+
+    pub fn set_value(&mut self, value: EV) {
+        self.value = value;
+    }
 }
 
 impl<EV> Hash for Target<EV> {
@@ -206,3 +247,57 @@ where
         }
     }
 }
+
+#[cfg(all(test, feature = "rstar"))]
+mod test {
+    use geo::Coord;
+
+    use crate::graph::{csr::DirectedCsrGraph, rstar::RTreeGraph};
+
+    use super::{Path, Target};
+
+    /// Unit vector of a longitude and latitude in degrees.
+    fn unit_vector(coord: Coord<f64>) -> [f64; 3] {
+        let (lon, lat) = (coord.x.to_radians(), coord.y.to_radians());
+        [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+    }
+
+    #[test]
+    fn line_string_interpolated() {
+        let coords = vec![Coord { x: 0., y: 60. }, Coord { x: 90., y: 60. }];
+        let graph: DirectedCsrGraph<f64, Coord<f64>> =
+            DirectedCsrGraph::from_edges_and_values(vec![(0, 1, 1.)], coords.clone());
+        let graph = RTreeGraph::new_from_graph(graph);
+        let path = Path::new(vec![Target::new(0, 0.), Target::new(1, 1.)]);
+
+        assert_eq!(
+            path.line_string_interpolated(&graph, 1),
+            path.line_string(&graph)
+        );
+
+        let line_string = path.line_string_interpolated(&graph, 4).unwrap();
+        assert_eq!(line_string.0.len(), 5);
+        assert_eq!(line_string.0.first(), coords.first());
+        assert_eq!(line_string.0.last(), coords.last());
+
+        // Interior points lie in the plane of the great circle through both nodes, which
+        // bends towards the pole instead of following the parallel.
+        let (a, b) = (unit_vector(coords[0]), unit_vector(coords[1]));
+        let normal = [
+            a[1] * b[2] - a[2] * b[1],
+            a[2] * b[0] - a[0] * b[2],
+            a[0] * b[1] - a[1] * b[0],
+        ];
+        for &coord in &line_string.0[1..4] {
+            let p = unit_vector(coord);
+            let distance = normal[0] * p[0] + normal[1] * p[1] + normal[2] * p[2];
+            assert!(
+                distance.abs() < 1e-9,
+                "{coord:?} is not on the great circle"
+            );
+            assert!(coord.y > 60.);
+        }
+        assert!(line_string.0[1].x < line_string.0[2].x);
+        assert!(line_string.0[2].x < line_string.0[3].x);
+    }
+}
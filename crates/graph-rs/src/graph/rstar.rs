@@ -1,17 +1,42 @@
-use std::{collections::HashSet, fmt::Debug};
+use std::{
+    collections::HashSet,
+    f64::consts::{FRAC_PI_2, PI},
+    fmt::Debug,
+    sync::OnceLock,
+};
 
-use geo::{BoundingRect, Coord, CoordFloat, MultiPoint, Point, Rect};
+use geo::{
+    Closest, Coord, CoordFloat, HaversineClosestPoint, HaversineDistance, Line, Point, Rect,
+};
 use log::info;
 use ordered_float::{FloatCore, OrderedFloat};
 use rstar::{
-    AABB, RTree, RTreeNum, RTreeObject, iterators::LocateInEnvelope, primitives::GeomWithData,
+    AABB, RTree, RTreeNode, RTreeNum, RTreeObject, iterators::LocateInEnvelope,
+    primitives::GeomWithData,
 };
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    CoordGraph, Coordinate, DirectedGraph, Graph, algorithms::dijkstra::Dijkstra, types::Direction,
+    CoordGraph, Coordinate, DirectedGraph, Graph, GraphError,
+    algorithms::dijkstra::{Dijkstra, Weight},
+    graph::csr::DirectedCsrGraph,
+    types::Direction,
 };
 
+/// Shape of the r-tree of a [RTreeGraph], see [RTreeGraph::tree_stats].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TreeStats {
+    /// Inner nodes of the tree, including the root.
+    pub nodes: usize,
+    /// Levels of inner nodes above the graph nodes, `1` if the root holds all of them.
+    pub depth: usize,
+    /// Average number of graph nodes in the inner nodes which hold graph nodes.
+    pub leaf_occupancy: f64,
+}
+
+/// Edges as lines, tagged with their `(from, to)` nodes.
+type EdgeTree<C> = RTree<GeomWithData<Line<C>, (usize, usize)>>;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct RTreeGraph<G, C>
 where
@@ -23,6 +48,11 @@ where
     graph: G,
 
     r_tree: Box<RTree<GeomWithData<Coord<C>, usize>>>,
+
+    /// Edges as lines, built on the first [CoordGraph::nearest_edge] and dropped when edges
+    /// or coordinates change.
+    #[serde(skip)]
+    edge_tree: OnceLock<EdgeTree<C>>,
 }
 
 impl<G, C> RTreeGraph<G, C>
@@ -44,7 +74,36 @@ where
 
         info!("Created r-tree: {} elements", r_tree.size());
 
-        Self { graph, r_tree }
+        Self {
+            graph,
+            r_tree,
+            edge_tree: OnceLock::new(),
+        }
+    }
+
+    /// Like [RTreeGraph::new_from_graph], but fails if a node of `graph` has no value instead
+    /// of leaving it out of the r-tree.
+    pub fn try_new_from_graph(graph: G) -> Result<Self, GraphError> {
+        info!("Creating r-tree for graph...");
+
+        let r_tree = Box::new(RTree::bulk_load(
+            (0..graph.node_count())
+                .map(|node| {
+                    Ok(GeomWithData::new(
+                        graph.try_node_value(node)?.as_coord(),
+                        node,
+                    ))
+                })
+                .collect::<Result<_, GraphError>>()?,
+        ));
+
+        info!("Created r-tree: {} elements", r_tree.size());
+
+        Ok(Self {
+            graph,
+            r_tree,
+            edge_tree: OnceLock::new(),
+        })
     }
 
     /// Returns the underlying graph data structure.
@@ -52,12 +111,49 @@ where
         &self.graph
     }
 
+    /// Drops the r-trees and returns the underlying graph data structure.
+    pub fn into_graph(self) -> G {
+        self.graph
+    }
+
     pub fn query(
         &self,
         envelope: &<GeomWithData<Coord<C>, usize> as RTreeObject>::Envelope,
     ) -> LocateInEnvelope<'_, GeomWithData<Coord<C>, usize>> {
         self.r_tree.locate_in_envelope(envelope)
     }
+
+    /// Walks the r-tree to count its inner nodes, depth and fill.
+    ///
+    /// The r-tree is balanced, so its depth only grows with the number of graph nodes and not
+    /// with how they are clustered.
+    pub fn tree_stats(&self) -> TreeStats {
+        let mut stats = TreeStats::default();
+        let (mut leaf_parents, mut leaves) = (0, 0);
+
+        let mut stack = vec![(self.r_tree.root(), 1)];
+        while let Some((node, depth)) = stack.pop() {
+            stats.nodes += 1;
+            stats.depth = stats.depth.max(depth);
+
+            let mut node_leaves = 0;
+            for child in node.children() {
+                match child {
+                    RTreeNode::Leaf(_) => node_leaves += 1,
+                    RTreeNode::Parent(parent) => stack.push((parent, depth + 1)),
+                }
+            }
+            if node_leaves > 0 {
+                leaf_parents += 1;
+                leaves += node_leaves;
+            }
+        }
+
+        if leaf_parents > 0 {
+            stats.leaf_occupancy = leaves as f64 / leaf_parents as f64;
+        }
+        stats
+    }
 }
 
 impl<G, C> RTreeGraph<G, C>
@@ -93,6 +189,18 @@ where
     }
 }
 
+impl<EV, NV, C> RTreeGraph<DirectedCsrGraph<EV, NV>, C>
+where
+    NV: Coordinate<C>,
+    EV: Debug + Default + Clone,
+    C: RTreeNum + CoordFloat,
+{
+    /// See [DirectedCsrGraph::copy_edge_attributes].
+    pub fn copy_edge_attributes(&mut self, edge: (usize, usize), from: (usize, usize)) {
+        self.graph.copy_edge_attributes(edge, from);
+    }
+}
+
 impl<G, C> Default for RTreeGraph<G, C>
 where
     G: Graph,
@@ -150,6 +258,7 @@ where
     }
 
     fn node_value_mut(&mut self, node: usize) -> Option<&mut Self::NV> {
+        self.edge_tree.take();
         self.graph.node_value_mut(node)
     }
 
@@ -164,8 +273,19 @@ where
         self.graph.nodes_iter()
     }
 
+    /// Unlike [Graph::node_value_mut], this moves the node in the r-tree to its new coordinate.
     fn set_node_value(&mut self, node: usize, value: Self::NV) -> Result<(), crate::GraphError> {
-        self.graph.set_node_value(node, value)
+        self.edge_tree.take();
+        let old_coord = self.graph.node_value(node).map(|value| value.as_coord());
+        let coord = value.as_coord();
+        self.graph.set_node_value(node, value)?;
+
+        if let Some(old_coord) = old_coord {
+            self.r_tree.remove(&GeomWithData::new(old_coord, node));
+        }
+        self.r_tree.insert(GeomWithData::new(coord, node));
+
+        Ok(())
     }
 
     fn add_node(&mut self, weight: Self::NV) -> usize {
@@ -178,14 +298,27 @@ where
     }
 
     fn add_edge(&mut self, a: usize, b: usize, weight: Self::EV) -> bool {
+        self.edge_tree.take();
         self.graph.add_edge(a, b, weight)
     }
 
     fn remove_node(&mut self, node: usize) -> Option<Self::NV> {
-        self.graph.remove_node(node)
+        let value = self.graph.remove_node(node)?;
+        self.edge_tree.take();
+
+        // All following node ids shift down by one, so the index is rebuilt.
+        *self.r_tree = RTree::bulk_load(
+            self.graph
+                .nodes_iter()
+                .map(|n| GeomWithData::new(n.1.as_coord(), n.0))
+                .collect(),
+        );
+
+        Some(value)
     }
 
     fn remove_edge(&mut self, edge: (usize, usize)) -> Option<Self::EV> {
+        self.edge_tree.take();
         self.graph.remove_edge(edge)
     }
 }
@@ -233,18 +366,31 @@ where
         self.graph.node_value(node).map(|c| c.as_coord())
     }
 
+    /// Returns the node with the smallest haversine distance to `point`.
+    ///
+    /// The r-tree compares longitude and latitude as euclidean coordinates, which distorts
+    /// distances away from the equator. Its nearest node only bounds the search.
     fn nearest_node(&self, point: &Coord<C>) -> Option<usize> {
-        self.r_tree.nearest_neighbor(point).map(|n| n.data)
-    }
-    fn nearest_node_bound(&self, coord: &Coord<C>, tolerance: C) -> Option<usize> {
-        info!("Searching neighbour for: {:?}", coord);
-        let mut neighbors = self.r_tree.nearest_neighbor_iter_with_distance_2(coord);
+        let candidate = self.r_tree.nearest_neighbor(point)?;
 
-        let neighbor_bound = neighbors.find(|node| node.1 <= tolerance);
+        let center = to_point(point);
+        let distance = center.haversine_distance(&to_point(candidate.geom()));
+        let envelope = haversine_envelope(center, distance);
+        let envelope = AABB::from_corners(from_point(envelope.min()), from_point(envelope.max()));
 
-        info!("Found points");
+        std::iter::once(candidate)
+            .chain(self.r_tree.locate_in_envelope(&envelope))
+            .map(|node| (node.data, center.haversine_distance(&to_point(node.geom()))))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|node| node.0)
+    }
 
-        neighbor_bound.map(|n| n.0.data)
+    /// Like [CoordGraph::nearest_node], but only if the node is at most `tolerance` meters away.
+    fn nearest_node_bound(&self, coord: &Coord<C>, tolerance: C) -> Option<usize> {
+        let node = self.nearest_node(coord)?;
+        let distance = to_point(coord).haversine_distance(&to_point(&self.node_coord(node)?));
+
+        (distance <= tolerance.to_f64()?).then_some(node)
     }
 
     fn locate_in_envelope(&self, envelope: &Rect<Self::C>) -> impl Iterator<Item = usize> {
@@ -252,23 +398,108 @@ where
         self.r_tree.locate_in_envelope(&envelope).map(|e| e.data)
     }
 
+    /// Returns the envelope of the r-tree, which rstar keeps up to date as nodes are added or
+    /// moved, so no node is visited.
     fn bounding_rect(&self) -> Option<Rect<C>> {
-        let points = MultiPoint::new(
-            self.r_tree
-                .iter()
-                .map(|geom| Point::from(*geom.geom()))
-                .collect(),
+        if self.r_tree.size() == 0 {
+            return None;
+        }
+
+        let envelope = self.r_tree.root().envelope();
+        Some(Rect::new(envelope.lower(), envelope.upper()))
+    }
+
+    /// Edges are compared by haversine distance and `tolerance` is in meters, like
+    /// [CoordGraph::nearest_node_bound].
+    fn nearest_edge(&self, point: &Coord<C>, tolerance: C) -> Option<(usize, usize, Coord<C>)> {
+        let edge_tree = self.edge_tree.get_or_init(|| {
+            RTree::bulk_load(
+                self.graph
+                    .edges()
+                    .filter_map(|(a, b)| {
+                        let line = Line::new(self.node_coord(a)?, self.node_coord(b)?);
+                        Some(GeomWithData::new(line, (a, b)))
+                    })
+                    .collect(),
+            )
+        });
+
+        let center = to_point(point);
+        let tolerance = tolerance.to_f64()?;
+        let envelope = haversine_envelope(center, tolerance);
+        let envelope = AABB::from_corners(
+            Point::from(from_point::<C>(envelope.min())),
+            Point::from(from_point::<C>(envelope.max())),
         );
 
-        points.bounding_rect()
+        edge_tree
+            .locate_in_envelope_intersecting(&envelope)
+            .map(|edge| {
+                let line = Line::new(to_point(&edge.geom().start), to_point(&edge.geom().end));
+                let closest = match line.haversine_closest_point(&center) {
+                    Closest::Intersection(closest) | Closest::SinglePoint(closest) => closest,
+                    Closest::Indeterminate => line.start_point(),
+                };
+                (edge.data, closest, center.haversine_distance(&closest))
+            })
+            .filter(|(_, _, distance)| *distance <= tolerance)
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|((a, b), closest, _)| (a, b, from_point(closest.0)))
     }
 }
 
+/// Mean earth radius in meters, as used by [HaversineDistance].
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+fn to_point<C: CoordFloat>(coord: &Coord<C>) -> Point<f64> {
+    Point::new(
+        coord.x.to_f64().unwrap_or(f64::NAN),
+        coord.y.to_f64().unwrap_or(f64::NAN),
+    )
+}
+
+fn from_point<C: CoordFloat>(coord: Coord<f64>) -> Coord<C> {
+    Coord {
+        x: C::from(coord.x).unwrap_or_else(C::nan),
+        y: C::from(coord.y).unwrap_or_else(C::nan),
+    }
+}
+
+/// Bounding box in degrees of all points within `distance` meters of `center`.
+pub fn haversine_envelope(center: Point<f64>, distance: f64) -> Rect<f64> {
+    let radius = distance / EARTH_RADIUS;
+    let (lon, lat) = (center.x().to_radians(), center.y().to_radians());
+    let (lat_min, lat_max) = (lat - radius, lat + radius);
+
+    // Near a pole every longitude may be in range.
+    let (lon_min, lon_max) = if lat_min > -FRAC_PI_2 && lat_max < FRAC_PI_2 {
+        let delta_lon = (radius.sin() / lat.cos()).asin();
+        if lon - delta_lon < -PI || lon + delta_lon > PI {
+            (-PI, PI)
+        } else {
+            (lon - delta_lon, lon + delta_lon)
+        }
+    } else {
+        (-PI, PI)
+    };
+
+    Rect::new(
+        Coord {
+            x: lon_min.to_degrees(),
+            y: lat_min.max(-FRAC_PI_2).to_degrees(),
+        },
+        Coord {
+            x: lon_max.to_degrees(),
+            y: lat_max.min(FRAC_PI_2).to_degrees(),
+        },
+    )
+}
+
 impl<G, C> Dijkstra for RTreeGraph<G, C>
 where
     G: DirectedGraph + Dijkstra,
     G::NV: Coordinate<C>,
-    G::EV: FloatCore + Debug + Default + Clone,
+    G::EV: FloatCore + Weight + Debug + Default + Clone,
     C: RTreeNum + CoordFloat,
 {
     fn dijkstra(
@@ -294,15 +525,19 @@ mod test {
     use std::{collections::HashMap, fs::File, io::BufReader};
 
     use approx::assert_relative_eq;
-    use geo::{HaversineDestination, Point};
+    use geo::{Coord, HaversineDestination, Point};
     use geozero::geojson::read_geojson;
 
     use crate::{
-        CoordGraph, Coordinate, Graph,
-        input::geo_zero::{ColumnValueClonable, GraphWriter},
+        CoordGraph, Coordinate, Graph, GraphError,
+        graph::csr::DirectedCsrGraph,
+        input::{
+            edgelist::EdgeList,
+            geo_zero::{ColumnValueClonable, GraphWriter},
+        },
     };
 
-    use super::RTreeGraph;
+    use super::{RTreeGraph, TreeStats};
 
     #[test]
     fn nearest_neighbour_search() {
@@ -422,4 +657,268 @@ mod test {
             epsilon = 1e-6
         );
     }
+
+    #[test]
+    fn point_and_tuple_nodes() {
+        let edges = vec![(0, 1, 1.), (1, 2, 1.)];
+
+        let mut graph: DirectedCsrGraph<f64, Point<f64>> =
+            DirectedCsrGraph::from(EdgeList::new(edges.clone()));
+        graph.node_values = vec![Point::new(0., 0.), Point::new(1., 0.), Point::new(2., 0.)];
+        let graph = RTreeGraph::new_from_graph(graph);
+
+        assert_eq!(graph.nearest_node(&Coord { x: 1.1, y: 0.2 }), Some(1));
+
+        let mut graph: DirectedCsrGraph<f64, (f64, f64)> =
+            DirectedCsrGraph::from(EdgeList::new(edges));
+        graph.node_values = vec![(0., 0.), (1., 0.), (2., 0.)];
+        let graph = RTreeGraph::new_from_graph(graph);
+
+        assert_eq!(graph.nearest_node(&Coord { x: 1.9, y: -0.2 }), Some(2));
+        assert_eq!(graph.node_coord(0), Some(Coord { x: 0., y: 0. }));
+    }
+
+    #[test]
+    fn nearest_node_high_latitude() {
+        let edges = vec![(0, 1, 1.)];
+
+        let mut graph: DirectedCsrGraph<f64, (f64, f64)> =
+            DirectedCsrGraph::from(EdgeList::new(edges));
+        // Node 0 is closer in degrees, but node 1 is closer in meters at this latitude.
+        graph.node_values = vec![(0., 79.5), (2., 80.)];
+        let graph = RTreeGraph::new_from_graph(graph);
+
+        assert_eq!(graph.nearest_node(&Coord { x: 0., y: 80. }), Some(1));
+
+        // Node 1 is about 39 km away, node 0 about 56 km.
+        let point = Coord { x: 0., y: 80. };
+        assert_eq!(graph.nearest_node_bound(&point, 40_000.), Some(1));
+        assert_eq!(graph.nearest_node_bound(&point, 30_000.), None);
+    }
+
+    #[test]
+    fn missing_node_values() {
+        let mut graph: DirectedCsrGraph<f64, (f64, f64)> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.), (1, 2, 1.)]));
+        graph.node_values = vec![(0., 0.), (1., 0.)];
+
+        assert!(matches!(
+            graph.try_node_value(2),
+            Err(GraphError::NodeNotFound(2))
+        ));
+        assert!(graph.try_neighbors(2).is_ok());
+        assert!(matches!(
+            graph.try_neighbors(3).map(|n| n.count()),
+            Err(GraphError::NodeNotFound(3))
+        ));
+
+        assert!(matches!(
+            RTreeGraph::try_new_from_graph(graph),
+            Err(GraphError::NodeNotFound(2))
+        ));
+    }
+
+    #[test]
+    fn edge_midpoint() {
+        let mut graph: DirectedCsrGraph<f64, (f64, f64)> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.), (1, 2, 1.)]));
+        graph.node_values = vec![(0., 0.), (2., 0.), (2., 60.)];
+        let graph = RTreeGraph::new_from_graph(graph);
+
+        let midpoint = graph.edge_midpoint(0, 1).unwrap();
+        assert_relative_eq!(midpoint.x, 1., epsilon = 1e-9);
+        assert_relative_eq!(midpoint.y, 0., epsilon = 1e-9);
+
+        // Along a meridian the great circle midpoint is halfway in latitude.
+        let midpoint = graph.edge_midpoint(1, 2).unwrap();
+        assert_relative_eq!(midpoint.x, 2., epsilon = 1e-9);
+        assert_relative_eq!(midpoint.y, 30., epsilon = 1e-9);
+
+        assert_eq!(graph.edge_midpoint(0, 2), None);
+        assert_eq!(graph.edge_midpoint(0, 3), None);
+    }
+
+    #[test]
+    fn nearest_edge() {
+        let mut graph: DirectedCsrGraph<f64, (f64, f64)> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.), (1, 2, 1.)]));
+        graph.node_values = vec![(0., 0.), (0.01, 0.), (0.01, 0.01)];
+        let mut graph = RTreeGraph::new_from_graph(graph);
+        let assert_nearest_edge = |graph: &RTreeGraph<_, _>, point, edge, closest: Coord| {
+            let (a, b, coord) = graph.nearest_edge(&point, 200.).unwrap();
+            assert_eq!((a, b), edge);
+            assert_relative_eq!(coord.x, closest.x, epsilon = 1e-7);
+            assert_relative_eq!(coord.y, closest.y, epsilon = 1e-7);
+        };
+
+        // About 110 m from the interior of the first edge and 460 m from the closest node.
+        let point = Coord { x: 0.004, y: 0.001 };
+        assert_eq!(graph.nearest_node(&point), Some(0));
+        assert_nearest_edge(&graph, point, (0, 1), Coord { x: 0.004, y: 0. });
+        assert_eq!(graph.nearest_edge(&point, 100.), None);
+
+        // Past the end of an edge the closest point is its endpoint.
+        let point = Coord {
+            x: -0.001,
+            y: -0.001,
+        };
+        assert_nearest_edge(&graph, point, (0, 1), Coord { x: 0., y: 0. });
+
+        // Changed edges are picked up.
+        graph.add_edge(0, 2, 1.);
+        let point = Coord { x: 0.003, y: 0.005 };
+        assert_nearest_edge(&graph, point, (0, 2), Coord { x: 0.004, y: 0.004 });
+    }
+
+    #[test]
+    fn bounding_rect() {
+        use geo::{BoundingRect, MultiPoint, Point};
+
+        let recomputed = |graph: &RTreeGraph<DirectedCsrGraph<f64, (f64, f64)>, f64>| {
+            MultiPoint::new(
+                graph
+                    .nodes_iter()
+                    .map(|(_, (x, y))| Point::new(*x, *y))
+                    .collect(),
+            )
+            .bounding_rect()
+        };
+
+        let mut graph: DirectedCsrGraph<f64, (f64, f64)> =
+            DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.), (1, 2, 1.)]));
+        graph.node_values = vec![(0., 0.), (2., 1.), (1., 3.)];
+        let mut graph = RTreeGraph::new_from_graph(graph);
+        let rect = |min: (f64, f64), max: (f64, f64)| Some(geo::Rect::new(min, max));
+
+        assert_eq!(graph.bounding_rect(), rect((0., 0.), (2., 3.)));
+        assert_eq!(graph.bounding_rect(), recomputed(&graph));
+
+        let node = graph.add_node((-1., 5.));
+        assert_eq!(graph.bounding_rect(), rect((-1., 0.), (2., 5.)));
+        assert_eq!(graph.bounding_rect(), recomputed(&graph));
+
+        graph.set_node_value(1, (4., -2.)).unwrap();
+        assert_eq!(graph.bounding_rect(), rect((-1., -2.), (4., 5.)));
+        assert_eq!(graph.nearest_node(&Coord { x: 4., y: -2. }), Some(1));
+
+        graph.remove_node(node);
+        assert_eq!(graph.bounding_rect(), rect((0., -2.), (4., 3.)));
+        assert_eq!(graph.bounding_rect(), recomputed(&graph));
+    }
+
+    #[test]
+    fn tree_stats() {
+        let tree_stats = |coords: Vec<(f64, f64)>| {
+            let graph: DirectedCsrGraph<f64, _> =
+                DirectedCsrGraph::from_edges_and_values(vec![], coords);
+            let graph: RTreeGraph<_, f64> = RTreeGraph::new_from_graph(graph);
+            graph.tree_stats()
+        };
+        let spread = |count: usize| {
+            (0..count)
+                .map(|i| ((i % 50) as f64, (i / 50) as f64))
+                .collect::<Vec<_>>()
+        };
+
+        let empty = tree_stats(vec![]);
+        assert_eq!(
+            empty,
+            TreeStats {
+                nodes: 1,
+                depth: 1,
+                leaf_occupancy: 0.,
+            }
+        );
+
+        let small = tree_stats(spread(4));
+        assert_eq!(small.depth, 1);
+        assert_eq!(small.leaf_occupancy, 4.);
+
+        let uniform = tree_stats(spread(1000));
+        assert!(uniform.depth > small.depth);
+        assert!(uniform.nodes > 1000 / 6);
+        assert!((1. ..=6.).contains(&uniform.leaf_occupancy));
+
+        // Half of the nodes in a tiny cluster do not make the balanced tree any deeper.
+        let mut clustered = spread(500);
+        clustered.extend((0..500).map(|i| (1e-6 * i as f64, 0.)));
+        let clustered = tree_stats(clustered);
+        assert_eq!(clustered.depth, uniform.depth);
+    }
+
+    #[test]
+    fn id_order() {
+        let edges = vec![(3, 0, 1.), (2, 1, 1.), (0, 3, 1.), (1, 2, 1.), (0, 1, 1.)];
+        let mut graph: DirectedCsrGraph<f64, (f64, f64)> =
+            DirectedCsrGraph::from(EdgeList::new(edges));
+        graph.node_values = vec![(3., 3.), (0., 0.), (2., 1.), (1., 2.)];
+        let graph = RTreeGraph::new_from_graph(graph);
+
+        let nodes: Vec<usize> = graph.nodes_iter().map(|(node, _)| node).collect();
+        assert_eq!(nodes, vec![0, 1, 2, 3]);
+        assert!(graph.edges().map(|(a, _)| a).is_sorted());
+
+        let envelope = geo::Rect::new(Coord { x: 0., y: 0. }, Coord { x: 3., y: 3. });
+        let mut located: Vec<usize> = graph.locate_in_envelope(&envelope).collect();
+        assert_eq!(graph.locate_in_envelope_sorted(&envelope), vec![0, 1, 2, 3]);
+        located.sort();
+        assert_eq!(located, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn empty_graph() {
+        let point = Coord { x: 13.4, y: 52.5 };
+        let graphs = [
+            RTreeGraph::new_from_graph(DirectedCsrGraph::<f64, (f64, f64)>::default()),
+            RTreeGraph::try_new_from_graph(DirectedCsrGraph::default()).unwrap(),
+            RTreeGraph::default(),
+        ];
+
+        for graph in graphs {
+            assert_eq!(graph.node_count(), 0);
+            assert_eq!(graph.nearest_node(&point), None);
+            assert_eq!(graph.nearest_node_bound(&point, 1.), None);
+            assert_eq!(graph.bounding_rect(), None);
+            let envelope = geo::Rect::new(Coord { x: 0., y: 0. }, Coord { x: 20., y: 60. });
+            assert_eq!(graph.locate_in_envelope(&envelope).count(), 0);
+        }
+    }
+
+    #[test]
+    fn dyn_graph() {
+        use crate::{DynCoordGraph, DynGraph};
+
+        let csr = || {
+            let mut graph: DirectedCsrGraph<f64, (f64, f64)> =
+                DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.), (1, 2, 2.)]));
+            graph.node_values = vec![(0., 0.), (1., 0.), (2., 0.)];
+            graph
+        };
+
+        let mut graphs: Vec<Box<dyn DynGraph<EdgeValue = f64, NodeValue = (f64, f64)>>> =
+            vec![Box::new(csr()), Box::new(RTreeGraph::new_from_graph(csr()))];
+
+        for graph in graphs.iter_mut() {
+            assert_eq!(graph.dyn_node_count(), 3);
+            assert_eq!(graph.dyn_edge_count(), 2);
+            assert_eq!(graph.dyn_neighbors(1).count(), 2);
+            assert_eq!(graph.dyn_node_value(2), Some(&(2., 0.)));
+
+            let node = graph.dyn_add_node((3., 0.));
+            assert!(graph.dyn_add_edge(2, node, 1.));
+            assert_eq!(graph.dyn_edge_count(), 3);
+            assert_eq!(graph.dyn_nodes_iter().count(), 4);
+        }
+
+        let graph: Box<dyn DynCoordGraph<EdgeValue = f64, NodeValue = (f64, f64), Scalar = f64>> =
+            Box::new(RTreeGraph::new_from_graph(csr()));
+        let point = Coord { x: 1.1, y: 0. };
+        assert_eq!(graph.dyn_nearest_node(&point), Some(1));
+        assert_eq!(graph.dyn_node_coord(1), Some(Coord { x: 1., y: 0. }));
+        let envelope = geo::Rect::new(Coord { x: 0.5, y: -1. }, Coord { x: 2.5, y: 1. });
+        let mut nodes: Vec<usize> = graph.dyn_locate_in_envelope(&envelope).collect();
+        nodes.sort_unstable();
+        assert_eq!(nodes, vec![1, 2]);
+        assert_eq!(graph.dyn_edge_count(), 2);
+    }
 }
@@ -0,0 +1,163 @@
+use std::cmp::Reverse;
+
+use log::debug;
+use num_traits::Zero;
+use ordered_float::{FloatCore, OrderedFloat};
+use priority_queue::PriorityQueue;
+use rustc_hash::{FxBuildHasher, FxHashMap, FxHashSet};
+
+use crate::{
+    DirectedGraph,
+    algorithms::dijkstra::{DijkstraResult, ResultNode},
+    graph::Target,
+};
+
+/// A [DirectedGraph] whose edge costs depend on the time a node is left.
+///
+/// `cost(node, edge, time)` returns the cost of leaving `node` over `edge` at `time`.
+/// An infinite cost means the edge can not be used at that time. The searches assume
+/// that leaving later never arrives earlier, which holds for schedules where waiting
+/// is part of the cost.
+pub struct TimeDependentGraph<G, F>
+where
+    G: DirectedGraph,
+{
+    graph: G,
+    cost: F,
+}
+
+impl<G, F> TimeDependentGraph<G, F>
+where
+    G: DirectedGraph,
+    G::EV: FloatCore,
+    F: Fn(usize, &Target<G::EV>, G::EV) -> G::EV,
+{
+    pub fn new(graph: G, cost: F) -> Self {
+        Self { graph, cost }
+    }
+
+    /// Returns the underlying graph.
+    pub fn graph(&self) -> &G {
+        &self.graph
+    }
+
+    /// Runs Dijkstra from `start_node` leaving at `start_time` until all `target_set`
+    /// nodes are settled.
+    ///
+    /// The cost of a result node is the travel time from `start_node`, so its arrival
+    /// time is `start_time + cost`. With a cost function returning the edge value this is
+    /// the same as the static Dijkstra.
+    pub fn dijkstra_time_dependent(
+        &self,
+        start_node: usize,
+        start_time: G::EV,
+        mut target_set: FxHashSet<usize>,
+    ) -> DijkstraResult<G::EV> {
+        let mut frontier = PriorityQueue::with_hasher(FxBuildHasher);
+        let mut best: FxHashMap<usize, (G::EV, Option<usize>)> = FxHashMap::default();
+        let mut result = FxHashSet::default();
+        let mut visited = FxHashSet::default();
+        best.insert(start_node, (G::EV::zero(), None));
+        frontier.push(start_node, Reverse(OrderedFloat(G::EV::zero())));
+
+        while !target_set.is_empty()
+            && let Some((node_id, _)) = frontier.pop()
+        {
+            visited.insert(node_id);
+            let (node_cost, predecessor) = best[&node_id];
+            let node = ResultNode::new(Target::new(node_id, node_cost), predecessor);
+
+            let time = start_time + node_cost;
+            for edge in self.graph.out_neighbors(node_id) {
+                let cost = (self.cost)(node_id, edge, time);
+                if cost.is_infinite() || visited.contains(&edge.target()) {
+                    continue;
+                }
+
+                let path_cost = node_cost + cost;
+                if best.get(&edge.target()).is_none_or(|(c, _)| path_cost < *c) {
+                    best.insert(edge.target(), (path_cost, Some(node_id)));
+                    frontier.push_increase(edge.target(), Reverse(OrderedFloat(path_cost)));
+                }
+            }
+
+            target_set.remove(&node_id);
+            result.insert(node);
+        }
+
+        if !target_set.is_empty() {
+            debug!("could not find a path to these nodes: {:?}", target_set);
+        }
+
+        DijkstraResult::new(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rustc_hash::FxHashSet;
+
+    use crate::{
+        Graph,
+        algorithms::dijkstra::Dijkstra,
+        graph::{Target, csr::DirectedCsrGraph},
+        input::edgelist::EdgeList,
+        types::Direction,
+    };
+
+    use super::TimeDependentGraph;
+
+    fn setup() -> DirectedCsrGraph<f64, ()> {
+        DirectedCsrGraph::from(EdgeList::new(vec![(0, 1, 1.), (1, 2, 1.), (0, 2, 4.)]))
+    }
+
+    #[test]
+    fn two_phase_cost() {
+        // After time 10 the edges over node 1 are congested.
+        let graph = TimeDependentGraph::new(setup(), |node, edge: &Target<f64>, time| {
+            match (node, edge.target()) {
+                (0, 1) if time >= 10. => 5.,
+                (1, 2) if time >= 2. => 10.,
+                _ => *edge.value(),
+            }
+        });
+        let targets = FxHashSet::from_iter([2]);
+
+        let result = graph.dijkstra_time_dependent(0, 0., targets.clone());
+        let node = result.get(2).unwrap();
+        assert_eq!(*node.cost(), 2.);
+        assert_eq!(node.prev_node_id(), Some(1));
+
+        let result = graph.dijkstra_time_dependent(0, 10., targets);
+        let node = result.get(2).unwrap();
+        assert_eq!(*node.cost(), 4.);
+        assert_eq!(node.prev_node_id(), Some(0));
+    }
+
+    #[test]
+    fn cheaper_path_found_later() {
+        // Node 2 is first reached directly at cost 4, then over node 1 at cost 2.
+        let graph = TimeDependentGraph::new(setup(), |_, edge: &Target<f64>, _| *edge.value());
+
+        let result = graph.dijkstra_time_dependent(0, 0., FxHashSet::from_iter([2]));
+        let node = result.get(2).unwrap();
+        assert_eq!(*node.cost(), 2.);
+        assert_eq!(node.prev_node_id(), Some(1));
+    }
+
+    #[test]
+    fn static_special_case() {
+        let graph = TimeDependentGraph::new(setup(), |_, edge: &Target<f64>, _| *edge.value());
+        let all_nodes = FxHashSet::from_iter(0..graph.graph().node_count());
+
+        let result = graph.dijkstra_time_dependent(0, 42., all_nodes);
+        let expected = graph.graph().dijkstra_full(0, Direction::Outgoing);
+
+        for node in expected.0.iter() {
+            assert_eq!(
+                result.get(node.node_id()).map(|n| *n.cost()),
+                Some(*node.cost())
+            );
+        }
+    }
+}
@@ -6,7 +6,6 @@ use serde::{Deserialize, Serialize};
 
 use core::f64;
 use std::{
-    cmp::Ordering,
     collections::{self, HashMap},
     hash::Hash,
     io::Read,
@@ -15,7 +14,7 @@ use std::{
     ops::Deref,
 };
 
-use geo::{EuclideanDistance, GeodesicDistance, HaversineDistance, point};
+use geo::{GeodesicDistance, HaversineDistance, point};
 use geozero::{
     ColumnValue, FeatureProcessor, GeomProcessor, PropertyProcessor, error::GeozeroError,
     geojson::GeoJson,
@@ -23,8 +22,6 @@ use geozero::{
 
 use crate::{Coordinate, DirectedGraph, Graph, coord, graph::csr::DirectedCsrGraph};
 
-use super::edgelist::EdgeList;
-
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Coord<T: CoordNum = f64> {
     pub x: T,
@@ -119,11 +116,9 @@ where
     }
 
     pub fn get_graph(&mut self) -> DirectedCsrGraph<f64, Coord> {
-        let edge_list = EdgeList::new(mem::take(&mut self.edges));
-
-        let graph: DirectedCsrGraph<_, ()> = DirectedCsrGraph::from(edge_list);
+        let edges = mem::take(&mut self.edges);
 
-        DirectedCsrGraph::new(mem::take(&mut self.nodes), graph.csr_out, graph.csr_inc)
+        DirectedCsrGraph::from_edges_and_values(edges.iter().copied(), mem::take(&mut self.nodes))
     }
 }
 
@@ -303,17 +298,6 @@ impl Clone for ColumnValueClonable {
     }
 }
 
-fn cmp_points(a: &Point, b: &Point) -> Ordering {
-    let d = a.euclidean_distance(b);
-
-    match d {
-        x if x < 0.0_f64 => Ordering::Less,
-        x if x == 0.0_f64 => Ordering::Equal,
-        x if x > 0.0_f64 => Ordering::Greater,
-        _ => unreachable!(),
-    }
-}
-
 fn to_ord_coord(&coord: &Coord) -> Coord<OrderedFloat<f64>> {
     coord! {x: OrderedFloat(coord.x), y: OrderedFloat(coord.y)}
 }
@@ -1,10 +1,10 @@
 #![feature(specialization)]
 use std::{error::Error, fmt::Display};
 
-use ::geo_types::{Coord, CoordNum};
-use geo::Rect;
+use ::geo_types::{Coord, CoordFloat, CoordNum, Point};
+use geo::{HaversineIntermediate, Rect};
 use graph::Target;
-use num_traits::Num;
+use num_traits::{FromPrimitive, Num};
 
 pub use geozero::{FeatureProcessor, GeomProcessor, PropertyProcessor};
 
@@ -26,6 +26,20 @@ pub mod types;
 pub enum GraphError {
     NodeNotFound(usize),
     EmptyNode(usize),
+    /// The offsets of a CSR are not monotonic at the given index or do not span its targets.
+    InvalidOffsets(usize),
+    TargetOutOfRange {
+        node: usize,
+        target: usize,
+    },
+    NodeCountMismatch {
+        expected: usize,
+        found: usize,
+    },
+    /// A saved Dijkstra cache belongs to a different graph.
+    CacheMismatch,
+    /// Reading or writing a saved Dijkstra cache failed.
+    CacheIo(String),
 }
 
 impl Error for GraphError {}
@@ -35,6 +49,15 @@ impl Display for GraphError {
         match self {
             Self::NodeNotFound(node) => write!(f, "node_id: {node} not found in graph"),
             Self::EmptyNode(node) => write!(f, "node \'{node}\' has no acociated value"),
+            Self::InvalidOffsets(index) => write!(f, "invalid csr offset at index {index}"),
+            Self::TargetOutOfRange { node, target } => {
+                write!(f, "edge ({node}, {target}) points outside of the graph")
+            }
+            Self::NodeCountMismatch { expected, found } => {
+                write!(f, "expected {expected} nodes, found {found}")
+            }
+            Self::CacheMismatch => write!(f, "dijkstra cache was saved for a different graph"),
+            Self::CacheIo(err) => write!(f, "could not access dijkstra cache: {err}"),
         }
     }
 }
@@ -73,12 +96,35 @@ pub trait Graph: Default {
     where
         Self::EV: 'a;
 
+    /// Returns every edge once. Edges are ordered by their first node.
     fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_;
 
     fn degree(&self, node: usize) -> usize;
 
     fn node_value(&self, node: usize) -> Option<&Self::NV>;
 
+    /// Like [Graph::node_value], but returns [GraphError::NodeNotFound] if `node` has no value.
+    fn try_node_value(&self, node: usize) -> Result<&Self::NV, GraphError> {
+        self.node_value(node).ok_or(GraphError::NodeNotFound(node))
+    }
+
+    /// Like [Graph::neighbors], but returns [GraphError::NodeNotFound] if `node` is not in the
+    /// graph.
+    fn try_neighbors<'a>(
+        &'a self,
+        node: usize,
+    ) -> Result<impl Iterator<Item = &'a Target<Self::EV>>, GraphError>
+    where
+        Self::EV: 'a,
+    {
+        if node < self.node_count() {
+            Ok(self.neighbors(node))
+        } else {
+            Err(GraphError::NodeNotFound(node))
+        }
+    }
+
+    /// Returns all nodes with their values, ordered by id.
     fn nodes_iter<'a>(&'a self) -> impl Iterator<Item = (usize, &'a Self::NV)>
     where
         Self::NV: 'a;
@@ -96,6 +142,189 @@ pub trait Graph: Default {
     fn remove_edge(&mut self, edge: (usize, usize)) -> Option<Self::EV>;
 }
 
+/// Object safe version of [Graph], implemented for every [Graph].
+///
+/// Graphs with different index structures can be held as
+/// `Box<dyn DynGraph<EdgeValue = _, NodeValue = _>>` and picked at runtime. Iterators are
+/// boxed. The methods carry a `dyn_` prefix and the associated types other names, so they do
+/// not clash with [Graph] on concrete graphs.
+pub trait DynGraph {
+    type EdgeValue;
+    type NodeValue;
+    fn dyn_node_count(&self) -> usize;
+
+    fn dyn_edge_count(&self) -> usize;
+
+    fn dyn_neighbors<'a>(
+        &'a self,
+        node: usize,
+    ) -> Box<dyn Iterator<Item = &'a Target<Self::EdgeValue>> + 'a>
+    where
+        Self::EdgeValue: 'a;
+
+    fn dyn_edges(&self) -> Box<dyn Iterator<Item = (usize, usize)> + '_>;
+
+    fn dyn_degree(&self, node: usize) -> usize;
+
+    fn dyn_node_value(&self, node: usize) -> Option<&Self::NodeValue>;
+
+    fn dyn_nodes_iter<'a>(&'a self) -> Box<dyn Iterator<Item = (usize, &'a Self::NodeValue)> + 'a>
+    where
+        Self::NodeValue: 'a;
+
+    fn dyn_node_value_mut(&mut self, node: usize) -> Option<&mut Self::NodeValue>;
+
+    fn dyn_set_node_value(&mut self, node: usize, value: Self::NodeValue)
+    -> Result<(), GraphError>;
+
+    fn dyn_add_node(&mut self, weight: Self::NodeValue) -> usize;
+
+    fn dyn_add_edge(&mut self, a: usize, b: usize, weight: Self::EdgeValue) -> bool;
+
+    fn dyn_remove_node(&mut self, node: usize) -> Option<Self::NodeValue>;
+
+    fn dyn_remove_edge(&mut self, edge: (usize, usize)) -> Option<Self::EdgeValue>;
+}
+
+impl<G: Graph> DynGraph for G {
+    type EdgeValue = G::EV;
+    type NodeValue = G::NV;
+
+    fn dyn_node_count(&self) -> usize {
+        Graph::node_count(self)
+    }
+
+    fn dyn_edge_count(&self) -> usize {
+        Graph::edge_count(self)
+    }
+
+    fn dyn_neighbors<'a>(
+        &'a self,
+        node: usize,
+    ) -> Box<dyn Iterator<Item = &'a Target<Self::EdgeValue>> + 'a>
+    where
+        Self::EdgeValue: 'a,
+    {
+        Box::new(Graph::neighbors(self, node))
+    }
+
+    fn dyn_edges(&self) -> Box<dyn Iterator<Item = (usize, usize)> + '_> {
+        Box::new(Graph::edges(self))
+    }
+
+    fn dyn_degree(&self, node: usize) -> usize {
+        Graph::degree(self, node)
+    }
+
+    fn dyn_node_value(&self, node: usize) -> Option<&Self::NodeValue> {
+        Graph::node_value(self, node)
+    }
+
+    fn dyn_nodes_iter<'a>(&'a self) -> Box<dyn Iterator<Item = (usize, &'a Self::NodeValue)> + 'a>
+    where
+        Self::NodeValue: 'a,
+    {
+        Box::new(Graph::nodes_iter(self))
+    }
+
+    fn dyn_node_value_mut(&mut self, node: usize) -> Option<&mut Self::NodeValue> {
+        Graph::node_value_mut(self, node)
+    }
+
+    fn dyn_set_node_value(
+        &mut self,
+        node: usize,
+        value: Self::NodeValue,
+    ) -> Result<(), GraphError> {
+        Graph::set_node_value(self, node, value)
+    }
+
+    fn dyn_add_node(&mut self, weight: Self::NodeValue) -> usize {
+        Graph::add_node(self, weight)
+    }
+
+    fn dyn_add_edge(&mut self, a: usize, b: usize, weight: Self::EdgeValue) -> bool {
+        Graph::add_edge(self, a, b, weight)
+    }
+
+    fn dyn_remove_node(&mut self, node: usize) -> Option<Self::NodeValue> {
+        Graph::remove_node(self, node)
+    }
+
+    fn dyn_remove_edge(&mut self, edge: (usize, usize)) -> Option<Self::EdgeValue> {
+        Graph::remove_edge(self, edge)
+    }
+}
+
+/// Object safe version of [CoordGraph], implemented for every [CoordGraph].
+///
+/// Lets spatial queries go through a
+/// `Box<dyn DynCoordGraph<EdgeValue = _, NodeValue = _, Scalar = _>>`, see [DynGraph].
+pub trait DynCoordGraph: DynGraph {
+    type Scalar: CoordNum;
+    fn dyn_node_coord(&self, node: usize) -> Option<Coord<Self::Scalar>>;
+
+    fn dyn_nearest_node(&self, point: &Coord<Self::Scalar>) -> Option<usize>;
+
+    fn dyn_nearest_node_bound(
+        &self,
+        point: &Coord<Self::Scalar>,
+        tolerance: Self::Scalar,
+    ) -> Option<usize>;
+
+    fn dyn_locate_in_envelope<'a>(
+        &'a self,
+        envelope: &'a Rect<Self::Scalar>,
+    ) -> Box<dyn Iterator<Item = usize> + 'a>;
+
+    fn dyn_bounding_rect(&self) -> Option<Rect<Self::Scalar>>;
+
+    fn dyn_nearest_edge(
+        &self,
+        point: &Coord<Self::Scalar>,
+        tolerance: Self::Scalar,
+    ) -> Option<(usize, usize, Coord<Self::Scalar>)>;
+}
+
+impl<G: CoordGraph> DynCoordGraph for G {
+    type Scalar = G::C;
+
+    fn dyn_node_coord(&self, node: usize) -> Option<Coord<Self::Scalar>> {
+        CoordGraph::node_coord(self, node)
+    }
+
+    fn dyn_nearest_node(&self, point: &Coord<Self::Scalar>) -> Option<usize> {
+        CoordGraph::nearest_node(self, point)
+    }
+
+    fn dyn_nearest_node_bound(
+        &self,
+        point: &Coord<Self::Scalar>,
+        tolerance: Self::Scalar,
+    ) -> Option<usize> {
+        CoordGraph::nearest_node_bound(self, point, tolerance)
+    }
+
+    fn dyn_locate_in_envelope<'a>(
+        &'a self,
+        envelope: &'a Rect<Self::Scalar>,
+    ) -> Box<dyn Iterator<Item = usize> + 'a> {
+        Box::new(CoordGraph::locate_in_envelope(self, envelope))
+    }
+
+    fn dyn_bounding_rect(&self) -> Option<Rect<Self::Scalar>> {
+        CoordGraph::bounding_rect(self)
+    }
+
+    fn dyn_nearest_edge(
+        &self,
+        point: &Coord<Self::Scalar>,
+        tolerance: Self::Scalar,
+    ) -> Option<(usize, usize, Coord<Self::Scalar>)> {
+        CoordGraph::nearest_edge(self, point, tolerance)
+    }
+}
+
 pub trait DirectedGraph: Graph {
     fn out_neighbors<'a>(&'a self, node: usize) -> impl Iterator<Item = &'a Target<Self::EV>>
     where
@@ -116,9 +345,48 @@ pub trait CoordGraph: Graph {
 
     fn nearest_node(&self, point: &Coord<Self::C>) -> Option<usize>;
 
+    /// Like [CoordGraph::nearest_node], but only if the node is within `tolerance` of `point`.
+    ///
+    /// Graphs with longitude and latitude coordinates measure distances and `tolerance` in
+    /// meters.
     fn nearest_node_bound(&self, point: &Coord<Self::C>, tolerance: Self::C) -> Option<usize>;
 
+    /// Returns the nodes in `envelope`, in the order of the spatial index.
     fn locate_in_envelope(&self, envelope: &Rect<Self::C>) -> impl Iterator<Item = usize>;
 
+    /// Like [CoordGraph::locate_in_envelope], but ordered by id.
+    fn locate_in_envelope_sorted(&self, envelope: &Rect<Self::C>) -> Vec<usize> {
+        let mut nodes: Vec<usize> = self.locate_in_envelope(envelope).collect();
+        nodes.sort_unstable();
+        nodes
+    }
+
     fn bounding_rect(&self) -> Option<Rect<Self::C>>;
+
+    /// Returns the edge closest to `point` within `tolerance`, as its nodes and the point on
+    /// the edge closest to `point`.
+    ///
+    /// Distances and `tolerance` are measured like in [CoordGraph::nearest_node_bound].
+    fn nearest_edge(
+        &self,
+        point: &Coord<Self::C>,
+        tolerance: Self::C,
+    ) -> Option<(usize, usize, Coord<Self::C>)>;
+
+    /// Returns the point halfway along the great circle between `a` and `b`, with node
+    /// coordinates as longitude and latitude.
+    ///
+    /// Returns `None` if `a` and `b` are not neighbors or a node has no coordinate.
+    fn edge_midpoint(&self, a: usize, b: usize) -> Option<Coord<Self::C>>
+    where
+        Self::C: CoordFloat + FromPrimitive,
+    {
+        if !self.neighbors(a).any(|target| target.target() == b) {
+            return None;
+        }
+
+        let a = Point::from(self.node_coord(a)?);
+        let b = Point::from(self.node_coord(b)?);
+        Some(a.haversine_intermediate(&b, Self::C::from_f64(0.5)?).into())
+    }
 }
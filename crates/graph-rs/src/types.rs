@@ -1,15 +1,26 @@
 use geo::{Coord, CoordNum};
 use num_traits::{Num, NumOps};
+use serde::{Deserialize, Serialize};
 
 use crate::{CoordGraph, graph::Target};
 
-#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Direction {
     Outgoing,
     Incoming,
     Undirected,
 }
 
+/// Kind of way an edge was imported from, so queries can be restricted to one mode of
+/// transport. See [DirectedCsrGraph::edge_class](crate::graph::csr::DirectedCsrGraph::edge_class).
+#[derive(PartialEq, Eq, Hash, Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub enum EdgeClass {
+    #[default]
+    Road,
+    Cycleway,
+    Footpath,
+}
+
 pub struct CoordNode<C, NV>
 where
     C: CoordNum,
@@ -0,0 +1,85 @@
+//! Compares [Dijkstra::dijkstra_full] of [DirectedCsrGraph] with petgraph's Dijkstra on random
+//! graphs.
+
+use graph_rs::{
+    Graph, algorithms::dijkstra::Dijkstra, graph::csr::DirectedCsrGraph, input::edgelist::EdgeList,
+    types::Direction,
+};
+use petgraph::graph::{DiGraph, NodeIndex};
+use rand::{Rng, SeedableRng, rngs::SmallRng};
+
+/// Random edges with integer weights, so path costs are exact in both implementations.
+fn random_edges(
+    rng: &mut SmallRng,
+    node_count: usize,
+    edge_count: usize,
+) -> Vec<(usize, usize, f64)> {
+    (0..edge_count)
+        .map(|_| {
+            (
+                rng.random_range(0..node_count),
+                rng.random_range(0..node_count),
+                rng.random_range(1..100) as f64,
+            )
+        })
+        .collect()
+}
+
+fn to_petgraph(edges: &[(usize, usize, f64)]) -> DiGraph<(), f64> {
+    DiGraph::from_edges(
+        edges
+            .iter()
+            .map(|&(a, b, weight)| (a as u32, b as u32, weight)),
+    )
+}
+
+/// Asserts that every source has the same costs in `graph` and `expected`.
+fn assert_same_costs(graph: &DirectedCsrGraph<f64, ()>, expected: &DiGraph<(), f64>) {
+    assert_eq!(graph.node_count(), expected.node_count());
+
+    for source in 0..graph.node_count() {
+        let result = graph.dijkstra_full(source, Direction::Outgoing);
+        let expected =
+            petgraph::algo::dijkstra(expected, NodeIndex::new(source), None, |e| *e.weight());
+
+        for node in 0..graph.node_count() {
+            assert_eq!(
+                result.cost(node),
+                expected.get(&NodeIndex::new(node)).copied(),
+                "cost from {source} to {node}"
+            );
+        }
+    }
+}
+
+#[test]
+fn random_graphs() {
+    let mut rng = SmallRng::seed_from_u64(1337);
+
+    for (node_count, edge_count) in [(2, 1), (10, 15), (50, 100), (100, 400), (200, 300)] {
+        let edges = random_edges(&mut rng, node_count, edge_count);
+        let graph: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(edges.clone()));
+        let expected = to_petgraph(&edges);
+
+        assert_same_costs(&graph, &expected);
+        // The second round is served from the Dijkstra cache.
+        assert_same_costs(&graph, &expected);
+    }
+}
+
+#[test]
+fn recomputed_weights() {
+    let mut rng = SmallRng::seed_from_u64(42);
+    let edges = random_edges(&mut rng, 50, 150);
+    let mut graph: DirectedCsrGraph<f64, ()> = DirectedCsrGraph::from(EdgeList::new(edges.clone()));
+    assert_same_costs(&graph, &to_petgraph(&edges));
+
+    // Costs cached before the change must not be served afterwards.
+    let weight = |from: usize, to: usize, weight: f64| weight + ((from * 7 + to) % 5) as f64;
+    graph.recompute_edge_weights(|from, to, &w| weight(from, to, w));
+    let edges: Vec<_> = edges
+        .into_iter()
+        .map(|(from, to, w)| (from, to, weight(from, to, w)))
+        .collect();
+    assert_same_costs(&graph, &to_petgraph(&edges));
+}
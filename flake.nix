@@ -182,6 +182,23 @@
             }
           );
 
+          # Build the graph core on its own, without rayon and the r-tree index
+          graph-rs-no-default-features = craneLib.cargoBuild (
+            commonArgs
+            // {
+              inherit cargoArtifacts;
+              cargoExtraArgs = "-p graph-rs --no-default-features";
+            }
+          );
+
+          graph-rs-cache-snapshot = craneLib.cargoNextest (
+            commonArgs
+            // {
+              inherit cargoArtifacts;
+              cargoExtraArgs = "-p graph-rs --features cache-snapshot";
+            }
+          );
+
           my-workspace-doc = craneLib.cargoDoc (commonArgs // { inherit cargoArtifacts; });
 
           # Check formatting